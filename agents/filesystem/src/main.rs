@@ -8,7 +8,7 @@ use notify::{
 };
 use rpc_support::{rpc_error::RpcError, DefaultRawRpcClient, RawRpcClient};
 use std::{error::Error, path::PathBuf, time::SystemTime};
-use tokio::net::TcpStream;
+use uuid::Uuid;
 
 async fn send_event<TRawRpcClient: RawRpcClient + Send + Sync>(
     client: &mut Client<TRawRpcClient>,
@@ -51,11 +51,9 @@ pub async fn main_inner() -> Result<(), Box<dyn Error>> {
         .watch(&path, notify::RecursiveMode::Recursive)
         .unwrap();
 
-    let raw_rpc_client = DefaultRawRpcClient::new(
-        TcpStream::connect(matches.get_one::<String>("url").unwrap())
-            .await
-            .unwrap(),
-    );
+    let raw_rpc_client = DefaultRawRpcClient::connect(matches.get_one::<String>("url").unwrap())
+        .await
+        .unwrap();
     let mut client = Client::new(raw_rpc_client);
 
     let mut walkdir = WalkDir::new(path.clone());
@@ -71,7 +69,7 @@ pub async fn main_inner() -> Result<(), Box<dyn Error>> {
         send_event(
             &mut client,
             FilesystemEvent {
-                kind: lib_directory_watcher::FilesystemEventKind::Created {},
+                kind: lib_directory_watcher::FilesystemEventKind::Created,
                 mount_id: mount_id.clone(),
                 path: make_path_relative(&path, &entry.path())
                     .to_string_lossy()
@@ -81,10 +79,8 @@ pub async fn main_inner() -> Result<(), Box<dyn Error>> {
                     .await
                     .unwrap()
                     .modified()
-                    .unwrap()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs(),
+                    .unwrap(),
+                idempotency_key: Some(Uuid::new_v4()),
             },
         )
         .await
@@ -100,7 +96,7 @@ pub async fn main_inner() -> Result<(), Box<dyn Error>> {
                     send_event(
                         &mut client,
                         FilesystemEvent {
-                            kind: lib_directory_watcher::FilesystemEventKind::Created {},
+                            kind: lib_directory_watcher::FilesystemEventKind::Created,
                             mount_id: mount_id.clone(),
                             path: make_path_relative(&path, &current_path)
                                 .to_string_lossy()
@@ -108,10 +104,8 @@ pub async fn main_inner() -> Result<(), Box<dyn Error>> {
                             timestamp: std::fs::metadata(current_path)
                                 .unwrap()
                                 .modified()
-                                .unwrap()
-                                .duration_since(SystemTime::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs(),
+                                .unwrap(),
+                            idempotency_key: Some(Uuid::new_v4()),
                         },
                     )
                     .await
@@ -121,20 +115,19 @@ pub async fn main_inner() -> Result<(), Box<dyn Error>> {
             notify::EventKind::Remove(RemoveKind::File)
             | notify::EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
                 let timestamp = std::fs::metadata(&path)
-                    .map_or_else(|_| std::time::SystemTime::now(), |x| x.modified().unwrap())
-                    .duration_since(SystemTime::UNIX_EPOCH)?
-                    .as_secs();
+                    .map_or_else(|_| SystemTime::now(), |x| x.modified().unwrap());
 
                 for current_path in event.paths {
                     send_event(
                         &mut client,
                         FilesystemEvent {
-                            kind: lib_directory_watcher::FilesystemEventKind::Deleted {},
+                            kind: lib_directory_watcher::FilesystemEventKind::Deleted,
                             mount_id: mount_id.clone(),
                             path: make_path_relative(&path, &current_path)
                                 .to_string_lossy()
                                 .to_string(),
                             timestamp,
+                            idempotency_key: Some(Uuid::new_v4()),
                         },
                     )
                     .await
@@ -157,10 +150,8 @@ pub async fn main_inner() -> Result<(), Box<dyn Error>> {
                         timestamp: std::fs::metadata(&event.paths[1])
                             .unwrap()
                             .modified()
-                            .unwrap()
-                            .duration_since(SystemTime::UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs(),
+                            .unwrap(),
+                        idempotency_key: Some(Uuid::new_v4()),
                     },
                 )
                 .await
@@ -171,7 +162,7 @@ pub async fn main_inner() -> Result<(), Box<dyn Error>> {
                     client
                         .file_changed(
                             FilesystemEvent {
-                                kind: lib_directory_watcher::FilesystemEventKind::Modified {},
+                                kind: lib_directory_watcher::FilesystemEventKind::Modified,
                                 mount_id: mount_id.clone(),
                                 path: make_path_relative(&path, &current_path)
                                     .to_string_lossy()
@@ -179,10 +170,8 @@ pub async fn main_inner() -> Result<(), Box<dyn Error>> {
                                 timestamp: std::fs::metadata(current_path)
                                     .unwrap()
                                     .modified()
-                                    .unwrap()
-                                    .duration_since(SystemTime::UNIX_EPOCH)
-                                    .unwrap()
-                                    .as_secs(),
+                                    .unwrap(),
+                                idempotency_key: Some(Uuid::new_v4()),
                             },
                             Metadata {},
                         )
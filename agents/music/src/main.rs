@@ -1,13 +1,14 @@
 use futures::{AsyncReadExt, StreamExt, TryStreamExt};
-use music::{Client, Metadata, RpcClient, StreamTrackRequest};
-use rpc_support::{DefaultRawRpcClient, RawRpcClient};
+use music::playback::{Client, RpcClient};
+use music::{Metadata, StreamTrackRequest};
+use rpc_support::DefaultRawRpcClient;
 use std::io::Cursor;
 use tokio::net::TcpStream;
 
 // todo this is inefficient, as it loads the whole file in memory
-async fn read_track<TRawRpcClient: RawRpcClient + Send + Sync>(
+async fn read_track(
     track_id: uuid::Uuid,
-    client: &mut Client<TRawRpcClient>,
+    client: &mut impl RpcClient,
 ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     let mut vec = vec![];
 
@@ -58,66 +59,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 #[cfg(test)]
 mod tests {
-    use rpc_support::{rpc_error::RpcError, ResponseStream};
-    use serde::{de::DeserializeOwned, Serialize};
+    use music::playback::testing::MockRpcClient;
+    use music::TrackData;
     use uuid::Uuid;
 
     use super::*;
 
-    struct MockRawRpcClient {
-        response_stream: Box<dyn Fn() -> ResponseStream<String> + Send + Sync>,
-    }
-
-    #[async_trait::async_trait]
-    impl RawRpcClient for MockRawRpcClient {
-        async fn send_rpc<TRequest, TMetadata, TResponse>(
-            &mut self,
-            _id: u64,
-            _method_name: &str,
-            _request: &TRequest,
-            _metadata: &TMetadata,
-        ) -> Result<TResponse, RpcError>
-        where
-            TRequest: Serialize + Sync + Send,
-            TMetadata: Serialize + Sync + Send,
-            TResponse: DeserializeOwned,
-        {
-            todo!();
-        }
-
-        async fn send_rpc_stream_request<TRequest, TMetadata, TResponse>(
-            &mut self,
-            _request_id: u64,
-            _method_name: &str,
-            _request: &TRequest,
-            _metadata: &TMetadata,
-        ) -> Result<ResponseStream<TResponse>, RpcError>
-        where
-            TRequest: Serialize + Sync + Send,
-            TMetadata: Serialize + Sync + Send,
-            TResponse: DeserializeOwned,
-        {
-            Ok(Box::pin((self.response_stream)().map(move |x| {
-                Ok::<_, RpcError>(serde_json::from_str(&x?)?)
-            })))
-        }
-    }
-
     #[tokio::test]
     async fn test_read_track() {
-        let mut client = Client::new(MockRawRpcClient {
-            response_stream: Box::new(|| {
-                Box::pin(
-                    async_stream::stream! {
-                        yield Ok("{\"data\": [1,2,3,4,5]}".to_string());
-                    }
-                    .boxed(),
-                )
+        let mut client = MockRpcClient::default();
+        client.stream_track.expect(Ok(vec![
+            Ok(TrackData {
+                data: vec![1, 2, 3],
             }),
-        });
+            Ok(TrackData { data: vec![4, 5] }),
+        ]));
+        let track_id = Uuid::new_v4();
 
-        let result = read_track(Uuid::new_v4(), &mut client).await.unwrap();
+        let result = read_track(track_id, &mut client).await.unwrap();
 
         assert_eq!(result, vec![1, 2, 3, 4, 5]);
+        assert_eq!(client.stream_track.calls()[0].0.track_id, track_id);
+        client.verify();
     }
 }
@@ -0,0 +1,124 @@
+use crate::formatted::Formatted;
+use serde::de::{Error, Visitor};
+use serde::{Deserializer, Serializer};
+use std::fmt::Formatter;
+use time::{Date, Month};
+
+/// Calendar dates as `"YYYY-MM-DD"` strings, as in ISO 8601
+pub struct Iso8601;
+
+struct DateVisitor;
+
+impl<'de> Visitor<'de> for DateVisitor {
+    type Value = Date;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        write!(formatter, "a date formatted as YYYY-MM-DD")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        let invalid = || E::invalid_value(serde::de::Unexpected::Str(v), &self);
+
+        // The year can be negative, so the string is split from the end
+        let mut parts = v.rsplitn(3, '-');
+        let (Some(day), Some(month), Some(year)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+
+        let year = year.parse().map_err(|_| invalid())?;
+        let month = month
+            .parse::<u8>()
+            .ok()
+            .and_then(|month| Month::try_from(month).ok())
+            .ok_or_else(invalid)?;
+        let day = day.parse().map_err(|_| invalid())?;
+
+        Date::from_calendar_date(year, month, day).map_err(|_| invalid())
+    }
+}
+
+impl Formatted<Iso8601> for Date {
+    fn serialize_formatted<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&format!(
+            "{:04}-{:02}-{:02}",
+            self.year(),
+            u8::from(self.month()),
+            self.day()
+        ))
+    }
+
+    fn deserialize_formatted<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(DateVisitor)
+    }
+}
+
+/// # Errors
+/// Can fail if the serializer fails
+pub fn serialize<T, S>(val: &T, ser: S) -> Result<S::Ok, S::Error>
+where
+    T: Formatted<Iso8601>,
+    S: Serializer,
+{
+    val.serialize_formatted(ser)
+}
+
+/// # Errors
+/// Can fail if a value is not a valid date formatted as `YYYY-MM-DD`
+pub fn deserialize<'de, T, D>(des: D) -> Result<T, D::Error>
+where
+    T: Formatted<Iso8601>,
+    D: Deserializer<'de>,
+{
+    T::deserialize_formatted(des)
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+    use time::{Date, Month};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Dates {
+        #[serde(with = "crate::date_serializer")]
+        date: Date,
+        #[serde(with = "crate::date_serializer", default)]
+        optional: Option<Date>,
+        #[serde(with = "crate::date_serializer")]
+        nested: BTreeMap<String, Vec<Date>>,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let date = Date::from_calendar_date(2023, Month::June, 5).unwrap();
+        let dates = Dates {
+            date,
+            optional: None,
+            nested: BTreeMap::from([("a".to_string(), vec![date])]),
+        };
+
+        let json = serde_json::to_string(&dates).unwrap();
+
+        assert_eq!(
+            json,
+            "{\"date\":\"2023-06-05\",\"optional\":null,\"nested\":{\"a\":[\"2023-06-05\"]}}"
+        );
+        assert_eq!(serde_json::from_str::<Dates>(&json).unwrap(), dates);
+    }
+
+    #[test]
+    fn test_rejects_invalid_dates() {
+        assert!(serde_json::from_str::<Dates>("{\"date\":\"2023-02-30\",\"nested\":{}}").is_err());
+        assert!(serde_json::from_str::<Dates>("{\"date\":\"2023-06\",\"nested\":{}}").is_err());
+    }
+}
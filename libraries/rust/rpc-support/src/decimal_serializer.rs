@@ -0,0 +1,125 @@
+use crate::formatted::Formatted;
+use rust_decimal::Decimal;
+use serde::de::{Error, Visitor};
+use serde::{Deserializer, Serializer};
+use std::fmt::Formatter;
+
+/// Decimal numbers as strings of digits with an optional sign and fraction, like `"-12.50"`, so that
+/// they are not rounded to the nearest float by the JSON parsers of other languages. The digits after
+/// the point are kept, `"1.50"` and `"1.5"` are the same number with a different scale.
+pub struct DecimalString;
+
+struct DecimalVisitor;
+
+// Exponents and the underscores that `Decimal` accepts are left out, so that every language can parse
+// the numbers with a simple pattern
+fn is_plain_decimal(value: &str) -> bool {
+    let unsigned = value.strip_prefix('-').unwrap_or(value);
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, "0"));
+
+    [whole, fraction]
+        .iter()
+        .all(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+}
+
+impl<'de> Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        write!(formatter, "a decimal number as a string, like \"-12.50\"")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        let invalid = || E::invalid_value(serde::de::Unexpected::Str(v), &self);
+
+        if !is_plain_decimal(v) {
+            return Err(invalid());
+        }
+
+        // Numbers with more digits than `Decimal` holds are rejected instead of rounded
+        Decimal::from_str_exact(v).map_err(|_| invalid())
+    }
+}
+
+impl Formatted<DecimalString> for Decimal {
+    fn serialize_formatted<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+
+    fn deserialize_formatted<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(DecimalVisitor)
+    }
+}
+
+/// # Errors
+/// Can fail if the serializer fails
+pub fn serialize<T, S>(val: &T, ser: S) -> Result<S::Ok, S::Error>
+where
+    T: Formatted<DecimalString>,
+    S: Serializer,
+{
+    val.serialize_formatted(ser)
+}
+
+/// # Errors
+/// Can fail if a value is not a decimal number as a string, or has too many digits
+pub fn deserialize<'de, T, D>(des: D) -> Result<T, D::Error>
+where
+    T: Formatted<DecimalString>,
+    D: Deserializer<'de>,
+{
+    T::deserialize_formatted(des)
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Prices {
+        #[serde(with = "crate::decimal_serializer")]
+        price: Decimal,
+        #[serde(with = "crate::decimal_serializer", default)]
+        discounts: Vec<Decimal>,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let prices = Prices {
+            price: Decimal::new(-1250, 2),
+            discounts: vec![Decimal::new(1, 1), Decimal::new(3, 0)],
+        };
+
+        let json = serde_json::to_string(&prices).unwrap();
+
+        assert_eq!(json, "{\"price\":\"-12.50\",\"discounts\":[\"0.1\",\"3\"]}");
+        assert_eq!(serde_json::from_str::<Prices>(&json).unwrap(), prices);
+    }
+
+    #[test]
+    fn test_rejects_invalid_decimals() {
+        for price in [
+            "12.5",
+            "\"\"",
+            "\"1e5\"",
+            "\"1_000\"",
+            "\".5\"",
+            "\"5.\"",
+            "\"+5\"",
+            "\"123456789012345678901234567890\"",
+        ] {
+            let json = format!("{{\"price\":{price}}}");
+            assert!(serde_json::from_str::<Prices>(&json).is_err(), "{price}");
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::formatted::Formatted;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::time::Duration;
+
+/// Durations as whole seconds and the nanoseconds on top of them, like `{"secs": 90, "nanos": 500000000}`
+/// for a minute and a half. This is the representation serde uses for `Duration`, fixed here so that
+/// the other languages can rely on it, except that the nanoseconds have to be less than a second.
+pub struct SecondsAndNanos;
+
+#[derive(Serialize, Deserialize)]
+struct Parts {
+    secs: u64,
+    nanos: u32,
+}
+
+impl Formatted<SecondsAndNanos> for Duration {
+    fn serialize_formatted<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Parts {
+            secs: self.as_secs(),
+            nanos: self.subsec_nanos(),
+        }
+        .serialize(serializer)
+    }
+
+    fn deserialize_formatted<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let parts = Parts::deserialize(deserializer)?;
+
+        if parts.nanos >= 1_000_000_000 {
+            return Err(D::Error::custom(
+                "The nanoseconds of a duration must be less than a second",
+            ));
+        }
+
+        Ok(Duration::new(parts.secs, parts.nanos))
+    }
+}
+
+/// # Errors
+/// Can fail if the serializer fails
+pub fn serialize<T, S>(val: &T, ser: S) -> Result<S::Ok, S::Error>
+where
+    T: Formatted<SecondsAndNanos>,
+    S: Serializer,
+{
+    val.serialize_formatted(ser)
+}
+
+/// # Errors
+/// Can fail if a value is not an object with the seconds and nanoseconds
+pub fn deserialize<'de, T, D>(des: D) -> Result<T, D::Error>
+where
+    T: Formatted<SecondsAndNanos>,
+    D: Deserializer<'de>,
+{
+    T::deserialize_formatted(des)
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+    use std::time::Duration;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Durations {
+        #[serde(with = "crate::duration_serializer")]
+        duration: Duration,
+        #[serde(with = "crate::duration_serializer", default)]
+        optional: Option<Duration>,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let durations = Durations {
+            duration: Duration::from_millis(90_500),
+            optional: Some(Duration::ZERO),
+        };
+
+        let json = serde_json::to_string(&durations).unwrap();
+
+        assert_eq!(
+            json,
+            "{\"duration\":{\"secs\":90,\"nanos\":500000000},\"optional\":{\"secs\":0,\"nanos\":0}}"
+        );
+        assert_eq!(serde_json::from_str::<Durations>(&json).unwrap(), durations);
+    }
+
+    #[test]
+    fn test_rejects_invalid_nanoseconds() {
+        assert!(serde_json::from_str::<Durations>(
+            "{\"duration\":{\"secs\":0,\"nanos\":1000000000}}"
+        )
+        .is_err());
+    }
+}
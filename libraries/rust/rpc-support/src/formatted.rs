@@ -0,0 +1,125 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+/// A value that can be serialized in the format `F`, either directly or by containing values that
+/// can be. This lets the serializers in this crate be used in `#[serde(with = "...")]` on fields
+/// of the types generated for `T?`, `T[]` and `map<K, T>` as well as on fields of type `T`.
+pub trait Formatted<F>: Sized {
+    /// # Errors
+    /// Can fail if the value cannot be represented in the format
+    fn serialize_formatted<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer;
+
+    /// # Errors
+    /// Can fail if the input is not in the format
+    fn deserialize_formatted<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>;
+}
+
+struct Borrowed<'a, F, T>(&'a T, PhantomData<F>);
+
+impl<'a, F, T> Borrowed<'a, F, T> {
+    fn new(value: &'a T) -> Self {
+        Self(value, PhantomData)
+    }
+}
+
+impl<F, T> Serialize for Borrowed<'_, F, T>
+where
+    T: Formatted<F>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize_formatted(serializer)
+    }
+}
+
+struct Owned<F, T>(T, PhantomData<F>);
+
+impl<'de, F, T> Deserialize<'de> for Owned<F, T>
+where
+    T: Formatted<F>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self(T::deserialize_formatted(deserializer)?, PhantomData))
+    }
+}
+
+impl<F, T> Formatted<F> for Option<T>
+where
+    T: Formatted<F>,
+{
+    fn serialize_formatted<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Some(value) => serializer.serialize_some(&Borrowed::<F, T>::new(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    fn deserialize_formatted<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Option::<Owned<F, T>>::deserialize(deserializer)?.map(|value| value.0))
+    }
+}
+
+impl<F, T> Formatted<F> for Vec<T>
+where
+    T: Formatted<F>,
+{
+    fn serialize_formatted<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.iter().map(Borrowed::<F, T>::new))
+    }
+
+    fn deserialize_formatted<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<Owned<F, T>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|value| value.0)
+            .collect())
+    }
+}
+
+impl<F, K, T> Formatted<F> for BTreeMap<K, T>
+where
+    K: Serialize + DeserializeOwned + Ord,
+    T: Formatted<F>,
+{
+    fn serialize_formatted<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(
+            self.iter()
+                .map(|(key, value)| (key, Borrowed::<F, T>::new(value))),
+        )
+    }
+
+    fn deserialize_formatted<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(BTreeMap::<K, Owned<F, T>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| (key, value.0))
+            .collect())
+    }
+}
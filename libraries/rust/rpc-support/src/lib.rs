@@ -4,18 +4,31 @@ use futures::{Stream, StreamExt};
 use platform::async_infra::run_with_error_handling;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf,
+};
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpStream;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::Mutex;
 use tracing::{debug, error, info};
 
+pub mod date_serializer;
+pub mod decimal_serializer;
+pub mod duration_serializer;
+pub mod formatted;
 pub mod rpc_error;
 pub mod system_time_serializer;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod validation;
 
 #[async_trait::async_trait]
 pub trait Client: Send {
@@ -50,10 +63,22 @@ impl Client for DefaultClient {
     }
 }
 
+/// The requests of a call with a stream of requests are sent as a `Start` frame without a request,
+/// an `Item` frame for every request and an `End` frame without a request
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum StreamFrame {
+    Start,
+    Item,
+    End,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct RequestEnvelope {
     pub method_name: String,
     pub request_id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<StreamFrame>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -73,11 +98,41 @@ type WaitingResponses = DashMap<u64, Sender<(ResponseEnvelope, Option<String>)>>
 type ActiveStreams = DashMap<u64, Sender<(ResponseEnvelope, Option<String>)>>;
 pub type ResponseStream<TResponse> =
     Pin<Box<dyn Stream<Item = Result<TResponse, RpcError>> + Unpin + Send>>;
+pub type RequestStream<TRequest> = Pin<Box<dyn Stream<Item = TRequest> + Unpin + Send>>;
+
+/// How the calls of a method are made, from the attributes of the method
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CallOptions {
+    /// How long to wait for the response, `@timeout`
+    pub timeout: Option<Duration>,
+    /// Whether the call can be sent again when the connection is lost, `@idempotent`
+    pub idempotent: bool,
+}
+
+const IDEMPOTENT_RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// # Errors
+/// Fails with `RpcError::Timeout` if the call takes longer than `timeout`, or with the error of the call
+pub async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    method_name: &str,
+    call: impl Future<Output = Result<T, RpcError>>,
+) -> Result<T, RpcError> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, call)
+            .await
+            .unwrap_or_else(|_| Err(RpcError::Timeout(method_name.to_string()))),
+        None => call.await,
+    }
+}
 
 pub struct DefaultRawRpcClient {
     waiting_responses: Arc<WaitingResponses>,
     active_streams: Arc<ActiveStreams>,
     request_tx: Sender<String>,
+    connection_lost: Arc<AtomicBool>,
+    address: Option<String>,
 }
 
 #[async_trait::async_trait]
@@ -106,6 +161,89 @@ pub trait RawRpcClient {
         TRequest: Serialize + Sync + Send,
         TMetadata: Serialize + Sync + Send,
         TResponse: DeserializeOwned;
+
+    async fn send_rpc_client_stream<TRequest, TMetadata, TResponse>(
+        &mut self,
+        request_id: u64,
+        method_name: &str,
+        requests: RequestStream<TRequest>,
+        metadata: &TMetadata,
+    ) -> Result<TResponse, RpcError>
+    where
+        TRequest: Serialize + Send + 'static,
+        TMetadata: Serialize + Sync + Send,
+        TResponse: DeserializeOwned;
+
+    async fn send_rpc_bidirectional_stream<TRequest, TMetadata, TResponse>(
+        &mut self,
+        request_id: u64,
+        method_name: &str,
+        requests: RequestStream<TRequest>,
+        metadata: &TMetadata,
+    ) -> Result<ResponseStream<TResponse>, RpcError>
+    where
+        TRequest: Serialize + Send + 'static,
+        TMetadata: Serialize + Sync + Send,
+        TResponse: DeserializeOwned;
+
+    /// Replaces a lost connection with a new one
+    ///
+    /// # Errors
+    /// Fails if the client cannot connect again
+    async fn reconnect(&mut self) -> Result<(), RpcError> {
+        Err(RpcError::ConnectionLost(
+            "the client cannot reconnect".to_string(),
+        ))
+    }
+
+    /// Forgets the call `id`, whose response is not waited for anymore
+    fn stop_waiting(&mut self, _id: u64) {}
+
+    /// Sends the request with the options of its method. Idempotent calls are sent again over a
+    /// new connection when the connection is lost.
+    ///
+    /// # Errors
+    /// Can fail if sending the request fails, if the call times out or if the call returns an error
+    async fn send_rpc_with_options<TRequest, TMetadata, TResponse>(
+        &mut self,
+        id: u64,
+        method_name: &str,
+        request: &TRequest,
+        metadata: &TMetadata,
+        options: CallOptions,
+    ) -> Result<TResponse, RpcError>
+    where
+        TRequest: Serialize + Sync + Send,
+        TMetadata: Serialize + Sync + Send,
+        TResponse: DeserializeOwned,
+    {
+        let mut retries = 0;
+
+        loop {
+            match with_timeout(
+                options.timeout,
+                method_name,
+                self.send_rpc(id, method_name, request, metadata),
+            )
+            .await
+            {
+                Err(RpcError::ConnectionLost(reason))
+                    if options.idempotent && retries < IDEMPOTENT_RETRIES =>
+                {
+                    info!("Sending {method_name} again, the connection was lost: {reason}");
+                }
+                Err(RpcError::Timeout(method_name)) => {
+                    self.stop_waiting(id);
+                    return Err(RpcError::Timeout(method_name));
+                }
+                result => return result,
+            }
+
+            retries += 1;
+            tokio::time::sleep(RETRY_DELAY * retries).await;
+            self.reconnect().await?;
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -125,27 +263,81 @@ impl<T> From<tokio::sync::mpsc::error::SendError<T>> for RpcClientTaskError {
 }
 
 async fn client_response_task(
-    read: OwnedReadHalf,
+    read: impl AsyncRead + Unpin,
     waiting_responses: Arc<WaitingResponses>,
     active_streams: Arc<ActiveStreams>,
+    connection_lost: Arc<AtomicBool>,
+) -> Result<(), RpcClientTaskError> {
+    let result = read_responses(read, &waiting_responses, &active_streams).await;
+
+    // Calls that are made from now on fail right away, see `DefaultRawRpcClient::check_connection`
+    connection_lost.store(true, Ordering::SeqCst);
+    let reason = result.as_ref().err().map_or_else(
+        || "the server closed the connection".to_string(),
+        ToString::to_string,
+    );
+    let request_ids: Vec<u64> = waiting_responses
+        .iter()
+        .map(|entry| *entry.key())
+        .chain(active_streams.iter().map(|entry| *entry.key()))
+        .collect();
+    for request_id in request_ids {
+        let Some((_, sender)) = waiting_responses
+            .remove(&request_id)
+            .or_else(|| active_streams.remove(&request_id))
+        else {
+            continue;
+        };
+        let envelope = ResponseEnvelope {
+            request_id,
+            error: Some(RpcError::ConnectionLost(reason.clone())),
+            stream_end: false,
+        };
+
+        // The caller may have stopped waiting already
+        if sender.send((envelope, None)).await.is_err() {
+            debug!("Request {request_id} is not waited for anymore");
+        }
+    }
+
+    result
+}
+
+async fn read_responses(
+    read: impl AsyncRead + Unpin,
+    waiting_responses: &WaitingResponses,
+    active_streams: &ActiveStreams,
 ) -> Result<(), RpcClientTaskError> {
     let mut reader = tokio::io::BufReader::new(read);
 
     loop {
         let mut response_envelope_line = String::new();
-        reader.read_line(&mut response_envelope_line).await?;
+        if reader.read_line(&mut response_envelope_line).await? == 0 {
+            return Ok(());
+        }
         let response_envelope: ResponseEnvelope = serde_json::from_str(&response_envelope_line)?;
-
-        // todo pass the error
-        if let Some(ref _error) = response_envelope.error {
-            if let Some((_, sender)) = waiting_responses.remove(&response_envelope.request_id) {
-                sender.send((response_envelope, None)).await?;
+        let request_id = response_envelope.request_id;
+
+        if let Some(ref error) = response_envelope.error {
+            // Errors of streams are passed on as well, the last one ends the stream, for example
+            // when the server refuses to start it
+            let sender = if response_envelope.stream_end {
+                active_streams.remove(&request_id).map(|(_, sender)| sender)
+            } else {
+                active_streams.get(&request_id).map(|sender| sender.clone())
+            };
+            if let Some(sender) = waiting_responses
+                .remove(&request_id)
+                .map(|(_, sender)| sender)
+                .or(sender)
+            {
+                // Calls that timed out are not waited for anymore
+                if sender.send((response_envelope, None)).await.is_err() {
+                    debug!("Request {request_id} is not waited for anymore");
+                }
                 continue;
             }
-            error!(
-                "Found response, but no request. Request ID: {}",
-                response_envelope.request_id
-            );
+            error!("Found error \"{error}\", but no request. Request ID: {request_id}");
 
             continue;
         }
@@ -154,13 +346,24 @@ async fn client_response_task(
         reader.read_line(&mut response_line).await?;
 
         if let Some((_, sender)) = waiting_responses.remove(&response_envelope.request_id) {
-            sender
+            if sender
                 .send((response_envelope, Some(response_line)))
-                .await?;
-        } else if let Some(sender) = active_streams.get(&response_envelope.request_id) {
-            sender
+                .await
+                .is_err()
+            {
+                debug!("Request {request_id} is not waited for anymore");
+            }
+        } else if let Some(sender) = active_streams
+            .get(&response_envelope.request_id)
+            .map(|sender| sender.clone())
+        {
+            if sender
                 .send((response_envelope, Some(response_line)))
-                .await?;
+                .await
+                .is_err()
+            {
+                active_streams.remove(&request_id);
+            }
         } else {
             error!(
                 "Found response, but no request. Request ID: {}",
@@ -171,7 +374,7 @@ async fn client_response_task(
 }
 
 async fn client_request_task(
-    mut writer: OwnedWriteHalf,
+    mut writer: impl AsyncWrite + Unpin,
     mut channel: Receiver<String>,
 ) -> Result<(), RpcClientTaskError> {
     while let Some(request_line) = channel.recv().await {
@@ -179,6 +382,10 @@ async fn client_request_task(
         writer.flush().await?;
     }
 
+    // The client was dropped, which the server sees as the connection being closed, also when the
+    // connection is in memory and its reading half is still open
+    writer.shutdown().await?;
+
     Ok(())
 }
 
@@ -198,38 +405,22 @@ impl RawRpcClient for DefaultRawRpcClient {
         TMetadata: Serialize + Sync + Send,
         TResponse: DeserializeOwned,
     {
-        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
         self.waiting_responses.insert(id, tx);
+        self.check_connection(id)?;
 
         self.send_raw_request(
             &RequestEnvelope {
                 method_name: method_name.to_string(),
                 request_id: id,
+                stream: None,
             },
             &metadata,
             &request,
         )
         .await?;
 
-        info!("Waiting for response");
-        let (response_envelope, response_line) = rx
-            .recv()
-            .await
-            .ok_or_else(|| RpcError::Custom("No response from client task".into()))?;
-        info!(
-            "Got response: {:?} {}",
-            response_envelope,
-            response_line.as_ref().unwrap_or(&String::new())
-        );
-
-        if let Some(error) = response_envelope.error {
-            return Err(error);
-        }
-
-        let response_line = response_line.ok_or_else(|| RpcError::Custom("No response".into()))?;
-        let response: TResponse = serde_json::from_str(&response_line)?;
-
-        Ok(response)
+        Self::wait_for_response(rx).await
     }
 
     /// # Panics
@@ -248,13 +439,15 @@ impl RawRpcClient for DefaultRawRpcClient {
         TMetadata: Serialize + Sync + Send,
         TResponse: DeserializeOwned,
     {
-        let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+        let (tx, rx) = tokio::sync::mpsc::channel(64);
         self.active_streams.insert(id, tx);
+        self.check_connection(id)?;
 
         self.send_raw_request(
             &RequestEnvelope {
                 method_name: method_name.to_string(),
                 request_id: id,
+                stream: None,
             },
             &metadata,
             &request,
@@ -263,47 +456,116 @@ impl RawRpcClient for DefaultRawRpcClient {
 
         info!("Stream request sent");
 
-        let rx_stream = Box::pin(async_stream::stream! {
-            while let Some(response_line) = rx.recv().await {
-                if response_line.0.stream_end {
-                    break;
-                }
+        Ok(Self::response_stream(rx))
+    }
 
-                yield response_line;
-            }
+    /// # Errors
+    /// Can fail if starting the stream of requests fails or if the call returns an error
+    async fn send_rpc_client_stream<TRequest, TMetadata, TResponse>(
+        &mut self,
+        id: u64,
+        method_name: &str,
+        requests: RequestStream<TRequest>,
+        metadata: &TMetadata,
+    ) -> Result<TResponse, RpcError>
+    where
+        TRequest: Serialize + Send + 'static,
+        TMetadata: Serialize + Sync + Send,
+        TResponse: DeserializeOwned,
+    {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        self.waiting_responses.insert(id, tx);
+        self.check_connection(id)?;
 
-            info!("Stream ended");
-        });
+        self.send_request_stream(id, method_name, requests, metadata)
+            .await?;
 
-        Ok(Box::pin(rx_stream.map(
-            move |(response_envelope, contents): (ResponseEnvelope, Option<String>)| {
-                match response_envelope.error {
-                    None => {
-                        let contents = contents.ok_or_else(|| {
-                            RpcError::Custom(format!(
-                                "No response for envelope {response_envelope:?}"
-                            ))
-                        })?;
+        Self::wait_for_response(rx).await
+    }
 
-                        Ok(serde_json::from_str(&contents)?)
-                    }
-                    Some(e) => Err(e),
-                }
-            },
-        )))
+    /// # Errors
+    /// Can fail if starting the stream of requests fails
+    async fn send_rpc_bidirectional_stream<TRequest, TMetadata, TResponse>(
+        &mut self,
+        id: u64,
+        method_name: &str,
+        requests: RequestStream<TRequest>,
+        metadata: &TMetadata,
+    ) -> Result<ResponseStream<TResponse>, RpcError>
+    where
+        TRequest: Serialize + Send + 'static,
+        TMetadata: Serialize + Sync + Send,
+        TResponse: DeserializeOwned,
+    {
+        let (tx, rx) = tokio::sync::mpsc::channel(64);
+        self.active_streams.insert(id, tx);
+        self.check_connection(id)?;
+
+        self.send_request_stream(id, method_name, requests, metadata)
+            .await?;
+
+        Ok(Self::response_stream(rx))
+    }
+
+    /// # Errors
+    /// Fails if the client was created from a stream instead of an address, or if connecting fails
+    async fn reconnect(&mut self) -> Result<(), RpcError> {
+        let address = self.address.clone().ok_or_else(|| {
+            RpcError::ConnectionLost(
+                "the client was created from a stream, so it cannot reconnect".to_string(),
+            )
+        })?;
+        let (read, write) = TcpStream::connect(&address).await?.into_split();
+        *self = Self::start(read, write, Some(address));
+
+        Ok(())
+    }
+
+    // A response that arrives later is dropped by the response task
+    fn stop_waiting(&mut self, id: u64) {
+        self.waiting_responses.remove(&id);
     }
 }
 
 impl DefaultRawRpcClient {
     pub fn new(stream: tokio::net::TcpStream) -> Self {
         let (read, write) = stream.into_split();
+        Self::start(read, write, None)
+    }
+
+    /// Makes the calls over a connection that is not a TCP one, for example an in-memory one in
+    /// tests. The client cannot reconnect when the connection is lost.
+    pub fn from_stream(stream: impl AsyncRead + AsyncWrite + Send + 'static) -> Self {
+        let (read, write) = tokio::io::split(stream);
+        Self::start(read, write, None)
+    }
+
+    /// Connects to `address`, and connects to it again when an idempotent call finds the connection
+    /// lost
+    ///
+    /// # Errors
+    /// Fails if the connection cannot be established
+    pub async fn connect(address: &str) -> Result<Self, RpcError> {
+        let (read, write) = TcpStream::connect(address).await?.into_split();
+
+        Ok(Self::start(read, write, Some(address.to_string())))
+    }
+
+    // Every connection has its own calls, the calls of a lost connection are failed by its response task
+    fn start(
+        read: impl AsyncRead + Unpin + Send + 'static,
+        write: impl AsyncWrite + Unpin + Send + 'static,
+        address: Option<String>,
+    ) -> Self {
         let waiting_responses = Arc::new(DashMap::new());
         let active_streams = Arc::new(DashMap::new());
+        let connection_lost = Arc::new(AtomicBool::new(false));
 
         tokio::task::spawn(run_with_error_handling(client_response_task(
             read,
             waiting_responses.clone(),
             active_streams.clone(),
+            connection_lost.clone(),
         )));
 
         let (request_tx, request_rx) = tokio::sync::mpsc::channel(64);
@@ -316,7 +578,24 @@ impl DefaultRawRpcClient {
             waiting_responses,
             active_streams,
             request_tx,
+            connection_lost,
+            address,
+        }
+    }
+
+    // The response task fails the calls it knows of when the connection is lost, the ones that are
+    // made afterwards are failed here
+    fn check_connection(&self, id: u64) -> Result<(), RpcError> {
+        if self.connection_lost.load(Ordering::SeqCst) {
+            self.waiting_responses.remove(&id);
+            self.active_streams.remove(&id);
+
+            return Err(RpcError::ConnectionLost(
+                "the connection is closed".to_string(),
+            ));
         }
+
+        Ok(())
     }
     async fn send_raw_request<TMetadata, TRequest>(
         &mut self,
@@ -328,18 +607,422 @@ impl DefaultRawRpcClient {
         TMetadata: Serialize,
         TRequest: Serialize,
     {
-        let mut buffer = String::new();
-        buffer.push_str(&serde_json::to_string(envelope)?);
-        buffer.push('\n');
-        buffer.push_str(&serde_json::to_string(&metadata)?);
-        buffer.push('\n');
-        buffer.push_str(&serde_json::to_string(&request)?);
-        buffer.push('\n');
+        let frame = request_frame(envelope, metadata, request)?;
+        self.request_tx
+            .send(frame)
+            .await
+            .map_err(|_| RpcError::ConnectionLost("the connection is closed".to_string()))?;
+
+        Ok(())
+    }
+
+    // The requests are sent by their own task, so that the responses can be received in the meantime
+    async fn send_request_stream<TRequest, TMetadata>(
+        &mut self,
+        id: u64,
+        method_name: &str,
+        mut requests: RequestStream<TRequest>,
+        metadata: &TMetadata,
+    ) -> Result<(), RpcError>
+    where
+        TRequest: Serialize + Send + 'static,
+        TMetadata: Serialize,
+    {
+        let envelope = |stream| RequestEnvelope {
+            method_name: method_name.to_string(),
+            request_id: id,
+            stream: Some(stream),
+        };
+
+        self.send_raw_request(&envelope(StreamFrame::Start), metadata, &())
+            .await?;
 
-        self.request_tx.send(buffer).await?;
+        let request_tx = self.request_tx.clone();
+        let (item, end) = (envelope(StreamFrame::Item), envelope(StreamFrame::End));
+        tokio::task::spawn(run_with_error_handling(async move {
+            while let Some(request) = requests.next().await {
+                request_tx
+                    .send(request_frame(&item, &(), &request)?)
+                    .await?;
+            }
+            request_tx.send(request_frame(&end, &(), &())?).await?;
+
+            Ok::<_, RpcClientTaskError>(())
+        }));
 
         Ok(())
     }
+
+    async fn wait_for_response<TResponse>(
+        mut rx: Receiver<(ResponseEnvelope, Option<String>)>,
+    ) -> Result<TResponse, RpcError>
+    where
+        TResponse: DeserializeOwned,
+    {
+        info!("Waiting for response");
+        let (response_envelope, response_line) = rx
+            .recv()
+            .await
+            .ok_or_else(|| RpcError::Custom("No response from client task".into()))?;
+        info!(
+            "Got response: {:?} {}",
+            response_envelope,
+            response_line.as_ref().unwrap_or(&String::new())
+        );
+
+        if let Some(error) = response_envelope.error {
+            return Err(error);
+        }
+
+        let response_line = response_line.ok_or_else(|| RpcError::Custom("No response".into()))?;
+        let response: TResponse = serde_json::from_str(&response_line)?;
+
+        Ok(response)
+    }
+
+    fn response_stream<TResponse>(
+        mut rx: Receiver<(ResponseEnvelope, Option<String>)>,
+    ) -> ResponseStream<TResponse>
+    where
+        TResponse: DeserializeOwned,
+    {
+        let rx_stream = Box::pin(async_stream::stream! {
+            while let Some(response_line) = rx.recv().await {
+                if response_line.0.stream_end && response_line.0.error.is_none() {
+                    break;
+                }
+
+                yield response_line;
+            }
+
+            info!("Stream ended");
+        });
+
+        Box::pin(rx_stream.map(
+            move |(response_envelope, contents): (ResponseEnvelope, Option<String>)| {
+                match response_envelope.error {
+                    None => {
+                        let contents = contents.ok_or_else(|| {
+                            RpcError::Custom(format!(
+                                "No response for envelope {response_envelope:?}"
+                            ))
+                        })?;
+
+                        Ok(serde_json::from_str(&contents)?)
+                    }
+                    Some(e) => Err(e),
+                }
+            },
+        ))
+    }
+}
+
+// Every request is sent as an envelope line, a metadata line and a payload line
+fn request_frame<TMetadata, TRequest>(
+    envelope: &RequestEnvelope,
+    metadata: &TMetadata,
+    request: &TRequest,
+) -> Result<String, serde_json::Error>
+where
+    TMetadata: Serialize,
+    TRequest: Serialize,
+{
+    let mut buffer = String::new();
+    buffer.push_str(&serde_json::to_string(envelope)?);
+    buffer.push('\n');
+    buffer.push_str(&serde_json::to_string(&metadata)?);
+    buffer.push('\n');
+    buffer.push_str(&serde_json::to_string(&request)?);
+    buffer.push('\n');
+
+    Ok(buffer)
+}
+
+/// A call read by a `CallReader`
+pub struct Call<TMetadata> {
+    pub method_name: String,
+    pub request_id: u64,
+    pub metadata: TMetadata,
+    pub payload_line: String,
+    requests: Option<Receiver<Result<String, RpcError>>>,
+}
+
+impl<TMetadata> Call<TMetadata> {
+    /// The requests of a call with a stream of requests, deserialized as they arrive. The stream
+    /// ends when the client ends it or disconnects, and fails if the call is too far behind on its
+    /// requests.
+    ///
+    /// # Errors
+    /// Fails if the client sent a single request instead of a stream, or took the stream already
+    pub fn request_stream<TRequest>(&mut self) -> Result<ResponseStream<TRequest>, RpcError>
+    where
+        TRequest: DeserializeOwned + Send + 'static,
+    {
+        let mut requests = self.requests.take().ok_or_else(|| {
+            RpcError::Custom(format!(
+                "The method \"{}\" expects a stream of requests",
+                self.method_name
+            ))
+        })?;
+
+        Ok(Box::pin(
+            Box::pin(async_stream::stream! {
+                while let Some(payload_line) = requests.recv().await {
+                    yield payload_line;
+                }
+            })
+            .map(|payload_line| Ok(serde_json::from_str(&payload_line?)?)),
+        ))
+    }
+}
+
+/// The name of the method that servers describe their methods with
+pub const DESCRIBE_METHOD: &str = "__describe";
+
+// The requests of a stream that are read before its call handles them. A stream that falls further
+// behind fails instead of stopping the connection from being read for the other calls
+const REQUEST_STREAM_BUFFER: usize = 64;
+
+/// Why `CallReader::next_call` returned no call
+#[derive(Debug, Error)]
+pub enum CallError {
+    /// The call cannot be served and should be answered with the error, the connection can still
+    /// be read
+    #[error("The call {request_id} is invalid: {error}")]
+    Invalid { request_id: u64, error: RpcError },
+    /// The connection cannot be read anymore
+    #[error("{0}")]
+    Connection(#[from] RpcError),
+}
+
+impl From<std::io::Error> for CallError {
+    fn from(e: std::io::Error) -> Self {
+        CallError::Connection(e.into())
+    }
+}
+
+/// Reads the calls from a connection of a server. The requests of calls with a stream of requests
+/// are passed on to the stream of their call, so that reading the next call never waits for a call
+/// to end or to handle its requests.
+pub struct CallReader<TReader> {
+    reader: TReader,
+    request_streams: HashMap<u64, Sender<Result<String, RpcError>>>,
+}
+
+impl<TReader> CallReader<TReader>
+where
+    TReader: AsyncBufRead + Unpin + Send,
+{
+    pub fn new(reader: TReader) -> Self {
+        Self {
+            reader,
+            request_streams: HashMap::new(),
+        }
+    }
+
+    /// # Errors
+    /// Returns `CallError::Invalid` if the metadata of the next call cannot be deserialized, and
+    /// `CallError::Connection` if the next call cannot be read from the connection
+    pub async fn next_call<TMetadata>(&mut self) -> Result<Call<TMetadata>, CallError>
+    where
+        TMetadata: DeserializeOwned,
+    {
+        loop {
+            let envelope_line = self.read_line().await?;
+            let metadata_line = self.read_line().await?;
+            let payload_line = self.read_line().await?;
+
+            debug!("Envelope: {}", envelope_line);
+            debug!("Metadata: {}", metadata_line);
+            debug!("Payload: {}", payload_line);
+
+            // Without an envelope the call cannot be answered
+            let envelope: RequestEnvelope =
+                serde_json::from_str(&envelope_line).map_err(RpcError::from)?;
+
+            // The requests of a stream are passed on to their call, which has read the metadata
+            match envelope.stream {
+                Some(StreamFrame::Item) => {
+                    // The last slot of the buffer is kept for the error of a stream that overflows,
+                    // whose remaining requests are dropped. The call can end before its stream of
+                    // requests does
+                    if let Some(sender) = self.request_streams.get(&envelope.request_id) {
+                        let sent = if sender.capacity() > 1 {
+                            sender.try_send(Ok(payload_line)).is_ok()
+                        } else {
+                            sender
+                                .try_send(Err(RpcError::Custom(format!(
+                                    "The call {} is more than {REQUEST_STREAM_BUFFER} requests behind on its stream",
+                                    envelope.request_id
+                                ))))
+                                .ok();
+                            false
+                        };
+
+                        if !sent {
+                            self.request_streams.remove(&envelope.request_id);
+                        }
+                    }
+                    continue;
+                }
+                Some(StreamFrame::End) => {
+                    self.request_streams.remove(&envelope.request_id);
+                    continue;
+                }
+                None | Some(StreamFrame::Start) => {}
+            }
+
+            // The requests of a stream whose metadata is invalid are dropped as they arrive
+            let metadata =
+                serde_json::from_str(&metadata_line).map_err(|error| CallError::Invalid {
+                    request_id: envelope.request_id,
+                    error: error.into(),
+                })?;
+
+            let requests = (envelope.stream == Some(StreamFrame::Start)).then(|| {
+                let (sender, receiver) = tokio::sync::mpsc::channel(REQUEST_STREAM_BUFFER + 1);
+                self.request_streams.insert(envelope.request_id, sender);
+
+                receiver
+            });
+
+            return Ok(Call {
+                method_name: envelope.method_name,
+                request_id: envelope.request_id,
+                metadata,
+                payload_line,
+                requests,
+            });
+        }
+    }
+
+    async fn read_line(&mut self) -> std::io::Result<String> {
+        let mut buf = String::new();
+        self.reader.read_line(&mut buf).await?;
+
+        Ok(buf)
+    }
+}
+
+/// The writing half of a connection of a server, whose reading half is read by a `CallReader`
+pub struct ResponseWriter {
+    writer: Box<dyn AsyncWrite + Unpin + Send>,
+}
+
+#[async_trait::async_trait]
+impl Client for ResponseWriter {
+    async fn write_all(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.writer.write_all(data).await
+    }
+
+    async fn read_line(&mut self) -> std::io::Result<String> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the requests are read by the CallReader",
+        ))
+    }
+}
+
+/// Splits a connection of a server, so that responses can be written while calls are read
+pub fn split_connection<TStream>(
+    stream: TStream,
+) -> (CallReader<BufReader<ReadHalf<TStream>>>, ResponseWriter)
+where
+    TStream: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, writer) = tokio::io::split(stream);
+
+    (
+        CallReader::new(BufReader::new(reader)),
+        ResponseWriter {
+            writer: Box::new(writer),
+        },
+    )
+}
+
+/// The calls of a connection of a server, each of which runs in its own task, so that the calls
+/// of a connection are served concurrently. When it is dropped, because the client disconnected,
+/// the calls with a stream of requests or responses are aborted, as they cannot end without the
+/// client. The other calls run to their end, so that they are not stopped halfway through.
+#[derive(Default)]
+pub struct CallTasks {
+    tasks: Arc<std::sync::Mutex<HashMap<u64, RunningCall>>>,
+    // The request IDs are chosen by the client, which can use one for several calls
+    next_task_id: AtomicU64,
+}
+
+struct RunningCall {
+    task: tokio::task::AbortHandle,
+    stream: bool,
+}
+
+impl CallTasks {
+    /// Runs a call without a stream, which keeps running when the client disconnects
+    pub fn spawn<F>(&self, call: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.spawn_call(call, false);
+    }
+
+    /// Runs a call with a stream of requests or responses, which is aborted when the client
+    /// disconnects
+    pub fn spawn_stream<F>(&self, call: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.spawn_call(call, true);
+    }
+
+    fn spawn_call<F>(&self, call: F, stream: bool)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let task_id = self.next_task_id.fetch_add(1, Ordering::Relaxed);
+
+        // The task cannot remove itself before it is inserted, as it waits for the lock
+        let mut running = lock_tasks(&self.tasks);
+        let tasks = self.tasks.clone();
+        let task = tokio::spawn(async move {
+            call.await;
+            lock_tasks(&tasks).remove(&task_id);
+        });
+        running.insert(
+            task_id,
+            RunningCall {
+                task: task.abort_handle(),
+                stream,
+            },
+        );
+    }
+
+    /// The number of calls that still run
+    #[must_use]
+    pub fn len(&self) -> usize {
+        lock_tasks(&self.tasks).len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for CallTasks {
+    fn drop(&mut self) {
+        for call in lock_tasks(&self.tasks).values().filter(|call| call.stream) {
+            call.task.abort();
+        }
+    }
+}
+
+// The map stays consistent when a task panics, as the lock is never held across a call
+fn lock_tasks(
+    tasks: &std::sync::Mutex<HashMap<u64, RunningCall>>,
+) -> std::sync::MutexGuard<'_, HashMap<u64, RunningCall>> {
+    tasks
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
 }
 
 /**
@@ -493,4 +1176,242 @@ mod test {
             String::from_utf8(output).unwrap()
         );
     }
+
+    #[tokio::test]
+    async fn test_next_call_with_request_stream() {
+        let input = [
+            "{\"method_name\":\"upload\",\"request_id\":1,\"stream\":\"start\"}\n\"meta\"\nnull\n",
+            "{\"method_name\":\"test\",\"request_id\":2}\n\"meta\"\n\"single\"\n",
+            "{\"method_name\":\"upload\",\"request_id\":1,\"stream\":\"item\"}\nnull\n\"first\"\n",
+            "{\"method_name\":\"upload\",\"request_id\":1,\"stream\":\"item\"}\nnull\n\"second\"\n",
+            "{\"method_name\":\"upload\",\"request_id\":1,\"stream\":\"end\"}\nnull\nnull\n",
+            "{\"method_name\":\"test\",\"request_id\":3}\n\"meta\"\n\"last\"\n",
+        ]
+        .concat();
+        let mut reader = CallReader::new(input.as_bytes());
+
+        let mut upload: Call<String> = reader.next_call().await.unwrap();
+        assert_eq!(upload.method_name, "upload");
+        assert_eq!(upload.request_id, 1);
+        assert_eq!(upload.metadata, "meta");
+
+        let mut single: Call<String> = reader.next_call().await.unwrap();
+        assert_eq!(single.request_id, 2);
+        assert_eq!(single.payload_line, "\"single\"\n");
+        assert!(single.request_stream::<String>().is_err());
+
+        let last: Call<String> = reader.next_call().await.unwrap();
+        assert_eq!(last.request_id, 3);
+        drop(reader);
+
+        let requests: Vec<String> = upload
+            .request_stream()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(requests, vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_next_call_is_not_blocked_by_a_slow_request_stream() {
+        let mut input =
+            "{\"method_name\":\"upload\",\"request_id\":1,\"stream\":\"start\"}\n\"meta\"\nnull\n"
+                .to_string();
+        for i in 0..REQUEST_STREAM_BUFFER + 10 {
+            input.push_str(&format!(
+                "{{\"method_name\":\"upload\",\"request_id\":1,\"stream\":\"item\"}}\nnull\n\"{i}\"\n"
+            ));
+        }
+        input.push_str("{\"method_name\":\"test\",\"request_id\":2}\n\"meta\"\nnull\n");
+        let mut reader = CallReader::new(input.as_bytes());
+
+        // The upload does not handle its requests until the unary call after them has been read
+        let mut upload: Call<String> = reader.next_call().await.unwrap();
+        let unary: Call<String> = tokio::time::timeout(Duration::from_secs(1), reader.next_call())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(unary.request_id, 2);
+
+        let requests: Vec<Result<String, RpcError>> =
+            upload.request_stream().unwrap().collect().await;
+        assert_eq!(requests.len(), REQUEST_STREAM_BUFFER + 1);
+        assert!(requests[..REQUEST_STREAM_BUFFER].iter().all(Result::is_ok));
+        assert!(requests[REQUEST_STREAM_BUFFER].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_next_call_with_invalid_metadata() {
+        let input = [
+            "{\"method_name\":\"test\",\"request_id\":1}\n42\nnull\n",
+            "{\"method_name\":\"test\",\"request_id\":2}\n\"meta\"\nnull\n",
+            "not an envelope\n\"meta\"\nnull\n",
+        ]
+        .concat();
+        let mut reader = CallReader::new(input.as_bytes());
+
+        let invalid = reader.next_call::<String>().await.err().unwrap();
+        assert!(matches!(
+            invalid,
+            CallError::Invalid {
+                request_id: 1,
+                error: RpcError::SerializationFailed(_)
+            }
+        ));
+
+        let valid: Call<String> = reader.next_call().await.unwrap();
+        assert_eq!(valid.request_id, 2);
+
+        let broken = reader.next_call::<String>().await.err().unwrap();
+        assert!(matches!(broken, CallError::Connection(_)));
+    }
+
+    #[tokio::test]
+    async fn test_stream_call_tasks_are_aborted_when_dropped() {
+        let calls = CallTasks::default();
+        let (finished_tx, finished_rx) = tokio::sync::oneshot::channel();
+        let (aborted_tx, mut aborted_rx) = tokio::sync::mpsc::channel::<()>(1);
+
+        calls.spawn_stream(async move {
+            finished_tx.send(()).unwrap();
+        });
+        // Both tasks are kept, whatever the request IDs of their calls
+        for _ in 0..2 {
+            let aborted_tx = aborted_tx.clone();
+            calls.spawn_stream(async move {
+                let _aborted_tx = aborted_tx;
+                futures::future::pending::<()>().await;
+            });
+        }
+        drop(aborted_tx);
+        finished_rx.await.unwrap();
+        tokio::task::yield_now().await;
+        assert_eq!(calls.len(), 2);
+
+        // The sender is dropped together with the aborted task
+        drop(calls);
+        assert!(aborted_rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_call_tasks_without_a_stream_run_to_their_end_when_dropped() {
+        let calls = CallTasks::default();
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        let (finished_tx, finished_rx) = tokio::sync::oneshot::channel();
+
+        calls.spawn(async move {
+            started_tx.send(()).unwrap();
+            release_rx.await.unwrap();
+            finished_tx.send(()).unwrap();
+        });
+        started_rx.await.unwrap();
+
+        drop(calls);
+        release_tx.send(()).unwrap();
+        finished_rx.await.unwrap();
+    }
+
+    // Accepts two connections, the first is closed before the call is answered
+    async fn flaky_server() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (mut reader, writer) = split_connection(socket);
+            let _: Call<()> = reader.next_call().await.unwrap();
+            drop((reader, writer));
+
+            let (socket, _) = listener.accept().await.unwrap();
+            let (mut reader, writer) = split_connection(socket);
+            let call: Call<()> = reader.next_call().await.unwrap();
+            let writer = Arc::new(Mutex::new(writer));
+            send_response(writer, Ok(call.method_name), call.request_id, false)
+                .await
+                .unwrap();
+        });
+
+        address
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_calls_are_sent_again() {
+        let address = flaky_server().await;
+        let mut client = DefaultRawRpcClient::connect(&address).await.unwrap();
+        let options = CallOptions {
+            timeout: Some(Duration::from_secs(5)),
+            idempotent: true,
+        };
+
+        let response: String = client
+            .send_rpc_with_options(0, "get", &(), &(), options)
+            .await
+            .unwrap();
+
+        assert_eq!(response, "get");
+    }
+
+    #[tokio::test]
+    async fn test_other_calls_fail_when_the_connection_is_lost() {
+        let address = flaky_server().await;
+        let mut client = DefaultRawRpcClient::connect(&address).await.unwrap();
+
+        let result: Result<String, _> = client
+            .send_rpc_with_options(0, "set", &(), &(), CallOptions::default())
+            .await;
+
+        assert!(matches!(result, Err(RpcError::ConnectionLost(_))));
+    }
+
+    #[tokio::test]
+    async fn test_calls_time_out() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut client = DefaultRawRpcClient::connect(&address).await.unwrap();
+        let options = CallOptions {
+            timeout: Some(Duration::from_millis(10)),
+            idempotent: false,
+        };
+
+        let result: Result<String, _> = client
+            .send_rpc_with_options(0, "get", &(), &(), options)
+            .await;
+
+        assert!(matches!(result, Err(RpcError::Timeout(method_name)) if method_name == "get"));
+        assert!(client.waiting_responses.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stream_errors_are_passed_on() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (mut reader, writer) = split_connection(socket);
+            let call: Call<()> = reader.next_call().await.unwrap();
+            let writer: Arc<Mutex<dyn Client>> = Arc::new(Mutex::new(writer));
+            send_stream_response::<()>(
+                writer,
+                Err(RpcError::Unauthorized("watch".to_string())),
+                call.request_id,
+            )
+            .await
+            .unwrap();
+        });
+        let mut client = DefaultRawRpcClient::connect(&address).await.unwrap();
+
+        let responses: Vec<Result<String, _>> = client
+            .send_rpc_stream_request(0, "watch", &(), &())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        assert!(matches!(
+            responses.as_slice(),
+            [Err(RpcError::Unauthorized(_))]
+        ));
+    }
 }
@@ -1,3 +1,4 @@
+use crate::validation::ValidationError;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::sync::mpsc::error::SendError;
@@ -12,6 +13,29 @@ pub enum RpcError {
     MpscError(String),
     #[error("{0}")]
     Custom(String),
+    /// The request breaks the constraints of its fields
+    #[error("The request is invalid: {}", join_errors(.0))]
+    Validation(Vec<ValidationError>),
+    /// No response arrived within the timeout of the method
+    #[error("The call of {0} timed out")]
+    Timeout(String),
+    /// The connection was lost before the response arrived
+    #[error("The connection was lost: {0}")]
+    ConnectionLost(String),
+    /// The caller lacks a scope that the method requires
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    /// The server has no method by the name
+    #[error("Unknown method: {0}")]
+    UnknownMethod(String),
+}
+
+fn join_errors(errors: &[ValidationError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl From<serde_json::Error> for RpcError {
@@ -1,45 +1,163 @@
-use serde::de::{Error, Visitor};
+use crate::formatted::Formatted;
+use serde::de::Error;
 use serde::ser::Error as SerError;
-use serde::{Deserializer, Serializer};
-use std::fmt::Formatter;
-use std::ops::Add;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::time::{Duration, SystemTime};
 
-struct TimestampVisitor;
+/// Times as the seconds and nanoseconds since the Unix epoch, in the representation serde uses for
+/// `SystemTime`, except that the seconds are negative for times before the epoch. The nanoseconds are
+/// always positive, so half a second before the epoch is `{"secs_since_epoch": -1, "nanos_since_epoch": 500000000}`.
+///
+/// Plain integers, which were used before, are read as whole seconds since the epoch.
+pub struct UnixTimestamp;
 
-impl<'de> Visitor<'de> for TimestampVisitor {
-    type Value = u64;
+#[derive(Serialize, Deserialize)]
+struct Timestamp {
+    secs_since_epoch: i64,
+    nanos_since_epoch: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyTimestamp {
+    Seconds(i64),
+    Precise(Timestamp),
+}
+
+impl Formatted<UnixTimestamp> for SystemTime {
+    fn serialize_formatted<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let out_of_range = || S::Error::custom("The timestamp does not fit in 64 bits");
 
-    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
-        write!(formatter, "u64")
+        let timestamp = match self.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(since) => Timestamp {
+                secs_since_epoch: i64::try_from(since.as_secs()).map_err(|_| out_of_range())?,
+                nanos_since_epoch: since.subsec_nanos(),
+            },
+            Err(error) => {
+                let before = error.duration();
+                let seconds = i64::try_from(before.as_secs()).map_err(|_| out_of_range())?;
+
+                if before.subsec_nanos() == 0 {
+                    Timestamp {
+                        secs_since_epoch: -seconds,
+                        nanos_since_epoch: 0,
+                    }
+                } else {
+                    Timestamp {
+                        secs_since_epoch: -seconds - 1,
+                        nanos_since_epoch: 1_000_000_000 - before.subsec_nanos(),
+                    }
+                }
+            }
+        };
+
+        timestamp.serialize(serializer)
     }
 
-    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    fn deserialize_formatted<'de, D>(deserializer: D) -> Result<Self, D::Error>
     where
-        E: Error,
+        D: Deserializer<'de>,
     {
-        Ok(v)
+        let timestamp = match AnyTimestamp::deserialize(deserializer)? {
+            AnyTimestamp::Seconds(seconds) => Timestamp {
+                secs_since_epoch: seconds,
+                nanos_since_epoch: 0,
+            },
+            AnyTimestamp::Precise(timestamp) => timestamp,
+        };
+
+        if timestamp.nanos_since_epoch >= 1_000_000_000 {
+            return Err(D::Error::custom(
+                "The nanoseconds of a timestamp must be less than a second",
+            ));
+        }
+
+        let seconds = Duration::from_secs(timestamp.secs_since_epoch.unsigned_abs());
+        let time = if timestamp.secs_since_epoch >= 0 {
+            SystemTime::UNIX_EPOCH.checked_add(seconds)
+        } else {
+            SystemTime::UNIX_EPOCH.checked_sub(seconds)
+        };
+
+        time.and_then(|time| {
+            time.checked_add(Duration::from_nanos(timestamp.nanos_since_epoch.into()))
+        })
+        .ok_or_else(|| D::Error::custom("The timestamp is out of range"))
     }
 }
 
 /// # Errors
-/// Can fail if the `SystemTime` was before `UNIX_EPOCH`
-pub fn serialize<S>(val: &SystemTime, ser: S) -> Result<S::Ok, S::Error>
+/// Can fail if a time is more than 2^63 seconds away from `UNIX_EPOCH`
+pub fn serialize<T, S>(val: &T, ser: S) -> Result<S::Ok, S::Error>
 where
+    T: Formatted<UnixTimestamp>,
     S: Serializer,
 {
-    ser.serialize_u64(
-        val.duration_since(SystemTime::UNIX_EPOCH)
-            .map_err(|_| S::Error::custom("The timestamp was before Unix Epoch?"))?
-            .as_secs(),
-    )
+    val.serialize_formatted(ser)
 }
 
 /// # Errors
-/// Can fail if the value is not `u64`
-pub fn deserialize<'de, D>(des: D) -> Result<SystemTime, D::Error>
+/// Can fail if a value is neither an integer nor an object with the seconds and nanoseconds
+pub fn deserialize<'de, T, D>(des: D) -> Result<T, D::Error>
 where
+    T: Formatted<UnixTimestamp>,
     D: Deserializer<'de>,
 {
-    Ok(SystemTime::UNIX_EPOCH.add(Duration::from_secs(des.deserialize_u64(TimestampVisitor)?)))
+    T::deserialize_formatted(des)
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+    use std::time::{Duration, SystemTime};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Times {
+        #[serde(with = "crate::system_time_serializer")]
+        time: SystemTime,
+        #[serde(with = "crate::system_time_serializer", default)]
+        times: Vec<SystemTime>,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let times = Times {
+            time: SystemTime::UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789),
+            times: vec![SystemTime::UNIX_EPOCH - Duration::from_millis(1500)],
+        };
+
+        let json = serde_json::to_string(&times).unwrap();
+
+        assert_eq!(
+            json,
+            "{\"time\":{\"secs_since_epoch\":1600000000,\"nanos_since_epoch\":123456789},\
+             \"times\":[{\"secs_since_epoch\":-2,\"nanos_since_epoch\":500000000}]}"
+        );
+        assert_eq!(serde_json::from_str::<Times>(&json).unwrap(), times);
+    }
+
+    #[test]
+    fn test_reads_whole_seconds() {
+        let times: Times = serde_json::from_str("{\"time\":1024,\"times\":[-1]}").unwrap();
+
+        assert_eq!(
+            times.time,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1024)
+        );
+        assert_eq!(
+            times.times,
+            [SystemTime::UNIX_EPOCH - Duration::from_secs(1)]
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_nanoseconds() {
+        assert!(serde_json::from_str::<Times>(
+            "{\"time\":{\"secs_since_epoch\":0,\"nanos_since_epoch\":1000000000}}"
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,82 @@
+use crate::rpc_error::RpcError;
+use crate::DefaultRawRpcClient;
+use std::collections::VecDeque;
+use tokio::io::DuplexStream;
+
+// Large enough that the calls of a test do not wait for the other end to read them
+const LOOPBACK_BUFFER: usize = 64 * 1024;
+
+/// A client connected to the returned in-memory connection, which a server can serve like a TCP
+/// connection. Used by the generated `testing::loopback` functions.
+///
+/// # Panics
+/// Panics when called outside of a Tokio runtime
+#[must_use]
+pub fn loopback() -> (DefaultRawRpcClient, DuplexStream) {
+    let (client, server) = tokio::io::duplex(LOOPBACK_BUFFER);
+
+    (DefaultRawRpcClient::from_stream(client), server)
+}
+
+/// One method of a generated `MockRpcClient`. Its calls are recorded together with their
+/// metadata, and answered with the responses they were expected with, in the order of the
+/// expectations. The responses of a stream are given as a `Vec`, and so are the requests.
+pub struct MockMethod<TRequest, TMetadata, TResponse> {
+    name: &'static str,
+    responses: VecDeque<Result<TResponse, RpcError>>,
+    calls: Vec<(TRequest, TMetadata)>,
+}
+
+impl<TRequest, TMetadata, TResponse> MockMethod<TRequest, TMetadata, TResponse> {
+    #[must_use]
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            responses: VecDeque::new(),
+            calls: vec![],
+        }
+    }
+
+    /// Expects one more call, which is answered with `response`
+    pub fn expect(&mut self, response: Result<TResponse, RpcError>) -> &mut Self {
+        self.responses.push_back(response);
+        self
+    }
+
+    /// The requests and metadata of the calls made so far
+    #[must_use]
+    pub fn calls(&self) -> &[(TRequest, TMetadata)] {
+        &self.calls
+    }
+
+    /// Records the call and answers it with the response of the oldest expectation
+    ///
+    /// # Errors
+    /// Returns the error the call was expected with
+    ///
+    /// # Panics
+    /// Panics if every expected call was made already
+    pub fn call(&mut self, request: TRequest, metadata: TMetadata) -> Result<TResponse, RpcError> {
+        self.calls.push((request, metadata));
+
+        self.responses.pop_front().unwrap_or_else(|| {
+            panic!(
+                "{} was called {} times, but expected fewer calls",
+                self.name,
+                self.calls.len()
+            )
+        })
+    }
+
+    /// # Panics
+    /// Panics if some of the expected calls were not made
+    pub fn verify(&self) {
+        assert!(
+            self.responses.is_empty(),
+            "{} was called {} times, but expected {} more calls",
+            self.name,
+            self.calls.len(),
+            self.responses.len()
+        );
+    }
+}
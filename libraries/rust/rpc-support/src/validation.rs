@@ -0,0 +1,261 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::OnceLock;
+
+/// A value that breaks one of the constraints of its field
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// Where the value is in the message, like `mounts[2].mount_id`
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// The location of a value in a message. It is only formatted when the value is invalid, so that
+/// validating valid messages does not allocate.
+pub enum Path<'a> {
+    Root,
+    Field(&'a Path<'a>, &'a str),
+    Index(&'a Path<'a>, usize),
+    Key(&'a Path<'a>, &'a dyn Debug),
+}
+
+impl Display for Path<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Path::Root => Ok(()),
+            Path::Field(Path::Root, name) => write!(f, "{name}"),
+            Path::Field(parent, name) => write!(f, "{parent}.{name}"),
+            Path::Index(parent, index) => write!(f, "{parent}[{index}]"),
+            Path::Key(parent, key) => write!(f, "{parent}[{key:?}]"),
+        }
+    }
+}
+
+/// Checks the constraints of the fields of a message, implemented by the generated types
+pub trait Validate {
+    /// Adds an error for every value at or below `path` that breaks a constraint
+    fn validate_at(&self, path: &Path, errors: &mut Vec<ValidationError>);
+
+    /// # Errors
+    /// Returns an error for every value that breaks a constraint
+    fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = vec![];
+        self.validate_at(&Path::Root, &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl<T: Validate> Validate for Option<T> {
+    fn validate_at(&self, path: &Path, errors: &mut Vec<ValidationError>) {
+        if let Some(value) = self {
+            value.validate_at(path, errors);
+        }
+    }
+}
+
+impl<T: Validate> Validate for Box<T> {
+    fn validate_at(&self, path: &Path, errors: &mut Vec<ValidationError>) {
+        self.as_ref().validate_at(path, errors);
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate_at(&self, path: &Path, errors: &mut Vec<ValidationError>) {
+        for (index, value) in self.iter().enumerate() {
+            value.validate_at(&Path::Index(path, index), errors);
+        }
+    }
+}
+
+impl<K: Debug, T: Validate> Validate for BTreeMap<K, T> {
+    fn validate_at(&self, path: &Path, errors: &mut Vec<ValidationError>) {
+        for (key, value) in self {
+            value.validate_at(&Path::Key(path, key), errors);
+        }
+    }
+}
+
+/// The regular expression of a `@pattern` constraint, compiled when it is first used
+pub struct Pattern {
+    source: &'static str,
+    regex: OnceLock<Regex>,
+}
+
+impl Pattern {
+    #[must_use]
+    pub const fn new(source: &'static str) -> Self {
+        Self {
+            source,
+            regex: OnceLock::new(),
+        }
+    }
+
+    /// # Panics
+    /// Will panic if the pattern is not a valid regular expression, which the message compiler rejects
+    #[must_use]
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex
+            .get_or_init(|| Regex::new(&format!("^(?:{})$", self.source)).unwrap())
+            .is_match(value)
+    }
+}
+
+fn push_error(path: &Path, message: String, errors: &mut Vec<ValidationError>) {
+    errors.push(ValidationError {
+        path: path.to_string(),
+        message,
+    });
+}
+
+pub fn check_pattern(
+    value: &str,
+    pattern: &Pattern,
+    path: &Path,
+    errors: &mut Vec<ValidationError>,
+) {
+    if !pattern.is_match(value) {
+        push_error(
+            path,
+            format!("must match the pattern \"{}\"", pattern.source),
+            errors,
+        );
+    }
+}
+
+pub fn check_length(
+    length: usize,
+    min: Option<u64>,
+    max: Option<u64>,
+    path: &Path,
+    errors: &mut Vec<ValidationError>,
+) {
+    let length = u64::try_from(length).unwrap_or(u64::MAX);
+
+    if let Some(min) = min.filter(|min| length < *min) {
+        push_error(
+            path,
+            format!("must have a length of at least {min}"),
+            errors,
+        );
+    }
+    if let Some(max) = max.filter(|max| length > *max) {
+        push_error(path, format!("must have a length of at most {max}"), errors);
+    }
+}
+
+/// `NaN` is outside of every range
+pub fn check_range<T: PartialOrd + Display>(
+    value: T,
+    min: Option<T>,
+    max: Option<T>,
+    path: &Path,
+    errors: &mut Vec<ValidationError>,
+) {
+    if let Some(min) = min {
+        if matches!(value.partial_cmp(&min), Some(Ordering::Less) | None) {
+            push_error(path, format!("must be at least {min}"), errors);
+        }
+    }
+    if let Some(max) = max {
+        if matches!(value.partial_cmp(&max), Some(Ordering::Greater) | None) {
+            push_error(path, format!("must be at most {max}"), errors);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Mount {
+        mount_id: String,
+        size: u32,
+    }
+
+    impl Validate for Mount {
+        fn validate_at(&self, path: &Path, errors: &mut Vec<ValidationError>) {
+            static PATTERN: Pattern = Pattern::new("[a-z0-9-]+");
+
+            check_pattern(
+                &self.mount_id,
+                &PATTERN,
+                &Path::Field(path, "mount_id"),
+                errors,
+            );
+            check_range(
+                self.size,
+                Some(1),
+                Some(10),
+                &Path::Field(path, "size"),
+                errors,
+            );
+        }
+    }
+
+    #[test]
+    fn test_collects_errors_with_paths() {
+        let mounts = BTreeMap::from([(
+            "a".to_string(),
+            vec![
+                Mount {
+                    mount_id: "valid-1".to_string(),
+                    size: 5,
+                },
+                Mount {
+                    mount_id: "Invalid-1".to_string(),
+                    size: 11,
+                },
+            ],
+        )]);
+
+        assert_eq!(
+            mounts.validate(),
+            Err(vec![
+                ValidationError {
+                    path: "[\"a\"][1].mount_id".to_string(),
+                    message: "must match the pattern \"[a-z0-9-]+\"".to_string(),
+                },
+                ValidationError {
+                    path: "[\"a\"][1].size".to_string(),
+                    message: "must be at most 10".to_string(),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_patterns_match_the_whole_value() {
+        let pattern = Pattern::new("a|b");
+
+        assert!(pattern.is_match("a"));
+        assert!(!pattern.is_match("ab"));
+    }
+
+    #[test]
+    fn test_checks_lengths_and_ranges() {
+        let mut errors = vec![];
+        check_length(0, Some(1), None, &Path::Root, &mut errors);
+        check_length(3, None, Some(3), &Path::Root, &mut errors);
+        check_range(f64::NAN, Some(0.0), None, &Path::Root, &mut errors);
+
+        let messages: Vec<_> = errors.iter().map(|error| error.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            ["must have a length of at least 1", "must be at least 0"]
+        );
+    }
+}
@@ -7,7 +7,6 @@ use platform::async_infra::run_with_error_handling;
 use platform::secrets::SecretProvider;
 use rpc_support::DefaultRawRpcClient;
 use std::sync::Arc;
-use tokio::net::TcpStream;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
@@ -39,12 +38,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await?,
     ));
     let file_status_store = Postgres::new(pg_client.clone());
-    let event_service = events::Client::new(DefaultRawRpcClient::new(
-        TcpStream::connect("svc-events:7654").await?,
-    ));
+    let event_service = events::Client::new(DefaultRawRpcClient::connect("svc-events:7654").await?);
     let rpc_server = RpcServer::new(file_status_store, event_service, Box::new(Uuid::new_v4));
     // TODO: make the bind addr/port configurable
-    let server = Server::new("0.0.0.0:7655", Arc::new(Mutex::new(rpc_server))).await?;
+    let server = Server::new("0.0.0.0:7655", Arc::new(rpc_server)).await?;
 
     tokio::spawn(run_with_error_handling(async move { server.run().await }));
 
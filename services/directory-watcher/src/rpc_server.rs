@@ -1,66 +1,84 @@
-use std::{
-    ops::Add,
-    sync::Weak,
-    time::{Duration, SystemTime},
-};
+use std::sync::Weak;
+use std::time::SystemTime;
 
-use events::{Client, RpcClient as EventsRpc};
+use events::RpcClient as EventsRpc;
 use lib_directory_watcher::{FilesystemEvent, FilesystemEventKind, Metadata, RpcServer as Rpc};
 use platform::mounts::PathInside;
-use rpc_support::{rpc_error::RpcError, Client as RpcClient, RawRpcClient};
+use rpc_support::validation::ValidationError;
+use rpc_support::{rpc_error::RpcError, Client as RpcClient};
 use time::OffsetDateTime;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::file_status_store::FileStatusStore;
 
-pub struct RpcServer<T: FileStatusStore + Sync + Send, TRawRpcClient: RawRpcClient + Send + Sync> {
-    file_status_store: T,
-    event_service: Client<TRawRpcClient>,
+pub struct RpcServer<T: FileStatusStore + Sync + Send, TEvents: EventsRpc + Send + Sync> {
+    // The calls are served concurrently, but the store and the client can only make one at a time.
+    // The store is locked until the event of its update is sent, so that the events of a path are
+    // sent in the order that the path was updated in
+    file_status_store: Mutex<T>,
+    event_service: Mutex<TEvents>,
     generate_uuid: Box<dyn Fn() -> Uuid + Send + Sync>,
 }
 
-impl<T: FileStatusStore + Sync + Send, TRawRpcClient: RawRpcClient + Send + Sync>
-    RpcServer<T, TRawRpcClient>
-{
-    pub const fn new(
+impl<T: FileStatusStore + Sync + Send, TEvents: EventsRpc + Send + Sync> RpcServer<T, TEvents> {
+    pub fn new(
         file_status_store: T,
-        event_service: events::Client<TRawRpcClient>,
+        event_service: TEvents,
         generate_uuid: Box<dyn Fn() -> Uuid + Send + Sync>,
     ) -> Self {
         Self {
-            file_status_store,
-            event_service,
+            file_status_store: Mutex::new(file_status_store),
+            event_service: Mutex::new(event_service),
             generate_uuid,
         }
     }
 }
 
+// `OffsetDateTime::from` panics for the instants it cannot represent, which clients can send
+fn to_offset_date_time(instant: SystemTime) -> Result<OffsetDateTime, RpcError> {
+    let converted = match instant.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(after) => time::Duration::try_from(after)
+            .ok()
+            .and_then(|after| OffsetDateTime::UNIX_EPOCH.checked_add(after)),
+        Err(before) => time::Duration::try_from(before.duration())
+            .ok()
+            .and_then(|before| OffsetDateTime::UNIX_EPOCH.checked_sub(before)),
+    };
+
+    converted.ok_or_else(|| {
+        RpcError::Validation(vec![ValidationError {
+            path: "timestamp".to_string(),
+            message: "is out of the supported range".to_string(),
+        }])
+    })
+}
+
 #[async_trait::async_trait]
-impl<T: FileStatusStore + Sync + Send, TRawRpcClient: RawRpcClient + Send + Sync> Rpc
-    for RpcServer<T, TRawRpcClient>
+impl<T: FileStatusStore + Sync + Send, TEvents: EventsRpc + Send + Sync> Rpc
+    for RpcServer<T, TEvents>
 {
-    #[allow(clippy::too_many_lines)]
+    #[allow(clippy::too_many_lines, clippy::significant_drop_tightening)]
     async fn file_changed(
-        &mut self,
+        &self,
         event: FilesystemEvent,
         _metadata: Metadata,
         _client: Weak<Mutex<dyn RpcClient>>,
     ) -> Result<(), RpcError> {
         info!("Received file changed event: {:?}", event);
+        // `file_changed` is idempotent, so a call that is sent again produces an event with the
+        // same ID, which the event service stores only once
+        let id = event
+            .idempotency_key
+            .unwrap_or_else(|| (self.generate_uuid)());
+        // Held until the event is sent, see `file_status_store`
+        let mut file_status_store = self.file_status_store.lock().await;
 
         match event.kind {
-            FilesystemEventKind::Created {} | FilesystemEventKind::Modified {} => {
-                let timestamp = OffsetDateTime::from_unix_timestamp(
-                    event
-                        .timestamp
-                        .try_into()
-                        .map_err(|e| RpcError::Custom(format!("{e}")))?,
-                )
-                .map_err(|e| RpcError::Custom(format!("{e}")))?;
+            FilesystemEventKind::Created | FilesystemEventKind::Modified => {
+                let timestamp = to_offset_date_time(event.timestamp)?;
 
-                let sync_status = self
-                    .file_status_store
+                let sync_status = file_status_store
                     .sync(
                         &PathInside::new(event.mount_id.clone(), event.path.clone()),
                         timestamp,
@@ -89,22 +107,24 @@ impl<T: FileStatusStore + Sync + Send, TRawRpcClient: RawRpcClient + Send + Sync
                 };
 
                 self.event_service
+                    .lock()
+                    .await
                     .send_event(
                         events::Event {
-                            id: (self.generate_uuid)(),
+                            id,
                             created_time: timestamp.into(),
                             data,
                         },
                         events::Metadata {
                             // TODO pass the correlation ID from parent scope!
-                            correlation_id: (self.generate_uuid)(),
+                            correlation_id: id,
                             source: "directory-watcher".to_string(),
                         },
                     )
                     .await?;
             }
             FilesystemEventKind::Moved { to } => {
-                self.file_status_store
+                file_status_store
                     .rename(
                         &PathInside::new(event.mount_id.clone(), event.path.clone()),
                         &PathInside::new(event.mount_id.clone(), to.clone()),
@@ -113,11 +133,12 @@ impl<T: FileStatusStore + Sync + Send, TRawRpcClient: RawRpcClient + Send + Sync
                     .map_err(|e| RpcError::Custom(format!("{e}")))?;
 
                 self.event_service
+                    .lock()
+                    .await
                     .send_event(
                         events::Event {
-                            id: (self.generate_uuid)(),
-                            created_time: SystemTime::UNIX_EPOCH
-                                .add(Duration::from_secs(event.timestamp)),
+                            id,
+                            created_time: event.timestamp,
                             data: events::EventKind::FileMoved {
                                 from: events::FileOnMountPath {
                                     path: event.path,
@@ -131,24 +152,25 @@ impl<T: FileStatusStore + Sync + Send, TRawRpcClient: RawRpcClient + Send + Sync
                         },
                         events::Metadata {
                             // TODO pass the correlation ID from parent scope!
-                            correlation_id: (self.generate_uuid)(),
+                            correlation_id: id,
                             source: "directory-watcher".to_string(),
                         },
                     )
                     .await?;
             }
-            FilesystemEventKind::Deleted {} => {
-                self.file_status_store
+            FilesystemEventKind::Deleted => {
+                file_status_store
                     .delete(&PathInside::new(event.mount_id.clone(), event.path.clone()))
                     .await
                     .map_err(|e| RpcError::Custom(format!("{e}")))?;
 
                 self.event_service
+                    .lock()
+                    .await
                     .send_event(
                         events::Event {
-                            id: (self.generate_uuid)(),
-                            created_time: SystemTime::UNIX_EPOCH
-                                .add(Duration::from_secs(event.timestamp)),
+                            id,
+                            created_time: event.timestamp,
                             data: events::EventKind::FileDeleted {
                                 path: events::FileOnMountPath {
                                     path: event.path,
@@ -158,7 +180,7 @@ impl<T: FileStatusStore + Sync + Send, TRawRpcClient: RawRpcClient + Send + Sync
                         },
                         events::Metadata {
                             // TODO pass the correlation ID from parent scope!
-                            correlation_id: (self.generate_uuid)(),
+                            correlation_id: id,
                             source: "directory-watcher".to_string(),
                         },
                     )
@@ -174,10 +196,11 @@ impl<T: FileStatusStore + Sync + Send, TRawRpcClient: RawRpcClient + Send + Sync
 mod tests {
     use std::str::FromStr;
     use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
 
-    use rpc_support::ResponseStream;
-    use serde::{de::DeserializeOwned, Serialize};
-    use tokio::sync::Mutex;
+    use events::testing::MockRpcClient;
+    use lib_directory_watcher::RpcClient as _;
+    use rpc_support::DefaultRawRpcClient;
 
     use super::*;
     use crate::file_status_store::Error;
@@ -186,6 +209,7 @@ mod tests {
     struct MockFileStatusStore {
         pub events: Vec<String>,
         pub sync_result: FileStatusSyncResult,
+        pub sync_duration: Duration,
     }
 
     impl MockFileStatusStore {
@@ -193,6 +217,7 @@ mod tests {
             Self {
                 events: vec![],
                 sync_result,
+                sync_duration: Duration::ZERO,
             }
         }
     }
@@ -218,199 +243,199 @@ mod tests {
         ) -> Result<FileStatusSyncResult, Error> {
             self.events
                 .push(format!("sync {path:?} at {modified_at:?}"));
+            tokio::time::sleep(self.sync_duration).await;
 
             Ok(self.sync_result)
         }
     }
 
-    struct MockRawRpcClient {
-        pub rpcs: Arc<Mutex<Vec<String>>>,
-    }
-
-    #[async_trait::async_trait]
-    impl RawRpcClient for MockRawRpcClient {
-        // TODO rename to send_rpc_request
-        async fn send_rpc<TRequest, TMetadata, TResponse>(
-            &mut self,
-            id: u64,
-            method_name: &str,
-            request: &TRequest,
-            metadata: &TMetadata,
-        ) -> Result<TResponse, RpcError>
-        where
-            TRequest: Serialize + Sync + Send,
-            TMetadata: Serialize + Sync + Send,
-            TResponse: DeserializeOwned,
-        {
-            self.rpcs.lock().await.push(format!(
-                "send_rpc {id} {method_name} {:?} {:?}",
-                serde_json::to_value(request),
-                serde_json::to_value(metadata)
-            ));
-
-            Ok(serde_json::from_str("null").unwrap())
+    type TestServer = RpcServer<MockFileStatusStore, MockRpcClient>;
+
+    // The calls of the client are served by the server, which expects to send `sent_events` events
+    fn serve(
+        sync_result: FileStatusSyncResult,
+        sent_events: usize,
+    ) -> (
+        Arc<TestServer>,
+        lib_directory_watcher::Client<DefaultRawRpcClient>,
+    ) {
+        let mut event_service = MockRpcClient::default();
+        for _ in 0..sent_events {
+            event_service.send_event.expect(Ok(()));
         }
 
-        async fn send_rpc_stream_request<TRequest, TMetadata, TResponse>(
-            &mut self,
-            _request_id: u64,
-            _method_name: &str,
-            _request: &TRequest,
-            _metadata: &TMetadata,
-        ) -> Result<ResponseStream<TResponse>, RpcError>
-        where
-            TRequest: Serialize + Sync + Send,
-            TMetadata: Serialize + Sync + Send,
-            TResponse: DeserializeOwned,
-        {
-            todo!()
-        }
+        let rpc_server = Arc::new(RpcServer::new(
+            MockFileStatusStore::new(sync_result),
+            event_service,
+            Box::new(|| Uuid::from_str("00000000-0000-0000-0000-000000000000").unwrap()),
+        ));
+        let client = lib_directory_watcher::testing::loopback(rpc_server.clone());
+
+        (rpc_server, client)
     }
 
-    #[tokio::test]
-    async fn test_file_created() {
-        let file_status_store = MockFileStatusStore::new(FileStatusSyncResult::Created);
-        let rpcs = Arc::new(Mutex::new(vec![]));
-        let raw_rpc_client = MockRawRpcClient { rpcs: rpcs.clone() };
+    async fn store_events(rpc_server: &TestServer) -> Vec<String> {
+        rpc_server.file_status_store.lock().await.events.clone()
+    }
 
-        let event_service = events::Client::new(raw_rpc_client);
+    async fn sent_events(rpc_server: &TestServer) -> Vec<(events::Event, events::Metadata)> {
+        let event_service = rpc_server.event_service.lock().await;
+        event_service.verify();
 
-        let mut rpc_server = RpcServer::new(
-            file_status_store,
-            event_service,
-            Box::new(|| Uuid::from_str("00000000-0000-0000-0000-000000000000").unwrap()),
-        );
+        event_service.send_event.calls().to_vec()
+    }
 
-        let event = FilesystemEvent {
-            kind: FilesystemEventKind::Created {},
+    fn event(kind: FilesystemEventKind) -> FilesystemEvent {
+        FilesystemEvent {
+            kind,
             path: "/test".to_string(),
             mount_id: "test".to_string(),
-            timestamp: 1024,
-        };
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1024),
+            idempotency_key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_created() {
+        let (rpc_server, mut client) = serve(FileStatusSyncResult::Created, 1);
 
-        rpc_server
-            .file_changed(event, Metadata {}, Weak::<Mutex<MockClient>>::new())
+        client
+            .file_changed(event(FilesystemEventKind::Created), Metadata {})
             .await
             .unwrap();
 
-        insta::assert_debug_snapshot!(rpc_server.file_status_store.events);
+        insta::assert_debug_snapshot!(store_events(&rpc_server).await);
+        insta::assert_debug_snapshot!(sent_events(&rpc_server).await);
+    }
 
-        let rpcs = rpcs.lock().await;
+    #[tokio::test]
+    async fn test_file_moved() {
+        let (rpc_server, mut client) = serve(FileStatusSyncResult::Created, 1);
+
+        client
+            .file_changed(
+                event(FilesystemEventKind::Moved {
+                    to: "/test2".to_string(),
+                }),
+                Metadata {},
+            )
+            .await
+            .unwrap();
 
-        insta::assert_debug_snapshot!(rpcs.clone());
+        insta::assert_debug_snapshot!(store_events(&rpc_server).await);
+        insta::assert_debug_snapshot!(sent_events(&rpc_server).await);
     }
 
-    struct MockClient;
-
-    #[async_trait::async_trait]
-    impl rpc_support::Client for MockClient {
-        async fn write_all(&mut self, _data: &[u8]) -> std::io::Result<()> {
-            todo!();
+    #[tokio::test]
+    async fn test_retried_calls_send_the_same_event() {
+        let (rpc_server, mut client) = serve(FileStatusSyncResult::Created, 2);
+        let idempotency_key = Uuid::from_u128(1);
+
+        for _ in 0..2 {
+            client
+                .file_changed(
+                    FilesystemEvent {
+                        idempotency_key: Some(idempotency_key),
+                        ..event(FilesystemEventKind::Moved {
+                            to: "/test2".to_string(),
+                        })
+                    },
+                    Metadata {},
+                )
+                .await
+                .unwrap();
         }
 
-        async fn read_line(&mut self) -> std::io::Result<String> {
-            todo!();
-        }
+        let sent_events = sent_events(&rpc_server).await;
+        assert_eq!(sent_events[0].0.id, idempotency_key);
+        assert_eq!(sent_events[1].0.id, idempotency_key);
     }
 
     #[tokio::test]
-    async fn test_file_moved() {
-        let file_status_store = MockFileStatusStore::new(FileStatusSyncResult::Created);
-        let rpcs = Arc::new(Mutex::new(vec![]));
-        let raw_rpc_client = MockRawRpcClient { rpcs: rpcs.clone() };
-
-        let event_service = events::Client::new(raw_rpc_client);
+    async fn test_repeated_events_are_sent_as_different_events() {
+        let (rpc_server, mut client) = serve(FileStatusSyncResult::Created, 2);
+
+        // The same file is moved twice, with the same timestamp
+        for key in [1, 2] {
+            client
+                .file_changed(
+                    FilesystemEvent {
+                        idempotency_key: Some(Uuid::from_u128(key)),
+                        ..event(FilesystemEventKind::Moved {
+                            to: "/test2".to_string(),
+                        })
+                    },
+                    Metadata {},
+                )
+                .await
+                .unwrap();
+        }
 
-        let mut rpc_server = RpcServer::new(
-            file_status_store,
-            event_service,
-            Box::new(|| Uuid::from_str("00000000-0000-0000-0000-000000000000").unwrap()),
-        );
+        let sent_events = sent_events(&rpc_server).await;
+        assert_eq!(sent_events.len(), 2);
+        assert_ne!(sent_events[0].0.id, sent_events[1].0.id);
+    }
 
-        let event = FilesystemEvent {
-            kind: FilesystemEventKind::Moved {
-                to: "/test2".to_string(),
-            },
-            path: "/test".to_string(),
-            mount_id: "test".to_string(),
-            timestamp: 1024,
-        };
+    #[tokio::test]
+    async fn test_file_deleted() {
+        let (rpc_server, mut client) = serve(FileStatusSyncResult::Created, 1);
 
-        rpc_server
-            .file_changed(event, Metadata {}, Weak::<Mutex<MockClient>>::new())
+        client
+            .file_changed(event(FilesystemEventKind::Deleted), Metadata {})
             .await
             .unwrap();
 
-        insta::assert_debug_snapshot!(rpc_server.file_status_store.events);
-
-        let rpcs = rpcs.lock().await;
-
-        insta::assert_debug_snapshot!(rpcs.clone());
+        insta::assert_debug_snapshot!(store_events(&rpc_server).await);
+        insta::assert_debug_snapshot!(sent_events(&rpc_server).await);
     }
 
     #[tokio::test]
-    async fn test_file_deleted() {
-        let file_status_store = MockFileStatusStore::new(FileStatusSyncResult::Created);
-        let rpcs = Arc::new(Mutex::new(vec![]));
-        let raw_rpc_client = MockRawRpcClient { rpcs: rpcs.clone() };
-
-        let event_service = events::Client::new(raw_rpc_client);
-
-        let mut rpc_server = RpcServer::new(
-            file_status_store,
-            event_service,
-            Box::new(|| Uuid::from_str("00000000-0000-0000-0000-000000000000").unwrap()),
-        );
-
-        let event = FilesystemEvent {
-            kind: FilesystemEventKind::Deleted {},
-            path: "/test".to_string(),
-            mount_id: "test".to_string(),
-            timestamp: 1024,
-        };
+    async fn test_file_modified() {
+        let (rpc_server, mut client) = serve(FileStatusSyncResult::Modified, 1);
 
-        rpc_server
-            .file_changed(event, Metadata {}, Weak::<Mutex<MockClient>>::new())
+        client
+            .file_changed(event(FilesystemEventKind::Modified), Metadata {})
             .await
             .unwrap();
 
-        insta::assert_debug_snapshot!(rpc_server.file_status_store.events);
-
-        let rpcs = rpcs.lock().await;
-
-        insta::assert_debug_snapshot!(rpcs.clone());
+        insta::assert_debug_snapshot!(store_events(&rpc_server).await);
+        insta::assert_debug_snapshot!(sent_events(&rpc_server).await);
     }
 
     #[tokio::test]
-    async fn test_file_modified() {
-        let file_status_store = MockFileStatusStore::new(FileStatusSyncResult::Modified);
-        let rpcs = Arc::new(Mutex::new(vec![]));
-        let raw_rpc_client = MockRawRpcClient { rpcs: rpcs.clone() };
-
-        let event_service = events::Client::new(raw_rpc_client);
-
-        let mut rpc_server = RpcServer::new(
-            file_status_store,
-            event_service,
-            Box::new(|| Uuid::from_str("00000000-0000-0000-0000-000000000000").unwrap()),
-        );
+    async fn test_timestamp_out_of_range() {
+        let (rpc_server, mut client) = serve(FileStatusSyncResult::Created, 0);
 
+        // Far beyond the year 9999, which is the last one `OffsetDateTime` supports
         let event = FilesystemEvent {
-            kind: FilesystemEventKind::Modified {},
-            path: "/test".to_string(),
-            mount_id: "test".to_string(),
-            timestamp: 1024,
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1 << 40),
+            ..event(FilesystemEventKind::Created)
         };
 
-        rpc_server
-            .file_changed(event, Metadata {}, Weak::<Mutex<MockClient>>::new())
-            .await
-            .unwrap();
-
-        insta::assert_debug_snapshot!(rpc_server.file_status_store.events);
+        let result = client.file_changed(event, Metadata {}).await;
 
-        let rpcs = rpcs.lock().await;
+        assert!(matches!(result, Err(RpcError::Validation(_))));
+        assert!(store_events(&rpc_server).await.is_empty());
+        assert!(sent_events(&rpc_server).await.is_empty());
+    }
 
-        insta::assert_debug_snapshot!(rpcs.clone());
+    #[tokio::test]
+    async fn test_calls_are_finished_when_the_client_disconnects() {
+        let (rpc_server, client) = serve(FileStatusSyncResult::Created, 1);
+        rpc_server.file_status_store.lock().await.sync_duration = Duration::from_millis(100);
+
+        // The client is dropped while the server is still syncing the file
+        let call = tokio::spawn(async move {
+            let mut client = client;
+            client
+                .file_changed(event(FilesystemEventKind::Created), Metadata {})
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        call.abort();
+        assert!(call.await.unwrap_err().is_cancelled());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(sent_events(&rpc_server).await.len(), 1);
     }
 }
@@ -145,14 +145,14 @@ impl RpcServer {
         }
     }
 
-    async fn save_event(&mut self, name: &str, message: Event) -> Result<(), RpcError> {
+    async fn save_event(&self, name: &str, message: Event) -> Result<(), RpcError> {
         let serde_value = serde_json::to_value(&message).map_err(rpc_error_map)?;
 
         self.postgres
             .lock()
             .await
             .execute(
-                "INSERT INTO events(id, created_timestamp, type, data) VALUES($1,$2,$3,$4)",
+                "INSERT INTO events(id, created_timestamp, type, data) VALUES($1,$2,$3,$4) ON CONFLICT (id) DO NOTHING",
                 &[&message.id, &message.created_time, &name, &serde_value],
             )
             .await
@@ -170,7 +170,7 @@ impl RpcServer {
 #[async_trait]
 impl Rpc for RpcServer {
     async fn subscribe(
-        &mut self,
+        &self,
         request: SubscribeRequest,
         _metadata: Metadata,
         _client: Weak<Mutex<dyn RpcClient>>,
@@ -192,7 +192,7 @@ impl Rpc for RpcServer {
     }
 
     async fn send_event(
-        &mut self,
+        &self,
         request: Event,
         _metadata: Metadata,
         _client: Weak<Mutex<dyn RpcClient>>,
@@ -248,7 +248,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         run_with_error_handling(connection).await;
     });
 
-    let rpc_server = Arc::new(Mutex::new(RpcServer::new(Arc::new(Mutex::new(client)))));
+    let rpc_server = Arc::new(RpcServer::new(Arc::new(Mutex::new(client))));
 
     // todo make the bind addr/port configurable
     let server = Server::new("0.0.0.0:7654", rpc_server).await?;
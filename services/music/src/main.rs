@@ -4,9 +4,11 @@ mod music_storage;
 use event_storage::EventStorage;
 use events::{EventKind, RpcClient as EventsRpc};
 use futures::stream::StreamExt;
+use music::library::RpcServer as LibraryRpc;
+use music::playback::RpcServer as PlaybackRpc;
 use music::{
-    Album, AllAlbums, AllAlbumsRequest, AllArtists, AllTracks, AllTracksRequest, Artist, Metadata,
-    RpcServer as MusicRpc, Server, StreamTrackRequest, Track, TrackData,
+    library, playback, Album, AllAlbums, AllAlbumsRequest, AllArtists, AllTracks,
+    AllTracksRequest, Artist, CombinedServer, Metadata, StreamTrackRequest, Track, TrackData,
 };
 use music_storage::{Error, MusicStorage, Postgres};
 use platform::async_infra;
@@ -30,9 +32,9 @@ struct RpcServer<TMusicStorage: MusicStorage> {
 }
 
 #[async_trait::async_trait]
-impl<TMusicStorage: MusicStorage + Send + Sync> MusicRpc for RpcServer<TMusicStorage> {
+impl<TMusicStorage: MusicStorage + Send + Sync> PlaybackRpc for RpcServer<TMusicStorage> {
     async fn stream_track(
-        &mut self,
+        &self,
         request: StreamTrackRequest,
         _metadata: Metadata,
         _client: Weak<Mutex<dyn RpcClient>>,
@@ -59,9 +61,12 @@ impl<TMusicStorage: MusicStorage + Send + Sync> MusicRpc for RpcServer<TMusicSto
             })
         })))
     }
+}
 
+#[async_trait::async_trait]
+impl<TMusicStorage: MusicStorage + Send + Sync> LibraryRpc for RpcServer<TMusicStorage> {
     async fn all_artists(
-        &mut self,
+        &self,
         _request: (),
         _metadata: Metadata,
         _client: Weak<Mutex<dyn RpcClient>>,
@@ -80,7 +85,7 @@ impl<TMusicStorage: MusicStorage + Send + Sync> MusicRpc for RpcServer<TMusicSto
     }
 
     async fn all_albums(
-        &mut self,
+        &self,
         request: AllAlbumsRequest,
         _metadata: Metadata,
         _client: Weak<Mutex<dyn RpcClient>>,
@@ -105,7 +110,7 @@ impl<TMusicStorage: MusicStorage + Send + Sync> MusicRpc for RpcServer<TMusicSto
     }
 
     async fn all_tracks(
-        &mut self,
+        &self,
         request: AllTracksRequest,
         _metadata: Metadata,
         _client: Weak<Mutex<dyn RpcClient>>,
@@ -125,6 +130,7 @@ impl<TMusicStorage: MusicStorage + Send + Sync> MusicRpc for RpcServer<TMusicSto
                     title: track.title,
                     artists: vec![], // TODO fill this
                     album_id: request.album_id,
+                    path: track.path,
                 })
                 .collect(),
         })
@@ -322,13 +328,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ));
 
     // todo make the bind addr/port configurable
-    let server = Server::new(
-        "0.0.0.0:7655",
-        Arc::new(Mutex::new(RpcServer {
-            music_storage: music_storage.clone(),
-        })),
-    )
-    .await?;
+    let rpc_server = Arc::new(RpcServer {
+        music_storage: music_storage.clone(),
+    });
+    let server = CombinedServer::new("0.0.0.0:7655")
+        .await?
+        .with_service(library::Handler::new(rpc_server.clone()))
+        .with_service(playback::Handler::new(rpc_server));
     server.run().await?;
 
     Ok(())
@@ -402,7 +408,7 @@ mod tests {
 
     #[tokio::test]
     async fn rpc_server_all_albums() {
-        let mut server = RpcServer {
+        let server = RpcServer {
             music_storage: Arc::new(Mutex::new(MockMusicStorage)),
         };
 
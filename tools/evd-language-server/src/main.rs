@@ -0,0 +1,34 @@
+mod positions;
+mod server;
+
+use std::error::Error;
+use std::path::PathBuf;
+
+use lsp_server::Connection;
+use lsp_types::InitializeParams;
+
+use crate::server::{capabilities, Server};
+
+// The workspace folders, or the root of clients that do not support them
+fn roots(params: &InitializeParams) -> Vec<PathBuf> {
+    let uris: Vec<_> = match &params.workspace_folders {
+        Some(folders) if !folders.is_empty() => folders.iter().map(|folder| &folder.uri).collect(),
+        _ => params.root_uri.iter().collect(),
+    };
+
+    uris.into_iter()
+        .filter_map(|uri| uri.to_file_path().ok())
+        .collect()
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let params = connection.initialize(serde_json::to_value(capabilities())?)?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+
+    Server::new(connection, roots(&params)).run()?;
+    io_threads.join()?;
+
+    Ok(())
+}
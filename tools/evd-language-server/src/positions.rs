@@ -0,0 +1,58 @@
+use lsp_types::{Position, Range};
+use message_compiler::parsing::Span;
+
+// Positions count lines and UTF-16 code units, spans count bytes
+pub fn offset(contents: &str, position: Position) -> usize {
+    let line_start: usize = contents
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum();
+    let line = contents[line_start..].lines().next().unwrap_or_default();
+
+    let mut units = 0;
+    for (index, character) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + index;
+        }
+        units += character.len_utf16();
+    }
+
+    line_start + line.len()
+}
+
+pub fn position(contents: &str, offset: usize) -> Position {
+    let offset = offset.min(contents.len());
+    let before = &contents[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    Position::new(
+        u32::try_from(before.matches('\n').count()).unwrap_or(u32::MAX),
+        u32::try_from(contents[line_start..offset].encode_utf16().count()).unwrap_or(u32::MAX),
+    )
+}
+
+pub fn range(contents: &str, span: Span) -> Range {
+    Range::new(position(contents, span.start), position(contents, span.end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn converts_between_offsets_and_positions() {
+        let contents = "/// Zażółć\nstruct A {\n    b: u8\n}";
+
+        assert_eq!(offset(contents, Position::new(0, 0)), 0);
+        assert_eq!(offset(contents, Position::new(0, 10)), 14);
+        assert_eq!(offset(contents, Position::new(2, 4)), 30);
+        assert_eq!(offset(contents, Position::new(2, 100)), 35);
+        assert_eq!(position(contents, 14), Position::new(0, 10));
+        assert_eq!(position(contents, 30), Position::new(2, 4));
+        assert_eq!(
+            range(contents, Span::new(33, 35)),
+            Range::new(Position::new(2, 7), Position::new(2, 9))
+        );
+    }
+}
@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, GotoDefinition, HoverRequest, Rename, Request as _};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Diagnostic, DiagnosticSeverity, GotoDefinitionParams, GotoDefinitionResponse, Hover,
+    HoverContents, HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind,
+    OneOf, PublishDiagnosticsParams, RenameParams, ServerCapabilities, TextDocumentPositionParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url, WorkspaceEdit,
+};
+use message_compiler::analysis::{self, CompletionKind, Definition};
+use message_compiler::parsing::grammar::RFileParser;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::positions::{offset, range};
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![":".into(), "(".into(), "<".into(), ",".into()]),
+            ..CompletionOptions::default()
+        }),
+        rename_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+// Directories that never contain the schemas of the project
+const SKIPPED_DIRECTORIES: &[&str] = &["target", "node_modules", "vendor"];
+
+fn find_schemas(directory: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if path.is_dir() {
+            if !name.starts_with('.') && !SKIPPED_DIRECTORIES.contains(&name.as_str()) {
+                find_schemas(&path, found);
+            }
+        } else if path.extension().is_some_and(|extension| extension == "evd") {
+            found.push(path);
+        }
+    }
+}
+
+pub struct Server {
+    connection: Connection,
+    /// The contents of the open files, which are used instead of the ones on disk
+    documents: HashMap<PathBuf, String>,
+    /// The directories searched for the files that import a renamed type
+    roots: Vec<PathBuf>,
+}
+
+impl Server {
+    pub fn new(connection: Connection, roots: Vec<PathBuf>) -> Self {
+        Self {
+            connection,
+            documents: HashMap::new(),
+            roots,
+        }
+    }
+
+    /// Handles the messages until the client asks the server to shut down.
+    ///
+    /// # Errors
+    /// Fails if the connection to the client is lost
+    pub fn run(mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+
+                    let response = self.handle_request(request);
+                    self.connection.sender.send(Message::Response(response))?;
+                }
+                // Notifications have no response to report the error in
+                Message::Notification(notification) => {
+                    if let Err(error) = self.handle_notification(notification) {
+                        eprintln!("Failed to handle a notification: {error}");
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<String> {
+        match self.documents.get(&analysis::normalize(path)) {
+            Some(contents) => Ok(contents.clone()),
+            None => std::fs::read_to_string(path),
+        }
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            HoverRequest::METHOD => respond(request, |params: HoverParams| {
+                self.hover(&params.text_document_position_params)
+            }),
+            GotoDefinition::METHOD => respond(request, |params: GotoDefinitionParams| {
+                self.definition(&params.text_document_position_params)
+            }),
+            Completion::METHOD => respond(request, |params: CompletionParams| {
+                self.completion(&params.text_document_position)
+            }),
+            Rename::METHOD => respond(request, |params: RenameParams| self.rename(&params)),
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unknown method \"{method}\""),
+                )
+            }
+        };
+
+        match result {
+            Ok(value) => Response {
+                id,
+                result: Some(value),
+                error: None,
+            },
+            Err(error) => Response::new_err(id, ErrorCode::RequestFailed as i32, error.to_string()),
+        }
+    }
+
+    fn handle_notification(
+        &mut self,
+        notification: Notification,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.insert(
+                    analysis::normalize(&path(&params.text_document.uri)?),
+                    params.text_document.text,
+                );
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // Only full synchronization is advertised, so the last change has the whole text
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(
+                        analysis::normalize(&path(&params.text_document.uri)?),
+                        change.text,
+                    );
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents
+                    .remove(&analysis::normalize(&path(&params.text_document.uri)?));
+                self.publish_diagnostics(params.text_document.uri, vec![])?;
+            }
+            _ => return Ok(()),
+        }
+
+        // A change can fix or break the files that import the changed one
+        let mut documents: Vec<_> = self.documents.keys().cloned().collect();
+        documents.sort();
+        for document in documents {
+            let diagnostics = self.diagnostics(&document);
+            self.publish_diagnostics(url(&document)?, diagnostics)?;
+        }
+
+        Ok(())
+    }
+
+    fn diagnostics(&self, path: &Path) -> Vec<Diagnostic> {
+        let contents = &self.documents[path];
+        let problems = analysis::check(path, contents, &|path| self.read(path))
+            .err()
+            .unwrap_or_default();
+
+        problems
+            .into_iter()
+            .map(|problem| Diagnostic {
+                range: range(contents, problem.span),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("evd".to_string()),
+                message: problem.message,
+                ..Diagnostic::default()
+            })
+            .collect()
+    }
+
+    fn publish_diagnostics(
+        &self,
+        uri: Url,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        self.connection
+            .sender
+            .send(Message::Notification(Notification::new(
+                PublishDiagnostics::METHOD.to_string(),
+                params,
+            )))?;
+
+        Ok(())
+    }
+
+    fn hover(
+        &self,
+        position: &TextDocumentPositionParams,
+    ) -> Result<Option<Hover>, Box<dyn Error + Send + Sync>> {
+        let path = path(&position.text_document.uri)?;
+        let contents = self.read(&path)?;
+        let Ok(file) = RFileParser::new().parse(&contents) else {
+            return Ok(None);
+        };
+        let typed = analysis::check(&path, &contents, &|path| self.read(path)).ok();
+
+        Ok(
+            analysis::hover(&file, typed.as_ref(), offset(&contents, position.position)).map(
+                |hover| Hover {
+                    contents: HoverContents::Markup(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: hover.contents,
+                    }),
+                    range: Some(range(&contents, hover.span)),
+                },
+            ),
+        )
+    }
+
+    fn definition(
+        &self,
+        position: &TextDocumentPositionParams,
+    ) -> Result<Option<GotoDefinitionResponse>, Box<dyn Error + Send + Sync>> {
+        let path = path(&position.text_document.uri)?;
+        let contents = self.read(&path)?;
+        let Ok(file) = RFileParser::new().parse(&contents) else {
+            return Ok(None);
+        };
+        let Some(symbol) = analysis::symbol_at(&file, offset(&contents, position.position)) else {
+            return Ok(None);
+        };
+
+        let (path, span) = match analysis::definition(&file, symbol.name) {
+            Some(Definition::Here(span)) => (path, span),
+            Some(Definition::Imported { path: imported }) => {
+                let imported = analysis::normalize(
+                    &path
+                        .parent()
+                        .unwrap_or_else(|| Path::new(""))
+                        .join(imported),
+                );
+                let imported_contents = self.read(&imported)?;
+                let Ok(imported_file) = RFileParser::new().parse(&imported_contents) else {
+                    return Ok(None);
+                };
+                let Some(Definition::Here(span)) =
+                    analysis::definition(&imported_file, symbol.name)
+                else {
+                    return Ok(None);
+                };
+
+                let range = range(&imported_contents, span);
+                return Ok(Some(GotoDefinitionResponse::Scalar(Location::new(
+                    url(&imported)?,
+                    range,
+                ))));
+            }
+            None => return Ok(None),
+        };
+
+        Ok(Some(GotoDefinitionResponse::Scalar(Location::new(
+            url(&path)?,
+            range(&contents, span),
+        ))))
+    }
+
+    fn completion(
+        &self,
+        position: &TextDocumentPositionParams,
+    ) -> Result<Option<CompletionResponse>, Box<dyn Error + Send + Sync>> {
+        let contents = self.read(&path(&position.text_document.uri)?)?;
+        let items = analysis::completions(&contents)
+            .into_iter()
+            .map(|completion| CompletionItem {
+                label: completion.label,
+                kind: Some(match completion.kind {
+                    CompletionKind::Builtin => CompletionItemKind::KEYWORD,
+                    CompletionKind::Struct => CompletionItemKind::STRUCT,
+                    CompletionKind::Enum => CompletionItemKind::ENUM,
+                    CompletionKind::Imported => CompletionItemKind::REFERENCE,
+                }),
+                ..CompletionItem::default()
+            })
+            .collect();
+
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+
+    fn rename(
+        &self,
+        params: &RenameParams,
+    ) -> Result<Option<WorkspaceEdit>, Box<dyn Error + Send + Sync>> {
+        let position = &params.text_document_position;
+        let path = path(&position.text_document.uri)?;
+
+        let mut paths: Vec<PathBuf> = self.documents.keys().cloned().collect();
+        for root in &self.roots {
+            find_schemas(root, &mut paths);
+        }
+        paths.sort();
+        paths.dedup();
+        let files: Vec<(PathBuf, String)> = paths
+            .into_iter()
+            .filter_map(|path| {
+                let contents = self.read(&path).ok()?;
+
+                Some((path, contents))
+            })
+            .collect();
+
+        let contents = self.read(&path)?;
+        let edits = analysis::rename(
+            &files,
+            &path,
+            offset(&contents, position.position),
+            &params.new_name,
+        )?;
+
+        let mut changes = HashMap::new();
+        for (path, spans) in edits {
+            let contents = self.read(&path)?;
+            changes.insert(
+                url(&path)?,
+                spans
+                    .into_iter()
+                    .map(|span| TextEdit::new(range(&contents, span), params.new_name.clone()))
+                    .collect(),
+            );
+        }
+
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+}
+
+fn respond<P, R>(
+    request: Request,
+    handle: impl FnOnce(P) -> Result<R, Box<dyn Error + Send + Sync>>,
+) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>>
+where
+    P: DeserializeOwned,
+    R: Serialize,
+{
+    let params: P = serde_json::from_value(request.params)?;
+
+    Ok(serde_json::to_value(handle(params)?)?)
+}
+
+fn path(uri: &Url) -> Result<PathBuf, String> {
+    uri.to_file_path()
+        .map_err(|()| format!("Only files can be opened, not \"{uri}\""))
+}
+
+fn url(path: &Path) -> Result<Url, String> {
+    Url::from_file_path(path).map_err(|()| format!("\"{}\" is not absolute", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_server::RequestId;
+    use lsp_types::{
+        DidOpenTextDocumentParams, Position, TextDocumentIdentifier, TextDocumentItem,
+    };
+
+    fn request<P: Serialize>(
+        connection: &Connection,
+        id: i32,
+        method: &str,
+        params: P,
+    ) -> Response {
+        connection
+            .sender
+            .send(Message::Request(Request::new(
+                RequestId::from(id),
+                method.to_string(),
+                params,
+            )))
+            .unwrap();
+
+        loop {
+            match connection.receiver.recv().unwrap() {
+                Message::Response(response) => return response,
+                Message::Notification(_) | Message::Request(_) => {}
+            }
+        }
+    }
+
+    fn position(uri: &Url, line: u32, character: u32) -> TextDocumentPositionParams {
+        TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(uri.clone()),
+            Position::new(line, character),
+        )
+    }
+
+    #[test]
+    pub fn answers_requests_about_open_files() {
+        let (server, client) = Connection::memory();
+        let server = std::thread::spawn(move || Server::new(server, vec![]).run().unwrap());
+        let uri = Url::from_file_path("/schemas/music.evd").unwrap();
+
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                DidOpenTextDocument::METHOD.to_string(),
+                DidOpenTextDocumentParams {
+                    text_document: TextDocumentItem::new(
+                        uri.clone(),
+                        "evd".to_string(),
+                        1,
+                        "struct Track { album: Album }\nstruct Album { title: strin }".to_string(),
+                    ),
+                },
+            )))
+            .unwrap();
+        let Message::Notification(published) = client.receiver.recv().unwrap() else {
+            panic!("Expected the diagnostics");
+        };
+        let published: PublishDiagnosticsParams = serde_json::from_value(published.params).unwrap();
+        assert_eq!(
+            published.diagnostics[0].message,
+            "A struct with name \"strin\" does not exist"
+        );
+
+        let definition = request(
+            &client,
+            1,
+            GotoDefinition::METHOD,
+            GotoDefinitionParams {
+                text_document_position_params: position(&uri, 0, 23),
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            },
+        );
+        let definition: GotoDefinitionResponse =
+            serde_json::from_value(definition.result.unwrap()).unwrap();
+        assert_eq!(
+            definition,
+            GotoDefinitionResponse::Scalar(Location::new(
+                uri.clone(),
+                lsp_types::Range::new(Position::new(1, 7), Position::new(1, 12))
+            ))
+        );
+
+        let unknown = request(&client, 2, "evd/unknown", ());
+        assert_eq!(
+            unknown.error.unwrap().code,
+            ErrorCode::MethodNotFound as i32
+        );
+
+        request(&client, 3, "shutdown", ());
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                "exit".to_string(),
+                (),
+            )))
+            .unwrap();
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,735 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+
+use crate::build::{self, module_name, ReadFile};
+use crate::diagnostics::parse_error_message;
+use crate::parsing::grammar::RFileParser;
+use crate::parsing::{
+    doc_line, FieldRaw, FileRaw, IdentifierRaw, MetadataRaw, RpcDefinitionRaw, Span,
+    StructDefinitionRaw, TypeRaw,
+};
+use crate::type_checking::{
+    TypeCheckError, TypeChecker, TypedField, TypedFieldType, TypedFile, TypedRpcCall, BUILTIN_TYPES,
+};
+
+/// A problem found in a file, which editors show at the span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+}
+
+impl From<&TypeCheckError> for Diagnostic {
+    fn from(error: &TypeCheckError) -> Self {
+        Self {
+            message: error.to_string(),
+            span: error.span(),
+        }
+    }
+}
+
+/// Parses and type checks the contents of the file at `path`, which may differ from the ones on disk.
+/// The files it imports are read with `read`.
+///
+/// # Errors
+/// Returns every problem found in the file, or the problems of the imported files at their imports
+pub fn check(path: &Path, contents: &str, read: ReadFile) -> Result<TypedFile, Vec<Diagnostic>> {
+    let ast = RFileParser::new().parse(contents).map_err(|error| {
+        let (message, span) = parse_error_message(&error);
+
+        vec![Diagnostic { message, span }]
+    })?;
+
+    let mut diagnostics = vec![];
+    let mut type_checker = TypeChecker::new();
+    for import in ast.imports() {
+        let imported_path = path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(import.path);
+
+        match build::load_with(&imported_path, read) {
+            Ok(imported) => {
+                type_checker =
+                    type_checker.with_import(import.path, &module_name(&imported_path), &imported);
+            }
+            Err(error) => diagnostics.push(Diagnostic {
+                message: format!("The file \"{}\" cannot be imported:\n{error}", import.path),
+                span: import.path_span,
+            }),
+        }
+    }
+
+    // The types of the imports that failed would be reported as missing everywhere they are used
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    type_checker
+        .check(&ast)
+        .map_err(|errors| errors.iter().map(Diagnostic::from).collect())
+}
+
+/// What a name of a struct or an enum is used for where it is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind<'input> {
+    /// The name after `struct` or `enum`
+    Declaration,
+    /// A name in `import "<path>" { ... }`
+    Import { path: &'input str },
+    /// The name used as a type
+    Reference,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol<'input> {
+    pub name: &'input str,
+    pub span: Span,
+    pub kind: SymbolKind<'input>,
+}
+
+fn references<'input>(type_: &TypeRaw<'input>, symbols: &mut Vec<Symbol<'input>>) {
+    match type_ {
+        TypeRaw::Type(IdentifierRaw(name, span)) => {
+            if !BUILTIN_TYPES.contains(name) {
+                symbols.push(Symbol {
+                    name,
+                    span: *span,
+                    kind: SymbolKind::Reference,
+                });
+            }
+        }
+        TypeRaw::Optional(inner) | TypeRaw::Array(inner) => references(inner, symbols),
+        TypeRaw::Map(key, value, _) => {
+            references(key, symbols);
+            references(value, symbols);
+        }
+    }
+}
+
+fn rpc_definitions<'a, 'input>(
+    file: &'a FileRaw<'input>,
+) -> impl Iterator<Item = (Option<&'input str>, &'a RpcDefinitionRaw<'input>)> {
+    let rpc = file
+        .rpc()
+        .into_iter()
+        .flat_map(|rpc| rpc.definitions.iter().map(|definition| (None, definition)));
+    let services = file.services().iter().flat_map(|service| {
+        service
+            .rpc
+            .definitions
+            .iter()
+            .map(|definition| (Some(service.name.0), definition))
+    });
+
+    rpc.chain(services)
+}
+
+/// Every struct and enum name written in the file, in the order they are written in.
+#[must_use]
+pub fn symbols<'input>(file: &FileRaw<'input>) -> Vec<Symbol<'input>> {
+    let mut symbols = vec![];
+    let declaration = |IdentifierRaw(name, span): &IdentifierRaw<'input>| Symbol {
+        name,
+        span: *span,
+        kind: SymbolKind::Declaration,
+    };
+
+    for import in file.imports() {
+        for IdentifierRaw(name, span) in &import.names {
+            symbols.push(Symbol {
+                name,
+                span: *span,
+                kind: SymbolKind::Import { path: import.path },
+            });
+        }
+    }
+    for field in file.metadata().map(MetadataRaw::fields).unwrap_or_default() {
+        references(&field.type_name, &mut symbols);
+    }
+    for constant in file.constants() {
+        references(&constant.type_name, &mut symbols);
+    }
+    for StructDefinitionRaw(name, fields, ..) in file.structs() {
+        symbols.push(declaration(name));
+        for field in fields {
+            references(&field.type_name, &mut symbols);
+        }
+    }
+    for enum_ in file.enums() {
+        symbols.push(declaration(&enum_.name));
+        for field in enum_.variants.iter().flat_map(|variant| &variant.fields) {
+            references(&field.type_name, &mut symbols);
+        }
+    }
+    for (_, definition) in rpc_definitions(file) {
+        let (RpcDefinitionRaw::Stream {
+            request, response, ..
+        }
+        | RpcDefinitionRaw::Unary {
+            request, response, ..
+        }) = definition;
+        references(request, &mut symbols);
+        references(response, &mut symbols);
+    }
+
+    symbols.sort_by_key(|symbol| symbol.span.start);
+    symbols
+}
+
+fn contains(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+/// The struct or enum name at the byte `offset`, including the offset right after the name
+#[must_use]
+pub fn symbol_at<'input>(file: &FileRaw<'input>, offset: usize) -> Option<Symbol<'input>> {
+    symbols(file)
+        .into_iter()
+        .find(|symbol| contains(symbol.span, offset))
+}
+
+/// Where a struct or an enum is declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Definition<'input> {
+    /// In the same file
+    Here(Span),
+    /// In the file at `path`, relative to this one
+    Imported { path: &'input str },
+}
+
+#[must_use]
+pub fn definition<'input>(file: &FileRaw<'input>, name: &str) -> Option<Definition<'input>> {
+    symbols(file)
+        .into_iter()
+        .filter(|symbol| symbol.name == name)
+        .find_map(|symbol| match symbol.kind {
+            SymbolKind::Declaration => Some(Definition::Here(symbol.span)),
+            SymbolKind::Import { path } => Some(Definition::Imported { path }),
+            SymbolKind::Reference => None,
+        })
+}
+
+/// The spans of every use of `name` in the file, including its declaration or import.
+#[must_use]
+pub fn occurrences(file: &FileRaw, name: &str) -> Vec<Span> {
+    symbols(file)
+        .into_iter()
+        .filter(|symbol| symbol.name == name)
+        .map(|symbol| symbol.span)
+        .collect()
+}
+
+/// Text shown when the cursor rests on a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hover {
+    /// Markdown, with the code in `evd` code blocks
+    pub contents: String,
+    pub span: Span,
+}
+
+fn hover_contents(code: &str, description: Option<String>, docs: &[String]) -> String {
+    let mut contents = format!("```evd\n{code}\n```");
+    for paragraph in description
+        .into_iter()
+        .chain((!docs.is_empty()).then(|| docs.join("\n")))
+    {
+        contents.push_str("\n\n");
+        contents.push_str(&paragraph);
+    }
+
+    contents
+}
+
+// Spells out what the type checker resolved the type to
+fn describe(type_: &TypedFieldType) -> String {
+    match type_ {
+        TypedFieldType::OtherStruct(name) => format!("struct `{name}`"),
+        TypedFieldType::Enum(name) => format!("enum `{name}`"),
+        TypedFieldType::Imported { module, name } => {
+            format!("`{name}` from the module `{module}`")
+        }
+        TypedFieldType::Optional(inner) => format!("optional {}", describe(inner)),
+        TypedFieldType::Array(inner) => format!("array of {}", describe(inner)),
+        TypedFieldType::Map(key, value) => {
+            format!("map from {} to {}", describe(key), describe(value))
+        }
+        TypedFieldType::Indirect(inner) => format!(
+            "{}, stored behind a pointer as it contains this definition",
+            describe(inner)
+        ),
+        builtin => format!("built-in `{builtin}`"),
+    }
+}
+
+fn field_hover(field: &TypedField) -> String {
+    hover_contents(
+        &format!("{}: {}", field.name(), field.type_name()),
+        Some(format!("Resolved to: {}", describe(field.type_name()))),
+        field.docs(),
+    )
+}
+
+fn call_hover(call: &TypedRpcCall) -> String {
+    let (TypedRpcCall::Stream {
+        request, response, ..
+    }
+    | TypedRpcCall::Unary {
+        request, response, ..
+    }) = call;
+    let request_stream = if call.request_stream() { "stream " } else { "" };
+    let response_stream = if matches!(call, TypedRpcCall::Stream { .. }) {
+        "stream "
+    } else {
+        ""
+    };
+
+    hover_contents(
+        &format!(
+            "{}({request_stream}{request}) -> {response_stream}{response}",
+            call.name()
+        ),
+        call.deprecated()
+            .map(|reason| format!("Deprecated: {reason}")),
+        call.docs(),
+    )
+}
+
+fn symbol_hover(file: &FileRaw, symbol: Symbol) -> String {
+    if let Some(Definition::Imported { path }) = definition(file, symbol.name) {
+        return hover_contents(
+            &format!("import \"{path}\" {{ {} }}", symbol.name),
+            None,
+            &[],
+        );
+    }
+
+    let docs = |lines: &[&str]| -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| doc_line(line).to_string())
+            .collect()
+    };
+    if let Some(StructDefinitionRaw(_, _, _, lines, _)) =
+        file.structs().iter().find(|s| s.0 .0 == symbol.name)
+    {
+        return hover_contents(&format!("struct {}", symbol.name), None, &docs(lines));
+    }
+    if let Some(enum_) = file.enums().iter().find(|e| e.name.0 == symbol.name) {
+        return hover_contents(&format!("enum {}", symbol.name), None, &docs(&enum_.docs));
+    }
+
+    hover_contents(
+        symbol.name,
+        Some("Not declared or imported in this file".to_string()),
+        &[],
+    )
+}
+
+fn find_field<'a>(fields: &'a [TypedField], raw: &FieldRaw) -> Option<&'a TypedField> {
+    fields.iter().find(|field| field.name() == raw.name.0)
+}
+
+// The typed field for the raw field that contains the offset
+fn typed_field_at<'a>(
+    file: &FileRaw,
+    typed: &'a TypedFile,
+    offset: usize,
+) -> Option<(&'a TypedField, Span)> {
+    let at = |fields: &[FieldRaw]| -> Option<(usize, Span)> {
+        fields
+            .iter()
+            .position(|field| contains(field.span, offset))
+            .map(|position| (position, fields[position].span))
+    };
+
+    if let Some(metadata) = file.metadata() {
+        if let Some((position, span)) = at(metadata.fields()) {
+            return find_field(typed.meta.fields(), &metadata.fields()[position])
+                .map(|field| (field, span));
+        }
+    }
+    for StructDefinitionRaw(name, fields, ..) in file.structs() {
+        if let Some((position, span)) = at(fields) {
+            let typed_struct = typed.structs.iter().find(|s| s.name() == name.0)?;
+            return find_field(typed_struct.fields(), &fields[position]).map(|field| (field, span));
+        }
+    }
+    for enum_ in file.enums() {
+        for variant in &enum_.variants {
+            if let Some((position, span)) = at(&variant.fields) {
+                let typed_variant = typed
+                    .enums
+                    .iter()
+                    .find(|e| e.name() == enum_.name.0)?
+                    .variants()
+                    .iter()
+                    .find(|v| v.name() == variant.name.0)?;
+                return find_field(typed_variant.fields(), &variant.fields[position])
+                    .map(|field| (field, span));
+            }
+        }
+    }
+
+    None
+}
+
+/// What to show for the name at the byte `offset`. Struct and enum names are described from the
+/// source, fields and RPC methods need the `typed` file, to show the types they were resolved to.
+#[must_use]
+pub fn hover(file: &FileRaw, typed: Option<&TypedFile>, offset: usize) -> Option<Hover> {
+    if let Some(symbol) = symbol_at(file, offset) {
+        return Some(Hover {
+            contents: symbol_hover(file, symbol),
+            span: symbol.span,
+        });
+    }
+
+    let typed = typed?;
+    if let Some((field, span)) = typed_field_at(file, typed, offset) {
+        return Some(Hover {
+            contents: field_hover(field),
+            span,
+        });
+    }
+
+    let (service, definition) =
+        rpc_definitions(file).find(|(_, definition)| contains(definition.name().span(), offset))?;
+    let calls = match service {
+        Some(service) => typed.services.iter().find(|s| s.name() == service)?.calls(),
+        None => typed.rpc.calls(),
+    };
+    let call = calls
+        .iter()
+        .find(|call| call.name() == definition.name().name())?;
+
+    Some(Hover {
+        contents: call_hover(call),
+        span: definition.name().span(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Builtin,
+    Struct,
+    Enum,
+    Imported,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+}
+
+/// The type names that can be used in the file: the built-in ones, followed by the declared and
+/// imported ones. The names are found without parsing, as the file being edited rarely parses.
+///
+/// # Panics
+/// Will not panic, the regular expression is valid
+#[must_use]
+pub fn completions(contents: &str) -> Vec<Completion> {
+    let names = Regex::new(
+        r#"//[^\n]*|\b(struct|enum)\s+([A-Za-z_][A-Za-z0-9_]*)|\bimport\s*"[^"]*"\s*\{([^}]*)\}"#,
+    )
+    .unwrap();
+
+    let mut completions: Vec<_> = BUILTIN_TYPES
+        .iter()
+        .map(|name| Completion {
+            label: (*name).to_string(),
+            kind: CompletionKind::Builtin,
+        })
+        .collect();
+    for captures in names.captures_iter(contents) {
+        if let (Some(keyword), Some(name)) = (captures.get(1), captures.get(2)) {
+            completions.push(Completion {
+                label: name.as_str().to_string(),
+                kind: if keyword.as_str() == "struct" {
+                    CompletionKind::Struct
+                } else {
+                    CompletionKind::Enum
+                },
+            });
+        } else if let Some(imported) = captures.get(3) {
+            completions.extend(
+                imported
+                    .as_str()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| Completion {
+                        label: name.to_string(),
+                        kind: CompletionKind::Imported,
+                    }),
+            );
+        }
+    }
+
+    completions
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RenameError {
+    /// There is no struct or enum name at the position, or the file does not parse
+    NothingToRename,
+    InvalidName(String),
+}
+
+impl Display for RenameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenameError::NothingToRename => {
+                write!(f, "Only the names of structs and enums can be renamed")
+            }
+            RenameError::InvalidName(name) => {
+                write!(f, "\"{name}\" cannot be used as the name of a type")
+            }
+        }
+    }
+}
+
+impl Error for RenameError {}
+
+/// Resolves `.` and `..` without looking at the file system, so that the paths of files that are
+/// only open in an editor can be compared.
+#[must_use]
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Renames the struct or enum at the byte `offset` of the file at `path`. It is renamed in the file
+/// that declares it and in all of the `files` that import it from there.
+///
+/// Returns the spans to replace with the new name, for each file that has any.
+///
+/// # Errors
+/// Fails if there is no struct or enum at the offset, or if the new name is not a valid identifier
+///
+/// # Panics
+/// Will not panic, the regular expression is valid
+pub fn rename(
+    files: &[(PathBuf, String)],
+    path: &Path,
+    offset: usize,
+    new_name: &str,
+) -> Result<Vec<(PathBuf, Vec<Span>)>, RenameError> {
+    let identifier = Regex::new("^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+    if !identifier.is_match(new_name) || BUILTIN_TYPES.contains(&new_name) {
+        return Err(RenameError::InvalidName(new_name.to_string()));
+    }
+
+    let parsed: Vec<(PathBuf, FileRaw)> = files
+        .iter()
+        .filter_map(|(path, contents)| {
+            let ast = RFileParser::new().parse(contents).ok()?;
+
+            Some((normalize(path), ast))
+        })
+        .collect();
+
+    let path = normalize(path);
+    let (_, file) = parsed
+        .iter()
+        .find(|(file_path, _)| file_path == &path)
+        .ok_or(RenameError::NothingToRename)?;
+    let name = symbol_at(file, offset)
+        .ok_or(RenameError::NothingToRename)?
+        .name;
+    let relative_to = |file_path: &Path, imported: &str| {
+        normalize(
+            &file_path
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(imported),
+        )
+    };
+    let declared_in = match definition(file, name) {
+        Some(Definition::Here(_)) => path.clone(),
+        Some(Definition::Imported { path: imported }) => relative_to(&path, imported),
+        None => return Err(RenameError::NothingToRename),
+    };
+
+    Ok(parsed
+        .iter()
+        .filter(|(file_path, file)| {
+            file_path == &declared_in
+                || matches!(
+                    definition(file, name),
+                    Some(Definition::Imported { path: imported })
+                        if relative_to(file_path, imported) == declared_in
+                )
+        })
+        .map(|(file_path, file)| (file_path.clone(), occurrences(file, name)))
+        .filter(|(_, spans)| !spans.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENTS: &str = "/// A path on a mount\nstruct FileOnMountPath { path: string }";
+    const MUSIC: &str = "\
+import \"../events/events.evd\" { FileOnMountPath }
+struct Track { path: FileOnMountPath, /// The album it is on\nalbum: Album? }
+struct Album { tracks: Track[] }
+rpc { track(guid) -> Track?; }";
+
+    fn parse(input: &str) -> FileRaw<'_> {
+        RFileParser::new().parse(input).unwrap()
+    }
+
+    fn read_events(path: &Path) -> std::io::Result<String> {
+        assert_eq!(path, Path::new("/schemas/music/../events/events.evd"));
+
+        Ok(EVENTS.to_string())
+    }
+
+    #[test]
+    pub fn reports_problems_with_spans() {
+        let diagnostics = check(Path::new("a.evd"), "struct A { b: B }", &|_| unreachable!())
+            .err()
+            .unwrap();
+        assert_eq!(
+            diagnostics,
+            [Diagnostic {
+                message: "A struct with name \"B\" does not exist".to_string(),
+                span: Span::new(14, 15),
+            }]
+        );
+
+        let diagnostics = check(Path::new("a.evd"), "struct A { b }", &|_| unreachable!())
+            .err()
+            .unwrap();
+        assert_eq!(diagnostics[0].span, Span::new(13, 14));
+    }
+
+    #[test]
+    pub fn reports_invalid_imports_at_the_import() {
+        let diagnostics = check(
+            Path::new("/schemas/music/music.evd"),
+            "import \"../events/events.evd\" { A } struct B { a: A }",
+            &|_| Ok("struct A { c: C }".to_string()),
+        )
+        .err()
+        .unwrap();
+
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert_eq!(diagnostics[0].span, Span::new(7, 29));
+        assert!(diagnostics[0]
+            .message
+            .starts_with("The file \"../events/events.evd\" cannot be imported:\n"));
+    }
+
+    #[test]
+    pub fn finds_definitions() {
+        let file = parse(MUSIC);
+
+        let symbol = symbol_at(&file, MUSIC.find("Album?").unwrap()).unwrap();
+        assert_eq!(symbol.kind, SymbolKind::Reference);
+        assert_eq!(
+            definition(&file, symbol.name),
+            Some(Definition::Here(Span::new(134, 139)))
+        );
+        assert_eq!(
+            definition(&file, "FileOnMountPath"),
+            Some(Definition::Imported {
+                path: "../events/events.evd"
+            })
+        );
+        assert_eq!(symbol_at(&file, MUSIC.find("guid").unwrap()), None);
+    }
+
+    #[test]
+    pub fn shows_resolved_types() {
+        let file = parse(MUSIC);
+        let typed = check(Path::new("/schemas/music/music.evd"), MUSIC, &read_events).unwrap();
+
+        let field = hover(&file, Some(&typed), MUSIC.find("album:").unwrap()).unwrap();
+        assert_eq!(
+            field.contents,
+            "```evd\nalbum: Album?\n```\n\nResolved to: optional struct `Album`\n\nThe album it is on"
+        );
+
+        let imported = hover(&file, Some(&typed), MUSIC.find("path: File").unwrap()).unwrap();
+        assert!(imported
+            .contents
+            .contains("Resolved to: `FileOnMountPath` from the module `events`"));
+
+        let method = hover(&file, Some(&typed), MUSIC.find("track(").unwrap()).unwrap();
+        assert_eq!(method.contents, "```evd\ntrack(guid) -> Track?\n```");
+
+        let declaration = hover(&file, None, MUSIC.find("Album {").unwrap()).unwrap();
+        assert_eq!(declaration.contents, "```evd\nstruct Album\n```");
+    }
+
+    #[test]
+    pub fn completes_type_names() {
+        let labels: Vec<_> = completions(
+            "import \"a.evd\" { A, B }\n// struct Commented {}\nstruct C { d: }\nenum D {}",
+        )
+        .into_iter()
+        .filter(|completion| completion.kind != CompletionKind::Builtin)
+        .map(|completion| completion.label)
+        .collect();
+
+        assert_eq!(labels, ["A", "B", "C", "D"]);
+    }
+
+    #[test]
+    pub fn renames_across_files() {
+        let files = vec![
+            (
+                PathBuf::from("/schemas/events/events.evd"),
+                EVENTS.to_string(),
+            ),
+            (PathBuf::from("/schemas/music/music.evd"), MUSIC.to_string()),
+            (
+                PathBuf::from("/schemas/other.evd"),
+                "struct FileOnMountPath {}".to_string(),
+            ),
+        ];
+
+        let edits = rename(
+            &files,
+            Path::new("/schemas/music/music.evd"),
+            MUSIC.find("FileOnMountPath }").unwrap(),
+            "MountPath",
+        )
+        .unwrap();
+
+        assert_eq!(
+            edits,
+            [
+                (
+                    PathBuf::from("/schemas/events/events.evd"),
+                    vec![Span::new(29, 44)]
+                ),
+                (
+                    PathBuf::from("/schemas/music/music.evd"),
+                    vec![Span::new(32, 47), Span::new(71, 86)]
+                ),
+            ]
+        );
+        assert_eq!(
+            rename(&files, Path::new("/schemas/other.evd"), 7, "u8"),
+            Err(RenameError::InvalidName("u8".to_string()))
+        );
+    }
+}
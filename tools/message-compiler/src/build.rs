@@ -0,0 +1,352 @@
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::diagnostics::SourceFile;
+use crate::naming::pascal_case;
+use crate::parsing::grammar::RFileParser;
+use crate::type_checking::{TypeChecker, TypedFile};
+use crate::{
+    compiler_json_schema, compiler_openrpc, compiler_php, compiler_rust, compiler_typescript,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Rust,
+    Php,
+    TypeScript,
+    JsonSchema,
+    OpenRpc,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rust" => Ok(Target::Rust),
+            "php" => Ok(Target::Php),
+            "ts" => Ok(Target::TypeScript),
+            "json-schema" => Ok(Target::JsonSchema),
+            "openrpc" => Ok(Target::OpenRpc),
+            other => Err(format!(
+                "Unknown target \"{other}\", expected one of: rust, php, ts, json-schema, openrpc"
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ImportCycle {
+    path: PathBuf,
+}
+
+impl Display for ImportCycle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The file \"{}\" imports itself", self.path.display())
+    }
+}
+
+impl Error for ImportCycle {}
+
+/// The name of the module generated from a file, which other files refer to its types by.
+#[must_use]
+pub fn module_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Reads the contents of a file, `std::fs::read_to_string` unless the file is open in an editor.
+pub type ReadFile<'a> = &'a dyn Fn(&Path) -> std::io::Result<String>;
+
+/// The crates that the Rust code of imported files is generated in, by the file name of the files,
+/// for crates that are not named after their file, e.g. `("directory-watcher.evd", "lib-directory-watcher")`.
+pub type Crates<'a> = &'a [(&'a str, &'a str)];
+
+// The module that the generated code refers to the types of an imported file through
+fn imported_module(path: &Path, crates: Crates) -> String {
+    crates
+        .iter()
+        .find(|(file, _)| path.file_name() == Some(OsStr::new(file)))
+        .map_or_else(|| module_name(path), |(_, crate_)| (*crate_).to_string())
+}
+
+fn load_with_dependencies(
+    path: &Path,
+    read: ReadFile,
+    crates: Crates,
+    loading: &mut Vec<PathBuf>,
+    dependencies: &mut Vec<PathBuf>,
+) -> Result<TypedFile, Box<dyn Error>> {
+    let contents = read(path).map_err(|e| format!("Failed to read \"{}\": {e}", path.display()))?;
+    dependencies.push(path.to_path_buf());
+
+    let name = path.display().to_string();
+    let source = SourceFile::new(&name, &contents);
+    let ast = RFileParser::new()
+        .parse(&contents)
+        .map_err(|e| source.parse_error(&e))?;
+
+    // Files that are only open in an editor do not exist on disk yet
+    loading.push(std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+
+    // Imports are resolved relative to the importing file
+    let mut type_checker = TypeChecker::new();
+    for import in ast.imports() {
+        let imported_path = path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(import.path);
+
+        if std::fs::canonicalize(&imported_path).is_ok_and(|path| loading.contains(&path)) {
+            return Err(Box::new(ImportCycle {
+                path: imported_path,
+            }));
+        }
+
+        let imported = load_with_dependencies(&imported_path, read, crates, loading, dependencies)?;
+        type_checker = type_checker.with_import(
+            import.path,
+            &imported_module(&imported_path, crates),
+            &imported,
+        );
+    }
+
+    loading.pop();
+
+    Ok(type_checker
+        .check(&ast)
+        .map_err(|e| source.type_check_errors(&e))?)
+}
+
+/// Parses and type checks a file, together with everything it imports.
+///
+/// # Errors
+/// Returns the rendered diagnostics if any of the files is invalid, or an IO error if it cannot be read
+pub fn load(path: impl AsRef<Path>) -> Result<TypedFile, Box<dyn Error>> {
+    load_with(path.as_ref(), &|path| std::fs::read_to_string(path))
+}
+
+/// Like `load`, but reads the files with `read`.
+///
+/// # Errors
+/// Returns the rendered diagnostics if any of the files is invalid, or the error returned by `read`
+pub fn load_with(path: &Path, read: ReadFile) -> Result<TypedFile, Box<dyn Error>> {
+    load_with_dependencies(path, read, &[], &mut vec![], &mut vec![])
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CompileOptions<'a> {
+    /// The namespace the PHP namespaces of the files are created in
+    pub php_namespace: Option<&'a str>,
+    /// The version of the API reported by OpenRPC documents
+    pub api_version: &'a str,
+    /// The crates of the imported files whose crate is not named after the file, for Rust
+    pub crates: Crates<'a>,
+}
+
+impl Default for CompileOptions<'_> {
+    fn default() -> Self {
+        Self {
+            php_namespace: None,
+            api_version: "0.0.0",
+            crates: &[],
+        }
+    }
+}
+
+/// Compiles a file for the target, returning the paths of the generated files relative to the output
+/// directory together with their contents.
+///
+/// # Errors
+/// Returns the rendered diagnostics if any of the files is invalid, or an IO error if it cannot be read
+pub fn compile(
+    path: &Path,
+    target: Target,
+    options: CompileOptions,
+) -> Result<Vec<(PathBuf, String)>, Box<dyn Error>> {
+    // Only Rust refers to imported types through crates, the other targets through sibling files
+    let crates = if target == Target::Rust {
+        options.crates
+    } else {
+        &[]
+    };
+    let file = load_with_dependencies(
+        path,
+        &|path| std::fs::read_to_string(path),
+        crates,
+        &mut vec![],
+        &mut vec![],
+    )?;
+    let module = module_name(path);
+
+    Ok(match target {
+        Target::Rust => vec![(
+            PathBuf::from(format!("{}.rs", module.replace('-', "_"))),
+            compiler_rust::compile(file),
+        )],
+        Target::Php => {
+            let namespace = pascal_case(&module);
+            let qualified_namespace = options.php_namespace.map_or_else(
+                || namespace.clone(),
+                |prefix| format!("{prefix}\\{namespace}"),
+            );
+
+            compiler_php::compile(&file, &qualified_namespace)
+                .into_iter()
+                .map(|(path, contents)| (Path::new(&namespace).join(path), contents))
+                .collect()
+        }
+        Target::TypeScript => vec![(
+            PathBuf::from(format!("{module}.ts")),
+            compiler_typescript::compile(&file),
+        )],
+        Target::JsonSchema => vec![(
+            PathBuf::from(format!("{module}.schema.json")),
+            compiler_json_schema::compile(&file),
+        )],
+        Target::OpenRpc => vec![(
+            PathBuf::from(format!("{module}.openrpc.json")),
+            compiler_openrpc::compile(&file, &module, options.api_version),
+        )],
+    })
+}
+
+/// Compiles a file into `structs.rs` in the `OUT_DIR` of the crate being built, to be used from `build.rs`.
+///
+/// # Errors
+/// Returns the rendered diagnostics if any of the files is invalid, or an IO error if it cannot be read
+pub fn compile_to_out_dir(path: &str) -> Result<(), Box<dyn Error>> {
+    compile_to_out_dir_with_crates(path, &[])
+}
+
+/// Like `compile_to_out_dir`, for files that import files whose crate is not named after the file.
+///
+/// # Errors
+/// Returns the rendered diagnostics if any of the files is invalid, or an IO error if it cannot be read
+pub fn compile_to_out_dir_with_crates(path: &str, crates: Crates) -> Result<(), Box<dyn Error>> {
+    let mut dependencies = vec![];
+    let file = load_with_dependencies(
+        Path::new(path),
+        &|path| std::fs::read_to_string(path),
+        crates,
+        &mut vec![],
+        &mut dependencies,
+    );
+
+    // Also rerun after a failure, as fixing the file has to be picked up
+    for dependency in &dependencies {
+        println!("cargo:rerun-if-changed={}", dependency.display());
+    }
+
+    let rust = compiler_rust::compile(file?);
+    let out_dir = std::env::var_os("OUT_DIR").ok_or("OUT_DIR is not set")?;
+    std::fs::write(Path::new(&out_dir).join("structs.rs"), rust)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::type_checking::TypedFieldType;
+
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("message-compiler-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for (path, contents) in files {
+            std::fs::write(directory.join(path), contents).unwrap();
+        }
+
+        directory
+    }
+
+    #[test]
+    pub fn resolves_imports_relative_to_the_file() {
+        let directory = write_files(
+            "imports",
+            &[
+                ("events.evd", "struct FileOnMountPath { path: string }"),
+                (
+                    "music.evd",
+                    "import \"events.evd\" { FileOnMountPath } struct Track { path: FileOnMountPath }",
+                ),
+            ],
+        );
+
+        let file = load(directory.join("music.evd")).unwrap();
+
+        assert_eq!(
+            file.structs[0].fields()[0].type_name(),
+            &TypedFieldType::Imported {
+                module: "events".to_string(),
+                name: "FileOnMountPath".to_string()
+            }
+        );
+    }
+
+    #[test]
+    pub fn refers_to_imported_types_through_their_crate() {
+        let directory = write_files(
+            "crates",
+            &[
+                (
+                    "directory-watcher.evd",
+                    "struct FilesystemEvent { path: string }",
+                ),
+                (
+                    "indexer.evd",
+                    "import \"directory-watcher.evd\" { FilesystemEvent } struct Indexed { event: FilesystemEvent }",
+                ),
+            ],
+        );
+        let path = directory.join("indexer.evd");
+        let rust = |crates| {
+            let options = CompileOptions {
+                crates,
+                ..CompileOptions::default()
+            };
+            compile(&path, Target::Rust, options).unwrap().remove(0).1
+        };
+
+        assert!(rust(&[]).contains("::directory_watcher::FilesystemEvent"));
+        assert!(rust(&[("directory-watcher.evd", "lib-directory-watcher")])
+            .contains("pub event: ::lib_directory_watcher::FilesystemEvent"));
+
+        // The other targets refer to the sibling file of the imported file
+        let options = CompileOptions {
+            crates: &[("directory-watcher.evd", "lib-directory-watcher")],
+            ..CompileOptions::default()
+        };
+        let (_, typescript) = compile(&path, Target::TypeScript, options)
+            .unwrap()
+            .remove(0);
+        assert!(typescript.contains("from \"./directory-watcher\""));
+    }
+
+    #[test]
+    pub fn rejects_import_cycles() {
+        let directory = write_files(
+            "cycles",
+            &[
+                ("a.evd", "import \"b.evd\" { B } struct A {}"),
+                ("b.evd", "import \"a.evd\" { A } struct B {}"),
+            ],
+        );
+
+        let error = load(directory.join("a.evd")).err().unwrap();
+
+        assert!(error.is::<ImportCycle>());
+    }
+
+    #[test]
+    pub fn target_from_str_test() {
+        assert_eq!("ts".parse(), Ok(Target::TypeScript));
+        assert!("go".parse::<Target>().is_err());
+    }
+}
@@ -0,0 +1,307 @@
+use serde_json::{json, Map, Value};
+
+use crate::type_checking::{
+    TypedConstraints, TypedEnum, TypedEnumTagging, TypedEnumVariant, TypedField, TypedFieldType,
+    TypedFile, TypedValue,
+};
+
+fn integer(minimum: i128, maximum: i128) -> Value {
+    json!({ "type": "integer", "minimum": minimum, "maximum": maximum })
+}
+
+pub(crate) fn type_schema(type_: &TypedFieldType, definitions: &str) -> Value {
+    match type_ {
+        TypedFieldType::U8 => integer(0, u8::MAX.into()),
+        TypedFieldType::U16 => integer(0, u16::MAX.into()),
+        TypedFieldType::U32 => integer(0, u32::MAX.into()),
+        TypedFieldType::U64 => integer(0, u64::MAX.into()),
+        TypedFieldType::S8 => integer(i8::MIN.into(), i8::MAX.into()),
+        TypedFieldType::S16 => integer(i16::MIN.into(), i16::MAX.into()),
+        TypedFieldType::S32 => integer(i32::MIN.into(), i32::MAX.into()),
+        TypedFieldType::S64 => integer(i64::MIN.into(), i64::MAX.into()),
+        TypedFieldType::F32 | TypedFieldType::F64 => json!({ "type": "number" }),
+        TypedFieldType::Bool => json!({ "type": "boolean" }),
+        // Sent as strings, so that they are not rounded to floats when parsed
+        TypedFieldType::Decimal => json!({ "type": "string", "pattern": "^-?[0-9]+(\\.[0-9]+)?$" }),
+        // The representation of `SystemTime` used by serde, with negative seconds before the epoch
+        TypedFieldType::Instant => json!({
+            "type": "object",
+            "properties": {
+                "secs_since_epoch": integer(i64::MIN.into(), i64::MAX.into()),
+                "nanos_since_epoch": integer(0, 999_999_999),
+            },
+            "required": ["secs_since_epoch", "nanos_since_epoch"],
+        }),
+        // The representation of `rpc_support::duration_serializer`
+        TypedFieldType::Duration => json!({
+            "type": "object",
+            "properties": {
+                "secs": integer(0, u64::MAX.into()),
+                "nanos": integer(0, 999_999_999),
+            },
+            "required": ["secs", "nanos"],
+        }),
+        TypedFieldType::Date => json!({ "type": "string", "format": "date" }),
+        TypedFieldType::Guid => json!({ "type": "string", "format": "uuid" }),
+        TypedFieldType::String => json!({ "type": "string" }),
+        TypedFieldType::Void => json!({ "type": "null" }),
+        TypedFieldType::Binary => json!({ "type": "array", "items": integer(0, u8::MAX.into()) }),
+        TypedFieldType::OtherStruct(name) | TypedFieldType::Enum(name) => {
+            json!({ "$ref": format!("{definitions}{name}") })
+        }
+        TypedFieldType::Imported { module, name } => {
+            json!({ "$ref": format!("{module}.schema.json#/$defs/{name}") })
+        }
+        TypedFieldType::Optional(type_) => json!({
+            "anyOf": [type_schema(type_, definitions), { "type": "null" }],
+        }),
+        TypedFieldType::Array(type_) => json!({
+            "type": "array",
+            "items": type_schema(type_, definitions),
+        }),
+        // Keys are always serialized as strings
+        TypedFieldType::Map(_, value) => json!({
+            "type": "object",
+            "additionalProperties": type_schema(value, definitions),
+        }),
+        TypedFieldType::Indirect(type_) => type_schema(type_, definitions),
+    }
+}
+
+fn value(value: &TypedValue) -> Value {
+    match value {
+        // Values are checked to fit their type, so they are in the range of either `i64` or `u64`
+        TypedValue::Integer(value) => i64::try_from(*value).map_or_else(
+            |_| Value::from(u64::try_from(*value).unwrap_or(u64::MAX)),
+            Value::from,
+        ),
+        TypedValue::Float(value) => Value::from(*value),
+        TypedValue::Boolean(value) => Value::Bool(*value),
+        TypedValue::String(value) => Value::from(value.as_str()),
+    }
+}
+
+// The constraints apply to the value of optional fields, which is the first schema of `anyOf`
+fn add_constraints(schema: &mut Value, type_: &TypedFieldType, constraints: &TypedConstraints) {
+    let (min_length, max_length) = match type_ {
+        TypedFieldType::Optional(inner) => {
+            return add_constraints(&mut schema["anyOf"][0], inner, constraints);
+        }
+        TypedFieldType::String => ("minLength", "maxLength"),
+        TypedFieldType::Map(..) => ("minProperties", "maxProperties"),
+        _ => ("minItems", "maxItems"),
+    };
+
+    if let Some(pattern) = &constraints.pattern {
+        // Patterns in JSON Schema match anywhere in the string
+        schema["pattern"] = Value::from(format!("^(?:{pattern})$"));
+    }
+    if let Some(length) = constraints.min_length {
+        schema[min_length] = Value::from(length);
+    }
+    if let Some(length) = constraints.max_length {
+        schema[max_length] = Value::from(length);
+    }
+    if let Some(min) = &constraints.min {
+        schema["minimum"] = value(min);
+    }
+    if let Some(max) = &constraints.max {
+        schema["maximum"] = value(max);
+    }
+}
+
+/// Sets the `description` of the schema to the `///` comments of its definition, if there are any
+pub(crate) fn add_description(schema: &mut Value, docs: &[String]) {
+    if !docs.is_empty() {
+        schema["description"] = Value::from(docs.join("\n"));
+    }
+}
+
+fn object_schema(fields: &[TypedField], definitions: &str) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![];
+
+    for field in fields {
+        let mut schema = type_schema(field.type_name(), definitions);
+        add_constraints(&mut schema, field.type_name(), field.constraints());
+        if let Some(default) = field.default() {
+            schema["default"] = value(default);
+        }
+        if field.deprecated().is_some() {
+            schema["deprecated"] = Value::Bool(true);
+        }
+        add_description(&mut schema, field.docs());
+        properties.insert(field.serialized_name().to_string(), schema);
+
+        // Missing optional fields are deserialized as `None`, and fields with a default as the default
+        if !matches!(field.type_name(), TypedFieldType::Optional(_)) && field.default().is_none() {
+            required.push(Value::from(field.serialized_name()));
+        }
+    }
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+// Matches the representation serde uses for the tagging of the enum
+fn variant_schema(
+    variant: &TypedEnumVariant,
+    tagging: &TypedEnumTagging,
+    definitions: &str,
+) -> Value {
+    let name = variant.serialized_name();
+
+    match tagging {
+        TypedEnumTagging::External if variant.is_unit() => json!({ "const": name }),
+        TypedEnumTagging::External => json!({
+            "type": "object",
+            "properties": { name: object_schema(variant.fields(), definitions) },
+            "required": [name],
+            "additionalProperties": false,
+        }),
+        TypedEnumTagging::Internal { tag } => {
+            let mut schema = object_schema(variant.fields(), definitions);
+            schema["properties"][tag] = json!({ "const": name });
+            if let Value::Array(required) = &mut schema["required"] {
+                required.insert(0, Value::from(tag.as_str()));
+            }
+
+            schema
+        }
+        TypedEnumTagging::Adjacent { tag, .. } if variant.is_unit() => json!({
+            "type": "object",
+            "properties": { tag: { "const": name } },
+            "required": [tag],
+            "additionalProperties": false,
+        }),
+        TypedEnumTagging::Adjacent { tag, content } => json!({
+            "type": "object",
+            "properties": {
+                tag: { "const": name },
+                content: object_schema(variant.fields(), definitions),
+            },
+            "required": [tag, content],
+            "additionalProperties": false,
+        }),
+    }
+}
+
+fn enum_schema(enum_: &TypedEnum, definitions: &str) -> Value {
+    let variants: Vec<_> = enum_
+        .variants()
+        .iter()
+        .map(|variant| {
+            let mut schema = variant_schema(variant, enum_.tagging(), definitions);
+            if variant.deprecated().is_some() {
+                schema["deprecated"] = Value::Bool(true);
+            }
+            add_description(&mut schema, variant.docs());
+
+            schema
+        })
+        .collect();
+
+    let mut schema = json!({ "oneOf": variants });
+    add_description(&mut schema, enum_.docs());
+
+    schema
+}
+
+/// Returns the schemas of the metadata, structs and enums of the file, keyed by their names.
+/// References between them point to `{definitions}{name}`.
+pub(crate) fn definitions(file: &TypedFile, definitions: &str) -> Map<String, Value> {
+    let mut result = Map::new();
+
+    result.insert(
+        "Metadata".to_string(),
+        object_schema(file.meta.fields(), definitions),
+    );
+
+    for struct_ in &file.structs {
+        let mut schema = object_schema(struct_.fields(), definitions);
+        add_description(&mut schema, struct_.docs());
+        result.insert(struct_.name().to_string(), schema);
+    }
+
+    for enum_ in &file.enums {
+        result.insert(enum_.name().to_string(), enum_schema(enum_, definitions));
+    }
+
+    result
+}
+
+/// Compiles the file into a JSON Schema document with a definition for the metadata and for every struct and enum.
+/// Imported types are referenced from `<module>.schema.json`.
+#[must_use]
+pub fn compile(file: &TypedFile) -> String {
+    let schema = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$defs": definitions(file, "#/$defs/"),
+    });
+
+    serde_json::to_string_pretty(&schema).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing;
+    use crate::type_checking::TypeChecker;
+
+    #[test]
+    pub fn compile_test() {
+        let input = "\
+            metadata { correlation_id: guid } \
+            @rename_all(\"camelCase\") struct Track { title: string @non_empty, artists: Artist[] @max_length(10), album: Album?, data: binary, played_at: instant, plays: u64 = 18446744073709551615, length: duration, price: decimal, gain: f64 @min(-60) @max(12) = -6.5, explicit: bool = false, released: date } \
+            struct Artist { name: string, rating: s8 = -1, @deprecated(\"use name\") nickname: string? @pattern(\"[a-z]+\"), labels: map<string, string> @max_length(3) } \
+            /// A collection of tracks\n struct Album { title: string, /// The album this one continues\n previous: Album? } \
+            /// Something that happened in the player\n enum Event { /// A track was played\n Played(track: Track), @deprecated(\"use Played\") Paused } \
+            @tag(\"type\") enum Command { Play(track: Track), Stop } \
+            @rename_all(\"snake_case\") @tag(\"kind\", \"data\") enum Change { Renamed(name: string), Removed }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let typed_file = TypeChecker::new().check(&ast).unwrap();
+
+        insta::assert_snapshot!(compile(&typed_file));
+    }
+
+    #[test]
+    pub fn type_schema_tests() {
+        assert_eq!(
+            type_schema(&TypedFieldType::U16, "#/$defs/"),
+            json!({ "type": "integer", "minimum": 0, "maximum": 65535 })
+        );
+        assert_eq!(
+            type_schema(
+                &TypedFieldType::Array(Box::new(TypedFieldType::Enum("E".to_string()))),
+                "#/components/schemas/"
+            ),
+            json!({ "type": "array", "items": { "$ref": "#/components/schemas/E" } })
+        );
+        assert_eq!(
+            type_schema(
+                &TypedFieldType::Map(
+                    Box::new(TypedFieldType::Guid),
+                    Box::new(TypedFieldType::Array(Box::new(TypedFieldType::String)))
+                ),
+                "#/$defs/"
+            ),
+            json!({
+                "type": "object",
+                "additionalProperties": { "type": "array", "items": { "type": "string" } },
+            })
+        );
+        assert_eq!(
+            type_schema(
+                &TypedFieldType::Imported {
+                    module: "events".to_string(),
+                    name: "FileOnMountPath".to_string()
+                },
+                "#/$defs/"
+            ),
+            json!({ "$ref": "events.schema.json#/$defs/FileOnMountPath" })
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use serde_json::{json, Value};
+
+use crate::compiler_json_schema::{add_description, definitions, type_schema};
+use crate::type_checking::{TypedFile, TypedRpcCall};
+
+const SCHEMAS: &str = "#/components/schemas/";
+
+// `name` is the name the method is called by on the wire
+fn method(name: &str, call: &TypedRpcCall) -> Value {
+    let (request, response, stream) = match call {
+        TypedRpcCall::Stream {
+            request, response, ..
+        } => (request, response, true),
+        TypedRpcCall::Unary {
+            request, response, ..
+        } => (request, response, false),
+    };
+
+    let mut method = json!({
+        "name": name,
+        "paramStructure": "by-position",
+        "params": [
+            { "name": "request", "required": true, "schema": type_schema(request, SCHEMAS) },
+            { "name": "metadata", "required": true, "schema": { "$ref": format!("{SCHEMAS}Metadata") } },
+        ],
+        "result": { "name": "response", "schema": type_schema(response, SCHEMAS) },
+        // Streams are sent as a sequence of requests or results, which OpenRPC has no notion of
+        "x-stream": stream,
+        "x-request-stream": call.request_stream(),
+    });
+    if call.deprecated().is_some() {
+        method["deprecated"] = Value::Bool(true);
+    }
+    add_description(&mut method, call.docs());
+
+    method
+}
+
+/// The `methods` and `components` of the OpenRPC document of the file, which generated servers
+/// answer the `__describe` method with.
+#[must_use]
+pub fn describe(file: &TypedFile) -> Value {
+    let methods: Vec<_> = file
+        .methods()
+        .into_iter()
+        .map(|(name, call)| method(&name, call))
+        .collect();
+
+    json!({
+        "methods": methods,
+        "components": { "schemas": definitions(file, SCHEMAS) },
+    })
+}
+
+/// Compiles the RPC methods of the file, including the ones of its services as `Service.method`, into an OpenRPC document, with the schemas of the metadata,
+/// structs and enums as its components.
+#[must_use]
+pub fn compile(file: &TypedFile, title: &str, version: &str) -> String {
+    let description = describe(file);
+    let document = json!({
+        "openrpc": "1.2.6",
+        "info": { "title": title, "version": version },
+        "methods": description["methods"],
+        "components": description["components"],
+    });
+
+    serde_json::to_string_pretty(&document).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing;
+    use crate::type_checking::TypeChecker;
+
+    #[test]
+    pub fn compile_test() {
+        let input = "\
+            metadata { correlation_id: guid } \
+            struct Track { title: string } \
+            rpc { /// Plays the track right away\n play(Track) -> void; tracks(void) -> stream Track; @deprecated(\"use tracks\") track(guid) -> Track?; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let typed_file = TypeChecker::new().check(&ast).unwrap();
+
+        insta::assert_snapshot!(compile(&typed_file, "music", "1.0.0"));
+    }
+
+    #[test]
+    pub fn compile_services_test() {
+        let input = "\
+            struct Track { title: string } \
+            service Library { tracks(void) -> stream Track; } \
+            service Playback { @idempotent @timeout(500ms) @requires(\"playback\") play(Track) -> void; queue(stream Track) -> void; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let typed_file = TypeChecker::new().check(&ast).unwrap();
+
+        insta::assert_snapshot!(compile(&typed_file, "music", "1.0.0"));
+    }
+}
@@ -0,0 +1,833 @@
+use std::fmt::Write;
+
+use crate::naming::{camel_case, constant_case, pascal_case};
+use crate::type_checking::{
+    TypedConstant, TypedEnum, TypedEnumTagging, TypedEnumVariant, TypedField, TypedFieldType,
+    TypedFile, TypedRpcCall, TypedValue,
+};
+
+mod runtime;
+
+fn class_name(namespace: &str, name: &str) -> String {
+    format!("\\{namespace}\\{name}")
+}
+
+// Imported files are expected to be compiled into a sibling namespace named after their module
+fn imported_namespace(namespace: &str, module: &str) -> String {
+    match namespace.rsplit_once('\\') {
+        Some((parent, _)) => format!("{parent}\\{}", pascal_case(module)),
+        None => pascal_case(module),
+    }
+}
+
+fn to_php_type(type_: &TypedFieldType, namespace: &str) -> String {
+    match type_ {
+        TypedFieldType::U8
+        | TypedFieldType::U16
+        | TypedFieldType::U32
+        | TypedFieldType::U64
+        | TypedFieldType::S8
+        | TypedFieldType::S16
+        | TypedFieldType::S32
+        | TypedFieldType::S64 => "int".to_string(),
+        // Durations are in seconds
+        TypedFieldType::F32 | TypedFieldType::F64 | TypedFieldType::Duration => "float".to_string(),
+        TypedFieldType::Bool => "bool".to_string(),
+        TypedFieldType::Instant | TypedFieldType::Date => "\\DateTimeImmutable".to_string(),
+        // Decimals are kept as numeric strings, which the bcmath functions work with
+        TypedFieldType::Decimal
+        | TypedFieldType::Guid
+        | TypedFieldType::String
+        | TypedFieldType::Binary => "string".to_string(),
+        TypedFieldType::Void => "mixed".to_string(),
+        TypedFieldType::OtherStruct(name) | TypedFieldType::Enum(name) => {
+            class_name(namespace, name)
+        }
+        TypedFieldType::Imported { module, name } => {
+            class_name(&imported_namespace(namespace, module), name)
+        }
+        TypedFieldType::Optional(type_) => match to_php_type(type_, namespace).as_str() {
+            "mixed" => "mixed".to_string(),
+            inner => format!("?{inner}"),
+        },
+        TypedFieldType::Array(_) | TypedFieldType::Map(..) => "array".to_string(),
+        TypedFieldType::Indirect(type_) => to_php_type(type_, namespace),
+    }
+}
+
+fn to_php_doc_type(type_: &TypedFieldType, namespace: &str) -> String {
+    match type_ {
+        TypedFieldType::Void => "null".to_string(),
+        TypedFieldType::Optional(type_) => match to_php_doc_type(type_, namespace) {
+            inner if inner.contains('<') => format!("{inner}|null"),
+            inner => format!("?{inner}"),
+        },
+        TypedFieldType::Array(type_) => format!("list<{}>", to_php_doc_type(type_, namespace)),
+        TypedFieldType::Map(key, value) => format!(
+            "array<{}, {}>",
+            to_php_type(key, namespace),
+            to_php_doc_type(value, namespace)
+        ),
+        TypedFieldType::Indirect(type_) => to_php_doc_type(type_, namespace),
+        other => to_php_type(other, namespace),
+    }
+}
+
+fn from_json(type_: &TypedFieldType, namespace: &str, expression: &str) -> String {
+    let json = class_name(namespace, "Json");
+
+    match type_ {
+        TypedFieldType::U8
+        | TypedFieldType::U16
+        | TypedFieldType::U32
+        | TypedFieldType::U64
+        | TypedFieldType::S8
+        | TypedFieldType::S16
+        | TypedFieldType::S32
+        | TypedFieldType::S64 => format!("{json}::int({expression})"),
+        TypedFieldType::F32 | TypedFieldType::F64 => format!("{json}::float({expression})"),
+        TypedFieldType::Bool => format!("{json}::bool({expression})"),
+        TypedFieldType::Guid | TypedFieldType::String => format!("{json}::string({expression})"),
+        TypedFieldType::Decimal => format!("{json}::decimal({expression})"),
+        TypedFieldType::Instant => format!("{json}::instant({expression})"),
+        TypedFieldType::Duration => format!("{json}::duration({expression})"),
+        TypedFieldType::Date => format!("{json}::date({expression})"),
+        TypedFieldType::Binary => format!("{json}::binary({expression})"),
+        TypedFieldType::Void => "null".to_string(),
+        TypedFieldType::OtherStruct(_)
+        | TypedFieldType::Enum(_)
+        | TypedFieldType::Imported { .. } => {
+            format!("{}::fromJson({expression})", to_php_type(type_, namespace))
+        }
+        TypedFieldType::Optional(inner) => format!(
+            "{json}::optional({expression}, static fn (mixed $value): {} => {})",
+            to_php_type(inner, namespace),
+            from_json(inner, namespace, "$value")
+        ),
+        TypedFieldType::Array(inner) => format!(
+            "{json}::list({expression}, static fn (mixed $value): {} => {})",
+            to_php_type(inner, namespace),
+            from_json(inner, namespace, "$value")
+        ),
+        TypedFieldType::Map(_, value) => format!(
+            "{json}::map({expression}, static fn (mixed $value): {} => {})",
+            to_php_type(value, namespace),
+            from_json(value, namespace, "$value")
+        ),
+        TypedFieldType::Indirect(inner) => from_json(inner, namespace, expression),
+    }
+}
+
+fn needs_json_conversion(type_: &TypedFieldType) -> bool {
+    match type_ {
+        // Empty arrays are encoded as lists, so maps always have to be converted to objects
+        TypedFieldType::Instant
+        | TypedFieldType::Duration
+        | TypedFieldType::Date
+        | TypedFieldType::Binary
+        | TypedFieldType::Map(..) => true,
+        TypedFieldType::Optional(inner)
+        | TypedFieldType::Array(inner)
+        | TypedFieldType::Indirect(inner) => needs_json_conversion(inner),
+        _ => false,
+    }
+}
+
+// Everything that does not need converting is either a scalar or implements \JsonSerializable
+fn to_json(type_: &TypedFieldType, namespace: &str, expression: &str) -> String {
+    if !needs_json_conversion(type_) {
+        return expression.to_string();
+    }
+
+    let json = class_name(namespace, "Json");
+
+    match type_ {
+        TypedFieldType::Instant => format!("{json}::fromInstant({expression})"),
+        TypedFieldType::Duration => format!("{json}::fromDuration({expression})"),
+        TypedFieldType::Date => format!("{json}::fromDate({expression})"),
+        TypedFieldType::Binary => format!("{json}::fromBinary({expression})"),
+        TypedFieldType::Optional(inner) => format!(
+            "{json}::optional({expression}, static fn ({} $value): mixed => {})",
+            to_php_type(inner, namespace),
+            to_json(inner, namespace, "$value")
+        ),
+        TypedFieldType::Array(inner) => format!(
+            "array_map(static fn ({} $value): mixed => {}, {expression})",
+            to_php_type(inner, namespace),
+            to_json(inner, namespace, "$value")
+        ),
+        TypedFieldType::Map(_, value) => format!(
+            "{json}::fromMap({expression}, static fn ({} $value): mixed => {})",
+            to_php_type(value, namespace),
+            to_json(value, namespace, "$value")
+        ),
+        TypedFieldType::Indirect(inner) => to_json(inner, namespace, expression),
+        _ => expression.to_string(),
+    }
+}
+
+fn to_php_value(value: &TypedValue) -> String {
+    match value {
+        TypedValue::Integer(value) => value.to_string(),
+        // The debug representation always has a fraction or an exponent, so it stays a float in PHP
+        TypedValue::Float(value) => format!("{value:?}"),
+        TypedValue::Boolean(value) => value.to_string(),
+        TypedValue::String(value) => to_php_string(value),
+    }
+}
+
+fn to_php_string(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn file_header(namespace: &str) -> String {
+    format!("<?php\n\ndeclare(strict_types=1);\n\nnamespace {namespace};\n\n")
+}
+
+/// Writes a docblock with the given lines, on a single line when there is only one
+fn write_docblock(output: &mut String, indent: &str, lines: &[String]) {
+    match lines {
+        [] => {}
+        [line] => writeln!(output, "{indent}/** {line} */").unwrap(),
+        lines => {
+            writeln!(output, "{indent}/**").unwrap();
+            for line in lines {
+                if line.is_empty() {
+                    writeln!(output, "{indent} *").unwrap();
+                } else {
+                    writeln!(output, "{indent} * {line}").unwrap();
+                }
+            }
+            writeln!(output, "{indent} */").unwrap();
+        }
+    }
+}
+
+/// The `///` comments of a definition, separated from the tags by an empty line
+fn doc_lines(docs: &[String], tags: Vec<String>) -> Vec<String> {
+    let mut lines = docs.to_vec();
+    if !lines.is_empty() && !tags.is_empty() {
+        lines.push(String::new());
+    }
+    lines.extend(tags);
+
+    lines
+}
+
+fn deprecation_tag(reason: Option<&str>) -> Vec<String> {
+    reason
+        .map(|reason| vec![format!("@deprecated {reason}")])
+        .unwrap_or_default()
+}
+
+fn generate_constructor(output: &mut String, fields: &[TypedField], namespace: &str) {
+    let documented: Vec<_> = fields
+        .iter()
+        .filter_map(|field| {
+            let native = to_php_type(field.type_name(), namespace);
+            let documented = to_php_doc_type(field.type_name(), namespace);
+
+            (native != documented).then(|| (documented, camel_case(field.name())))
+        })
+        .collect();
+
+    if !documented.is_empty() {
+        output.push_str("    /**\n");
+        for (type_, name) in documented {
+            writeln!(output, "     * @param {type_} ${name}").unwrap();
+        }
+        output.push_str("     */\n");
+    }
+
+    if fields.is_empty() {
+        output.push_str("    public function __construct()\n    {\n    }\n");
+        return;
+    }
+
+    output.push_str("    public function __construct(\n");
+    for field in fields {
+        write_docblock(
+            output,
+            "        ",
+            &doc_lines(field.docs(), deprecation_tag(field.deprecated())),
+        );
+        writeln!(
+            output,
+            "        public readonly {} ${},",
+            to_php_type(field.type_name(), namespace),
+            camel_case(field.name())
+        )
+        .unwrap();
+    }
+    output.push_str("    ) {\n    }\n");
+}
+
+fn generate_from_json(
+    output: &mut String,
+    method_name: &str,
+    fields: &[TypedField],
+    namespace: &str,
+) {
+    writeln!(
+        output,
+        "    public static function {method_name}(mixed $json): self\n    {{"
+    )
+    .unwrap();
+    if fields.is_empty() {
+        writeln!(
+            output,
+            "        {}::object($json);\n\n        return new self();\n    }}",
+            class_name(namespace, "Json")
+        )
+        .unwrap();
+        return;
+    }
+
+    writeln!(
+        output,
+        "        $json = {}::object($json);\n",
+        class_name(namespace, "Json")
+    )
+    .unwrap();
+
+    output.push_str("        return new self(\n");
+    for field in fields {
+        let fallback = field
+            .default()
+            .map_or_else(|| "null".to_string(), to_php_value);
+        writeln!(
+            output,
+            "            {},",
+            from_json(
+                field.type_name(),
+                namespace,
+                &format!("$json['{}'] ?? {fallback}", field.serialized_name())
+            )
+        )
+        .unwrap();
+    }
+    output.push_str("        );\n    }\n");
+}
+
+fn generate_json_object(fields: &[TypedField], namespace: &str, indent: &str) -> String {
+    if fields.is_empty() {
+        return "new \\stdClass()".to_string();
+    }
+
+    generate_json_array(&[], fields, namespace, indent)
+}
+
+// The `entries` are PHP expressions keyed by strings, which come before the fields
+fn generate_json_array(
+    entries: &[(&str, String)],
+    fields: &[TypedField],
+    namespace: &str,
+    indent: &str,
+) -> String {
+    let mut result = "[\n".to_string();
+    for (key, value) in entries {
+        writeln!(result, "{indent}    {} => {value},", to_php_string(key)).unwrap();
+    }
+    for field in fields {
+        writeln!(
+            result,
+            "{indent}    '{}' => {},",
+            field.serialized_name(),
+            to_json(
+                field.type_name(),
+                namespace,
+                &format!("$this->{}", camel_case(field.name()))
+            )
+        )
+        .unwrap();
+    }
+    result.push_str(indent);
+    result.push(']');
+
+    result
+}
+
+fn generate_data_class(
+    namespace: &str,
+    name: &str,
+    fields: &[TypedField],
+    docs: &[String],
+) -> String {
+    let mut output = file_header(namespace);
+
+    write_docblock(&mut output, "", docs);
+    writeln!(
+        output,
+        "final class {name} implements \\JsonSerializable\n{{"
+    )
+    .unwrap();
+    generate_constructor(&mut output, fields, namespace);
+    output.push('\n');
+    generate_from_json(&mut output, "fromJson", fields, namespace);
+    writeln!(
+        output,
+        "\n    public function jsonSerialize(): mixed\n    {{\n        return {};\n    }}\n}}",
+        generate_json_object(fields, namespace, "        ")
+    )
+    .unwrap();
+
+    output
+}
+
+fn generate_constants(namespace: &str, constants: &[TypedConstant]) -> String {
+    let mut output = file_header(namespace);
+
+    output.push_str("final class Constants\n{\n");
+    for (index, constant) in constants.iter().enumerate() {
+        if index > 0 {
+            output.push('\n');
+        }
+        write_docblock(&mut output, "    ", constant.docs());
+        writeln!(
+            output,
+            "    public const {} = {};",
+            constant_case(constant.name()),
+            to_php_value(constant.value())
+        )
+        .unwrap();
+    }
+    output.push_str("}\n");
+
+    output
+}
+
+fn generate_enum(namespace: &str, enum_: &TypedEnum) -> Vec<(String, String)> {
+    let enum_name = enum_.name();
+    let mut base = file_header(namespace);
+
+    write_docblock(&mut base, "", enum_.docs());
+    writeln!(
+        base,
+        "abstract class {enum_name} implements \\JsonSerializable\n{{"
+    )
+    .unwrap();
+    let json = class_name(namespace, "Json");
+    let variant_class = |name| class_name(&format!("{namespace}\\{enum_name}"), name);
+    let read_variant = match enum_.tagging() {
+        // Unit variants are serialized as just their name
+        TypedEnumTagging::External if enum_.variants().iter().any(TypedEnumVariant::is_unit) => {
+            format!("$json = \\is_string($json) ? [$json => null] : {json}::object($json);\n        $variant = array_key_first($json);")
+        }
+        TypedEnumTagging::External => {
+            format!("$json = {json}::object($json);\n        $variant = array_key_first($json);")
+        }
+        TypedEnumTagging::Internal { tag } | TypedEnumTagging::Adjacent { tag, .. } => format!(
+            "$json = {json}::object($json);\n        $variant = {json}::string($json[{}] ?? null);",
+            to_php_string(tag)
+        ),
+    };
+    writeln!(
+        base,
+        "    public static function fromJson(mixed $json): self\n    {{\n        {read_variant}\n\n        return match ($variant) {{"
+    )
+    .unwrap();
+    for variant in enum_.variants() {
+        let contents = match enum_.tagging() {
+            _ if variant.is_unit() => None,
+            TypedEnumTagging::External => Some(format!(
+                "$json[{}]",
+                to_php_string(variant.serialized_name())
+            )),
+            // The fields are next to the tag
+            TypedEnumTagging::Internal { .. } => Some("$json".to_string()),
+            TypedEnumTagging::Adjacent { content, .. } => {
+                Some(format!("$json[{}] ?? null", to_php_string(content)))
+            }
+        };
+        let value = contents.map_or_else(
+            || format!("new {}()", variant_class(variant.name())),
+            |contents| {
+                format!(
+                    "{}::fromContents({contents})",
+                    variant_class(variant.name())
+                )
+            },
+        );
+        writeln!(
+            base,
+            "            {} => {value},",
+            to_php_string(variant.serialized_name())
+        )
+        .unwrap();
+    }
+    writeln!(
+        base,
+        "            default => throw new \\UnexpectedValueException(\"Unknown variant of {enum_name}: {{$variant}}\"),\n        }};\n    }}\n}}"
+    )
+    .unwrap();
+
+    let mut files = vec![(format!("{enum_name}.php"), base)];
+
+    for variant in enum_.variants() {
+        let mut output = file_header(&format!("{namespace}\\{enum_name}"));
+
+        write_docblock(
+            &mut output,
+            "",
+            &doc_lines(variant.docs(), deprecation_tag(variant.deprecated())),
+        );
+        writeln!(
+            output,
+            "final class {} extends {}\n{{",
+            variant.name(),
+            class_name(namespace, enum_name)
+        )
+        .unwrap();
+        generate_constructor(&mut output, variant.fields(), namespace);
+        if !variant.is_unit() {
+            output.push('\n');
+            generate_from_json(&mut output, "fromContents", variant.fields(), namespace);
+        }
+
+        let name = to_php_string(variant.serialized_name());
+        let json = match enum_.tagging() {
+            TypedEnumTagging::External if variant.is_unit() => name,
+            TypedEnumTagging::External => format!(
+                "[\n            {name} => {},\n        ]",
+                generate_json_object(variant.fields(), namespace, "            ")
+            ),
+            TypedEnumTagging::Internal { tag } => {
+                generate_json_array(&[(tag, name)], variant.fields(), namespace, "        ")
+            }
+            TypedEnumTagging::Adjacent { tag, .. } if variant.is_unit() => {
+                generate_json_array(&[(tag, name)], &[], namespace, "        ")
+            }
+            TypedEnumTagging::Adjacent { tag, content } => generate_json_array(
+                &[
+                    (tag, name),
+                    (
+                        content,
+                        generate_json_object(variant.fields(), namespace, "            "),
+                    ),
+                ],
+                &[],
+                namespace,
+                "        ",
+            ),
+        };
+        writeln!(
+            output,
+            "\n    public function jsonSerialize(): mixed\n    {{\n        return {json};\n    }}\n}}"
+        )
+        .unwrap();
+
+        files.push((format!("{enum_name}/{}.php", variant.name()), output));
+    }
+
+    files
+}
+
+// `method_name` is the name the method is called by on the wire
+fn generate_client_method(
+    output: &mut String,
+    method_name: &str,
+    call: &TypedRpcCall,
+    namespace: &str,
+) {
+    let (name, request, response, stream) = match call {
+        TypedRpcCall::Stream {
+            name,
+            request,
+            response,
+            ..
+        } => (name, request, response, true),
+        TypedRpcCall::Unary {
+            name,
+            request,
+            response,
+            ..
+        } => (name, request, response, false),
+    };
+
+    let metadata = class_name(namespace, "Metadata");
+    // Requests of a stream that are not serialized as they are are mapped while they are sent
+    let mut prelude = String::new();
+    let (parameters, request_json) = if call.request_stream() {
+        let request_json = to_json(request, namespace, "$request");
+        if request_json != "$request" {
+            prelude = format!(
+                "        $requests = (static function (iterable $requests): \\Generator {{\n            foreach ($requests as $request) {{\n                yield {request_json};\n            }}\n        }})($requests);\n\n"
+            );
+        }
+
+        (
+            format!("iterable $requests, {metadata} $metadata"),
+            "$requests".to_string(),
+        )
+    } else if request == &TypedFieldType::Void {
+        (format!("{metadata} $metadata"), "null".to_string())
+    } else {
+        (
+            format!(
+                "{} $request, {metadata} $metadata",
+                to_php_type(request, namespace)
+            ),
+            to_json(request, namespace, "$request"),
+        )
+    };
+    let request_doc = to_php_doc_type(request, namespace);
+    let mut tags = deprecation_tag(call.deprecated());
+    if call.request_stream() {
+        tags.push(format!("@param iterable<{request_doc}> $requests"));
+    } else if request != &TypedFieldType::Void && request_doc != to_php_type(request, namespace) {
+        tags.push(format!("@param {request_doc} $request"));
+    }
+    let (call_method, stream_method) = if call.request_stream() {
+        ("clientStream", "bidirectionalStream")
+    } else {
+        ("call", "stream")
+    };
+
+    let body = if stream {
+        tags.push(format!(
+            "@return \\Generator<int, {}, mixed, void>",
+            to_php_doc_type(response, namespace)
+        ));
+
+        format!(
+            "): \\Generator\n    {{\n{prelude}        foreach ($this->raw->{stream_method}('{method_name}', {request_json}, $metadata) as $item) {{\n            yield {};\n        }}\n    }}\n",
+            from_json(response, namespace, "$item")
+        )
+    } else if response == &TypedFieldType::Void {
+        format!(
+            "): void\n    {{\n{prelude}        $this->raw->{call_method}('{method_name}', {request_json}, $metadata);\n    }}\n"
+        )
+    } else {
+        let response_doc = to_php_doc_type(response, namespace);
+        if response_doc != to_php_type(response, namespace) {
+            tags.push(format!("@return {response_doc}"));
+        }
+
+        format!(
+            "): {}\n    {{\n{prelude}        return {};\n    }}\n",
+            to_php_type(response, namespace),
+            from_json(
+                response,
+                namespace,
+                &format!("$this->raw->{call_method}('{method_name}', {request_json}, $metadata)")
+            )
+        )
+    };
+
+    let doc_lines = doc_lines(call.docs(), tags);
+    output.push('\n');
+    if !doc_lines.is_empty() {
+        output.push_str("    /**\n");
+        for line in doc_lines {
+            if line.is_empty() {
+                output.push_str("     *\n");
+            } else {
+                writeln!(output, "     * {line}").unwrap();
+            }
+        }
+        output.push_str("     */\n");
+    }
+    write!(
+        output,
+        "    public function {}({parameters}{body}",
+        camel_case(name)
+    )
+    .unwrap();
+}
+
+fn generate_client(
+    name: &str,
+    docs: &[String],
+    methods: &[(String, &TypedRpcCall)],
+    namespace: &str,
+) -> String {
+    let mut output = file_header(namespace);
+
+    write_docblock(&mut output, "", docs);
+    writeln!(
+        output,
+        "final class {name}\n{{\n    public function __construct(private readonly RawRpcClient $raw)\n    {{\n    }}"
+    )
+    .unwrap();
+    for (method_name, call) in methods {
+        generate_client_method(&mut output, method_name, call, namespace);
+    }
+    output.push_str("}\n");
+
+    output
+}
+
+/// Compiles the file into PHP classes in `namespace`, one class per file as expected by PSR-4 autoloading.
+/// Returns the paths of the files, relative to the directory of the namespace, together with their contents.
+#[must_use]
+pub fn compile(file: &TypedFile, namespace: &str) -> Vec<(String, String)> {
+    let namespace = namespace.trim_matches('\\');
+    let mut files = vec![];
+
+    for (name, contents) in [
+        ("Json", runtime::JSON),
+        ("RpcError", runtime::RPC_ERROR),
+        ("Transport", runtime::TRANSPORT),
+        ("StreamTransport", runtime::STREAM_TRANSPORT),
+        ("RawRpcClient", runtime::RAW_RPC_CLIENT),
+    ] {
+        files.push((format!("{name}.php"), file_header(namespace) + contents));
+    }
+
+    if !file.constants.is_empty() {
+        files.push((
+            "Constants.php".to_string(),
+            generate_constants(namespace, &file.constants),
+        ));
+    }
+
+    files.push((
+        "Metadata.php".to_string(),
+        generate_data_class(namespace, "Metadata", file.meta.fields(), &[]),
+    ));
+
+    for struct_ in &file.structs {
+        files.push((
+            format!("{}.php", struct_.name()),
+            generate_data_class(namespace, struct_.name(), struct_.fields(), struct_.docs()),
+        ));
+    }
+
+    for enum_ in &file.enums {
+        files.extend(generate_enum(namespace, enum_));
+    }
+
+    // The `rpc` block and services are never used together
+    if file.services.is_empty() {
+        let methods: Vec<_> = file
+            .rpc
+            .calls()
+            .iter()
+            .map(|call| (call.name().to_string(), call))
+            .collect();
+        files.push((
+            "Client.php".to_string(),
+            generate_client("Client", &[], &methods, namespace),
+        ));
+    }
+    for service in &file.services {
+        let name = format!("{}Client", service.name());
+        let methods: Vec<_> = service
+            .calls()
+            .iter()
+            .map(|call| (service.method_name(call), call))
+            .collect();
+        files.push((
+            format!("{name}.php"),
+            generate_client(&name, service.docs(), &methods, namespace),
+        ));
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing;
+    use crate::type_checking::TypeChecker;
+
+    #[test]
+    pub fn compile_test() {
+        let input = "\
+            metadata { correlation_id: guid } \
+            /// The version of the protocol\n const VERSION: string = \"1.0\"; const default_volume: f32 = 50; \
+            @rename_all(\"camelCase\") struct Track { title: string, id: guid, artists: Artist[], album: Album?, data: binary, played_at: instant[], skipped_at: instant?, tags: map<string, string[]>, plays_by_day: map<u32, instant[]>?, length: duration, price: decimal, gain: f64 = -6.5, explicit: bool = false, released: date? } \
+            struct Artist { name: string, @deprecated(\"use name\") nickname: string? = \"it's\" } \
+            struct Album { title: string, previous: Album?, plays: u32 = 0 } \
+            /// Something that happened in the player\n enum Event { Played(track: Track, /// When the track started\n at: instant), /// The player was paused\n @deprecated(\"use Played\") Paused } \
+            @tag(\"type\") enum Command { Play(track: Track), Stop } \
+            @rename_all(\"kebab-case\") @tag(\"kind\", \"data\") enum Change { Renamed(name: string), Cleared() , Removed } \
+            rpc { @idempotent @timeout(5s) play(Track) -> void; /// Everything that happens from now on\n events(void) -> stream Event; /// Fetches an album\n ///\n /// Returns null if there is none\n @deprecated(\"use events\") album(guid) -> Album?; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let typed_file = TypeChecker::new().check(&ast).unwrap();
+
+        let rendered: String = compile(&typed_file, "Ramona\\Music")
+            .into_iter()
+            .filter(|(path, _)| !runtime_file(path))
+            .map(|(path, contents)| format!("// {path}\n{contents}\n"))
+            .collect();
+
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    pub fn compile_services_test() {
+        let input = "\
+            struct Track { title: string } \
+            /// Browses the tracks\n service Library { tracks(void) -> stream Track; track(guid) -> Track?; } \
+            service Playback { play(Track) -> void; queue(stream Track) -> u32; chat(stream map<string, u32>) -> stream Track; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let typed_file = TypeChecker::new().check(&ast).unwrap();
+
+        let rendered: String = compile(&typed_file, "Ramona\\Music")
+            .into_iter()
+            .filter(|(path, _)| path.ends_with("Client.php") && !runtime_file(path))
+            .map(|(path, contents)| format!("// {path}\n{contents}\n"))
+            .collect();
+
+        insta::assert_snapshot!(rendered);
+    }
+
+    fn runtime_file(path: &str) -> bool {
+        [
+            "Json.php",
+            "RpcError.php",
+            "Transport.php",
+            "StreamTransport.php",
+            "RawRpcClient.php",
+        ]
+        .contains(&path)
+    }
+
+    #[test]
+    pub fn to_php_type_tests() {
+        let namespace = "Ramona\\Music";
+
+        assert_eq!(to_php_type(&TypedFieldType::U64, namespace), "int");
+        assert_eq!(to_php_type(&TypedFieldType::Guid, namespace), "string");
+        assert_eq!(
+            to_php_type(&TypedFieldType::Instant, namespace),
+            "\\DateTimeImmutable"
+        );
+        assert_eq!(
+            to_php_type(
+                &TypedFieldType::Optional(Box::new(TypedFieldType::OtherStruct("Foo".to_string()))),
+                namespace
+            ),
+            "?\\Ramona\\Music\\Foo"
+        );
+        assert_eq!(
+            to_php_doc_type(
+                &TypedFieldType::Array(Box::new(TypedFieldType::Enum("Foo".to_string()))),
+                namespace
+            ),
+            "list<\\Ramona\\Music\\Foo>"
+        );
+        assert_eq!(
+            to_php_doc_type(
+                &TypedFieldType::Map(
+                    Box::new(TypedFieldType::U32),
+                    Box::new(TypedFieldType::Optional(Box::new(TypedFieldType::String)))
+                ),
+                namespace
+            ),
+            "array<int, ?string>"
+        );
+        assert_eq!(
+            to_php_type(
+                &TypedFieldType::Imported {
+                    module: "events".to_string(),
+                    name: "FileOnMountPath".to_string()
+                },
+                namespace
+            ),
+            "\\Ramona\\Events\\FileOnMountPath"
+        );
+    }
+}
@@ -0,0 +1,419 @@
+// Support classes emitted next to every compiled file, so that the generated code has no dependencies
+
+pub(crate) const JSON: &str = r#"final class Json
+{
+    public static function encode(mixed $value): string
+    {
+        return json_encode($value, JSON_THROW_ON_ERROR | JSON_UNESCAPED_SLASHES | JSON_UNESCAPED_UNICODE);
+    }
+
+    public static function decode(string $json): mixed
+    {
+        return json_decode($json, true, 512, JSON_THROW_ON_ERROR);
+    }
+
+    /**
+     * @return array<array-key, mixed>
+     */
+    public static function object(mixed $value): array
+    {
+        if (!is_array($value)) {
+            throw new \UnexpectedValueException('Expected an object, got ' . get_debug_type($value));
+        }
+
+        return $value;
+    }
+
+    public static function int(mixed $value): int
+    {
+        if (!is_int($value)) {
+            throw new \UnexpectedValueException('Expected an integer, got ' . get_debug_type($value));
+        }
+
+        return $value;
+    }
+
+    public static function float(mixed $value): float
+    {
+        // Whole numbers can be encoded without a fraction, which decodes to an integer
+        if (!is_float($value) && !is_int($value)) {
+            throw new \UnexpectedValueException('Expected a number, got ' . get_debug_type($value));
+        }
+
+        return (float) $value;
+    }
+
+    public static function bool(mixed $value): bool
+    {
+        if (!is_bool($value)) {
+            throw new \UnexpectedValueException('Expected a boolean, got ' . get_debug_type($value));
+        }
+
+        return $value;
+    }
+
+    public static function string(mixed $value): string
+    {
+        if (!is_string($value)) {
+            throw new \UnexpectedValueException('Expected a string, got ' . get_debug_type($value));
+        }
+
+        return $value;
+    }
+
+    public static function decimal(mixed $value): string
+    {
+        $value = self::string($value);
+        if (preg_match('/^-?[0-9]+(\.[0-9]+)?$/D', $value) !== 1) {
+            throw new \UnexpectedValueException("Expected a decimal number, got '{$value}'");
+        }
+
+        return $value;
+    }
+
+    public static function binary(mixed $value): string
+    {
+        return pack('C*', ...self::list($value, self::int(...)));
+    }
+
+    /**
+     * @return list<int>
+     */
+    public static function fromBinary(string $value): array
+    {
+        return array_values(unpack('C*', $value) ?: []);
+    }
+
+    public static function instant(mixed $value): \DateTimeImmutable
+    {
+        // Whole seconds, as sent by older versions
+        if (is_int($value)) {
+            return new \DateTimeImmutable("@{$value}");
+        }
+
+        $value = self::object($value);
+        $seconds = self::int($value['secs_since_epoch'] ?? null);
+        $microseconds = intdiv(self::int($value['nanos_since_epoch'] ?? null), 1000);
+
+        return (new \DateTimeImmutable("@{$seconds}"))->modify("+{$microseconds} microseconds");
+    }
+
+    /**
+     * @return array{secs_since_epoch: int, nanos_since_epoch: int}
+     */
+    public static function fromInstant(\DateTimeImmutable $value): array
+    {
+        return [
+            'secs_since_epoch' => $value->getTimestamp(),
+            'nanos_since_epoch' => (int) $value->format('u') * 1000,
+        ];
+    }
+
+    public static function duration(mixed $value): float
+    {
+        $value = self::object($value);
+
+        return self::int($value['secs'] ?? null) + self::int($value['nanos'] ?? null) / 1_000_000_000;
+    }
+
+    /**
+     * @return array{secs: int, nanos: int}
+     */
+    public static function fromDuration(float $value): array
+    {
+        $seconds = (int) floor($value);
+
+        return [
+            'secs' => $seconds,
+            'nanos' => min((int) round(($value - $seconds) * 1_000_000_000), 999_999_999),
+        ];
+    }
+
+    public static function date(mixed $value): \DateTimeImmutable
+    {
+        $value = self::string($value);
+        $date = \DateTimeImmutable::createFromFormat('!Y-m-d', $value, new \DateTimeZone('UTC'));
+
+        // Out of range days and months are silently moved to the following ones when parsing
+        if ($date === false || $date->format('Y-m-d') !== $value) {
+            throw new \UnexpectedValueException("Expected a date formatted as YYYY-MM-DD, got {$value}");
+        }
+
+        return $date;
+    }
+
+    public static function fromDate(\DateTimeImmutable $value): string
+    {
+        return $value->format('Y-m-d');
+    }
+
+    /**
+     * @template T
+     * @param callable(mixed): T $map
+     * @return list<T>
+     */
+    public static function list(mixed $value, callable $map): array
+    {
+        if (!is_array($value) || !array_is_list($value)) {
+            throw new \UnexpectedValueException('Expected a list, got ' . get_debug_type($value));
+        }
+
+        return array_map($map, $value);
+    }
+
+    /**
+     * @template T
+     * @param callable(mixed): T $map
+     * @return array<array-key, T>
+     */
+    public static function map(mixed $value, callable $map): array
+    {
+        if (!is_array($value)) {
+            throw new \UnexpectedValueException('Expected an object, got ' . get_debug_type($value));
+        }
+
+        return array_map($map, $value);
+    }
+
+    /**
+     * @param array<array-key, mixed> $value
+     * @param callable(mixed): mixed $map
+     */
+    public static function fromMap(array $value, callable $map): \stdClass
+    {
+        return (object) array_map($map, $value);
+    }
+
+    /**
+     * @template T
+     * @param callable(mixed): T $map
+     * @return T|null
+     */
+    public static function optional(mixed $value, callable $map): mixed
+    {
+        return $value === null ? null : $map($value);
+    }
+}
+"#;
+
+pub(crate) const RPC_ERROR: &str = r#"final class RpcError extends \RuntimeException
+{
+    /**
+     * @param list<array{path: string, message: string}> $validationErrors
+     */
+    public function __construct(
+        public readonly string $kind,
+        string $message,
+        public readonly array $validationErrors = [],
+    ) {
+        parent::__construct($message);
+    }
+
+    public static function fromJson(mixed $json): self
+    {
+        $json = Json::object($json);
+        $kind = (string) array_key_first($json);
+        $details = $json[$kind] ?? null;
+
+        // Requests that break the constraints of their fields are rejected with an error for every value
+        if (is_array($details)) {
+            $validationErrors = Json::list($details, static function (mixed $error): array {
+                $error = Json::object($error);
+
+                return [
+                    'path' => Json::string($error['path'] ?? null),
+                    'message' => Json::string($error['message'] ?? null),
+                ];
+            });
+            $messages = array_map(static fn (array $error): string => "{$error['path']}: {$error['message']}", $validationErrors);
+
+            return new self($kind, implode(', ', $messages), $validationErrors);
+        }
+
+        return new self($kind, Json::string($details));
+    }
+}
+"#;
+
+pub(crate) const TRANSPORT: &str = r#"interface Transport
+{
+    public function writeLine(string $line): void;
+    public function readLine(): string;
+}
+"#;
+
+pub(crate) const STREAM_TRANSPORT: &str = r#"final class StreamTransport implements Transport
+{
+    /**
+     * @param resource $stream
+     */
+    public function __construct(private $stream)
+    {
+    }
+
+    public static function connect(string $address, float $timeout = 5.0): self
+    {
+        $stream = stream_socket_client("tcp://{$address}", $errorCode, $errorMessage, $timeout);
+
+        if ($stream === false) {
+            throw new RpcError('IoError', "Failed to connect to {$address}: {$errorMessage}");
+        }
+
+        return new self($stream);
+    }
+
+    public function writeLine(string $line): void
+    {
+        $data = $line . "\n";
+
+        while ($data !== '') {
+            $written = fwrite($this->stream, $data);
+
+            if ($written === false || $written === 0) {
+                throw new RpcError('IoError', 'Failed to write to the connection');
+            }
+
+            $data = substr($data, $written);
+        }
+    }
+
+    public function readLine(): string
+    {
+        $line = fgets($this->stream);
+
+        if ($line === false) {
+            throw new RpcError('IoError', 'The connection was closed');
+        }
+
+        return $line;
+    }
+}
+"#;
+
+pub(crate) const RAW_RPC_CLIENT: &str = r#"/**
+ * Speaks the newline-delimited JSON protocol of `rpc_support`: every request is an envelope line, a metadata line
+ * and a payload line, every response is an envelope line followed by a payload line unless the envelope has an error.
+ * A stream of requests is sent between a "start" and an "end" frame, with an "item" frame for every request.
+ */
+final class RawRpcClient
+{
+    private int $nextRequestId = 0;
+
+    public function __construct(private readonly Transport $transport)
+    {
+    }
+
+    public function call(string $methodName, mixed $request, mixed $metadata): mixed
+    {
+        $requestId = $this->send($methodName, $request, $metadata);
+
+        return $this->receive($requestId)[1];
+    }
+
+    /**
+     * @return \Generator<int, mixed, mixed, void>
+     */
+    public function stream(string $methodName, mixed $request, mixed $metadata): \Generator
+    {
+        $requestId = $this->send($methodName, $request, $metadata);
+
+        yield from $this->responses($requestId);
+    }
+
+    /**
+     * @param iterable<mixed> $requests
+     */
+    public function clientStream(string $methodName, iterable $requests, mixed $metadata): mixed
+    {
+        $requestId = $this->sendStream($methodName, $requests, $metadata);
+
+        return $this->receive($requestId)[1];
+    }
+
+    /**
+     * The connection is not read while requests are written, so all of them are sent before the first response is read.
+     *
+     * @param iterable<mixed> $requests
+     * @return \Generator<int, mixed, mixed, void>
+     */
+    public function bidirectionalStream(string $methodName, iterable $requests, mixed $metadata): \Generator
+    {
+        $requestId = $this->sendStream($methodName, $requests, $metadata);
+
+        yield from $this->responses($requestId);
+    }
+
+    /**
+     * @return \Generator<int, mixed, mixed, void>
+     */
+    private function responses(int $requestId): \Generator
+    {
+        while (true) {
+            [$streamEnd, $payload] = $this->receive($requestId);
+
+            if ($streamEnd) {
+                return;
+            }
+
+            yield $payload;
+        }
+    }
+
+    private function send(string $methodName, mixed $request, mixed $metadata): int
+    {
+        $requestId = $this->nextRequestId++;
+        $this->write(['method_name' => $methodName, 'request_id' => $requestId], $metadata, $request);
+
+        return $requestId;
+    }
+
+    /**
+     * @param iterable<mixed> $requests
+     */
+    private function sendStream(string $methodName, iterable $requests, mixed $metadata): int
+    {
+        $requestId = $this->nextRequestId++;
+        $envelope = static fn (string $stream): array => [
+            'method_name' => $methodName,
+            'request_id' => $requestId,
+            'stream' => $stream,
+        ];
+
+        $this->write($envelope('start'), $metadata, null);
+        foreach ($requests as $request) {
+            $this->write($envelope('item'), null, $request);
+        }
+        $this->write($envelope('end'), null, null);
+
+        return $requestId;
+    }
+
+    /**
+     * @param array<string, mixed> $envelope
+     */
+    private function write(array $envelope, mixed $metadata, mixed $request): void
+    {
+        $this->transport->writeLine(Json::encode($envelope));
+        $this->transport->writeLine(Json::encode($metadata));
+        $this->transport->writeLine(Json::encode($request));
+    }
+
+    /**
+     * @return array{bool, mixed}
+     */
+    private function receive(int $requestId): array
+    {
+        $envelope = Json::object(Json::decode($this->transport->readLine()));
+
+        if (($envelope['request_id'] ?? null) !== $requestId) {
+            throw new RpcError('Custom', "Expected a response to request {$requestId}");
+        }
+
+        if (($envelope['error'] ?? null) !== null) {
+            throw RpcError::fromJson($envelope['error']);
+        }
+
+        return [($envelope['stream_end'] ?? false) === true, Json::decode($this->transport->readLine())];
+    }
+}
+"#;
@@ -1,15 +1,23 @@
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote, TokenStreamExt};
 
-use crate::type_checking::{TypedFieldType, TypedFile, TypedRpc};
+use crate::compiler_openrpc;
+use crate::naming::snake_case;
+use crate::type_checking::{TypedFieldType, TypedFile, TypedRpc, TypedRpcCall, TypedService};
 
 mod traits;
 
 use traits::{
-    generate_enums, generate_header, generate_metadata, generate_rpc_trait, generate_structs,
+    generate_constants, generate_docs, generate_enums, generate_header, generate_metadata,
+    generate_request_param, generate_rpc_trait, generate_structs,
 };
 
-fn generate_rpc_client(rpc: &TypedRpc) -> TokenStream {
+// The methods of a service are called as `Service.method`, those of the `rpc` block by their name
+fn method_name(service: Option<&str>, name: &str) -> String {
+    service.map_or_else(|| name.to_string(), |service| format!("{service}.{name}"))
+}
+
+fn generate_rpc_client(rpc: &TypedRpc, service: Option<&str>) -> TokenStream {
     let mut result = quote! {
         #[allow(unused)] use rpc_support::RawRpcClient;
         #[allow(unused)] use std::sync::atomic::{AtomicU64, Ordering};
@@ -39,23 +47,31 @@ fn generate_rpc_client(rpc: &TypedRpc) -> TokenStream {
             crate::type_checking::TypedRpcCall::Stream {
                 name,
                 request,
+                request_stream,
                 response,
+                ..
             } => {
                 let name_ident = format_ident!("{}", name);
-                let request: syn::Type = syn::parse_str(&to_rust_type(request)).unwrap();
+                let method_name = method_name(service, name);
+                let request_param = generate_request_param(request, *request_stream, true);
                 let response: syn::Type = syn::parse_str(&to_rust_type(response)).unwrap();
+                let (send, request) = if *request_stream {
+                    (quote!(send_rpc_bidirectional_stream), quote!(requests))
+                } else {
+                    (quote!(send_rpc_stream_request), quote!(&request))
+                };
 
                 quote! {
                     async fn #name_ident(
                         &mut self,
-                        request: #request,
+                        #request_param,
                         metadata: Metadata,
                     ) -> Result<Pin<Box<dyn Stream<Item = Result<#response, RpcError>> + Unpin + Send>>, RpcError> {
                         self.raw
-                            .send_rpc_stream_request(
+                            .#send(
                                 self.id.fetch_add(1, Ordering::AcqRel),
-                                #name,
-                                &request,
+                                #method_name,
+                                #request,
                                 &metadata,
                             )
                             .await
@@ -65,22 +81,57 @@ fn generate_rpc_client(rpc: &TypedRpc) -> TokenStream {
             crate::type_checking::TypedRpcCall::Unary {
                 name,
                 request,
+                request_stream,
                 response,
+                options,
+                ..
             } => {
                 let name_ident = format_ident!("{}", name);
-                let request: syn::Type = syn::parse_str(&to_rust_type(request)).unwrap();
+                let method_name = method_name(service, name);
+                let request_param = generate_request_param(request, *request_stream, true);
                 let response: syn::Type = syn::parse_str(&to_rust_type(response)).unwrap();
+                let timeout = match options.timeout {
+                    Some(timeout) => {
+                        let millis = proc_macro2::Literal::u64_unsuffixed(
+                            u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX),
+                        );
+                        quote!(Some(std::time::Duration::from_millis(#millis)))
+                    }
+                    None => quote!(None),
+                };
+                let id = quote!(self.id.fetch_add(1, Ordering::AcqRel));
+
+                // Idempotent calls are sent again by the raw client, the requests of a stream are
+                // used up by the first attempt so those calls can only time out
+                let call = if *request_stream {
+                    let call = quote! {
+                        self.raw.send_rpc_client_stream(#id, #method_name, requests, &metadata)
+                    };
+
+                    if options.timeout.is_some() {
+                        quote!(rpc_support::with_timeout(#timeout, #method_name, #call))
+                    } else {
+                        call
+                    }
+                } else if options.idempotent || options.timeout.is_some() {
+                    let idempotent = options.idempotent;
+
+                    quote! {
+                        self.raw.send_rpc_with_options(
+                            #id,
+                            #method_name,
+                            &request,
+                            &metadata,
+                            rpc_support::CallOptions { timeout: #timeout, idempotent: #idempotent },
+                        )
+                    }
+                } else {
+                    quote!(self.raw.send_rpc(#id, #method_name, &request, &metadata))
+                };
 
                 quote! {
-                    async fn #name_ident(&mut self, request: #request, metadata: Metadata) -> Result<#response, RpcError> {
-                        self.raw
-                            .send_rpc(
-                                self.id.fetch_add(1, Ordering::AcqRel),
-                                #name,
-                                &request,
-                                &metadata,
-                            )
-                            .await
+                    async fn #name_ident(&mut self, #request_param, metadata: Metadata) -> Result<#response, RpcError> {
+                        #call.await
                     }
                 }
             }
@@ -99,38 +150,256 @@ fn generate_rpc_client(rpc: &TypedRpc) -> TokenStream {
     result
 }
 
-fn generate_rpc_server_method_match(rpc: &TypedRpc) -> TokenStream {
+// The `MockRpcClient` has a `MockMethod` field for every method, named like the method, through
+// which tests set the responses and read the requests
+fn generate_mock_client(rpc: &TypedRpc) -> TokenStream {
+    let mut fields = quote!();
+    let mut constructors = quote!();
+    let mut verifications = quote!();
+    let mut rpc_methods = quote!();
+
+    for method in &rpc.calls {
+        let (name, request, request_stream, response) = match method {
+            TypedRpcCall::Stream {
+                name,
+                request,
+                request_stream,
+                response,
+                ..
+            }
+            | TypedRpcCall::Unary {
+                name,
+                request,
+                request_stream,
+                response,
+                ..
+            } => (name, request, *request_stream, response),
+        };
+        let name_ident = format_ident!("{}", name);
+        let request_param = generate_request_param(request, request_stream, true);
+        let response: syn::Type = syn::parse_str(&to_rust_type(response)).unwrap();
+        let mut request: syn::Type = syn::parse_str(&to_rust_type(request)).unwrap();
+
+        // The requests of a stream are recorded once the stream ends
+        let call_request = if request_stream {
+            request = syn::parse_quote!(Vec<#request>);
+            quote!(futures::StreamExt::collect(requests).await)
+        } else {
+            quote!(request)
+        };
+
+        let method_impl = if matches!(method, TypedRpcCall::Stream { .. }) {
+            fields.append_all(quote! {
+                pub #name_ident: MockMethod<#request, Metadata, Vec<Result<#response, RpcError>>>,
+            });
+
+            quote! {
+                async fn #name_ident(
+                    &mut self,
+                    #request_param,
+                    metadata: Metadata,
+                ) -> Result<Pin<Box<dyn Stream<Item = Result<#response, RpcError>> + Unpin + Send>>, RpcError> {
+                    let responses = self.#name_ident.call(#call_request, metadata)?;
+                    Ok(Box::pin(futures::stream::iter(responses)))
+                }
+            }
+        } else {
+            fields.append_all(quote! {
+                pub #name_ident: MockMethod<#request, Metadata, #response>,
+            });
+
+            quote! {
+                async fn #name_ident(&mut self, #request_param, metadata: Metadata) -> Result<#response, RpcError> {
+                    self.#name_ident.call(#call_request, metadata)
+                }
+            }
+        };
+
+        constructors.append_all(quote!(#name_ident: MockMethod::new(#name),));
+        verifications.append_all(quote!(self.#name_ident.verify();));
+        rpc_methods.append_all(method_impl);
+    }
+
+    quote! {
+        use rpc_support::testing::MockMethod;
+
+        /// An `RpcClient` for tests, which answers the calls of every method with the responses
+        /// they are expected with and records them
+        pub struct MockRpcClient {
+            #fields
+        }
+
+        impl Default for MockRpcClient {
+            fn default() -> Self {
+                Self { #constructors }
+            }
+        }
+
+        impl MockRpcClient {
+            /// # Panics
+            /// Panics if some of the expected calls were not made
+            pub fn verify(&self) {
+                #verifications
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl RpcClient for MockRpcClient {
+            #rpc_methods
+        }
+    }
+}
+
+// Mocks and fakes for tests, only compiled with the `testing` feature of the crate that includes the
+// generated code. `serve` starts serving the in-memory `connection` with `rpc`.
+fn generate_testing(rpc: &TypedRpc, serve: &TokenStream) -> TokenStream {
+    let mock_client = generate_mock_client(rpc);
+
+    quote! {
+        #[cfg(feature = "testing")]
+        pub mod testing {
+            use super::*;
+
+            #mock_client
+
+            /// A `Client` whose calls are served by `rpc` like a `Server` serves them, over an
+            /// in-memory connection instead of a TCP one
+            ///
+            /// # Panics
+            /// Panics when called outside of a Tokio runtime
+            pub fn loopback<T>(rpc: Arc<T>) -> Client<rpc_support::DefaultRawRpcClient>
+            where
+                T: RpcServer + Send + Sync + 'static,
+            {
+                let (raw, connection) = rpc_support::testing::loopback();
+                #serve
+
+                Client::new(raw)
+            }
+        }
+    }
+}
+
+// Binds the `result` of the call, the methods with `@requires` are only called once `authorize`
+// accepts the caller
+fn generate_rpc_server_call(
+    name: &str,
+    request: &TypedFieldType,
+    request_stream: bool,
+    requires: &[String],
+) -> TokenStream {
+    let call = generate_rpc_server_method_call(name, request, request_stream);
+
+    if requires.is_empty() {
+        return quote! {
+            let result = #call;
+        };
+    }
+
+    quote! {
+        let authorized = rpc.authorize(#name, &[#(#requires),*], &call.metadata).await;
+        let result = match authorized {
+            Ok(()) => #call,
+            Err(error) => Err(error),
+        };
+    }
+}
+
+// Requests that contain structs or enums are validated before they are passed to the `RpcServer`,
+// the requests of a stream as they arrive. Requests that cannot be deserialized are answered with
+// the error instead of calling the method.
+fn generate_rpc_server_method_call(
+    name: &str,
+    request: &TypedFieldType,
+    request_stream: bool,
+) -> TokenStream {
+    let name_ident = format_ident!("{}", name);
+
+    if request_stream {
+        let requests = if contains_definitions(request) {
+            let request: syn::Type = syn::parse_str(&to_rust_type(request)).unwrap();
+            quote! {
+                Box::pin(requests.map(|request: Result<#request, RpcError>| {
+                    let request = request?;
+                    request.validate().map_err(RpcError::Validation)?;
+                    Ok(request)
+                }))
+            }
+        } else {
+            quote!(requests)
+        };
+
+        return quote! {
+            match call.request_stream() {
+                Ok(requests) => rpc.#name_ident(#requests, call.metadata, Arc::downgrade(&client)).await,
+                Err(error) => Err(error),
+            }
+        };
+    }
+
+    let request_type: syn::Type = syn::parse_str(&to_rust_type(request)).unwrap();
+    let call = if contains_definitions(request) {
+        quote! {
+            match request.validate() {
+                Ok(()) => rpc.#name_ident(request, call.metadata, Arc::downgrade(&client)).await,
+                Err(errors) => Err(RpcError::Validation(errors)),
+            }
+        }
+    } else {
+        quote! {
+            rpc.#name_ident(request, call.metadata, Arc::downgrade(&client)).await
+        }
+    };
+
+    quote! {
+        match serde_json::from_str::<#request_type>(&call.payload_line) {
+            Ok(request) => #call,
+            Err(error) => Err(RpcError::from(error)),
+        }
+    }
+}
+
+// Matches on the `method_name` of the `call`
+fn generate_rpc_server_method_match(rpc: &TypedRpc, service: Option<&str>) -> TokenStream {
     let mut method_cases = quote! {};
 
     for method in &rpc.calls {
         let case = match method {
             crate::type_checking::TypedRpcCall::Stream {
                 name,
-                request: _,
-                response: _,
+                request,
+                request_stream,
+                options,
+                ..
             } => {
-                let name_ident = format_ident!("{}", name);
+                let call =
+                    generate_rpc_server_call(name, request, *request_stream, &options.requires);
+                let name = self::method_name(service, name);
 
                 quote! {
                     #name => {
-                        let result = rpc.lock().await.#name_ident(serde_json::from_str(&payload_line)?, metadata, Arc::downgrade(&client)).await;
+                        #call
 
-                        send_stream_response(client.clone(), result, request_id).await?;
+                        send_stream_response(client.clone(), result, call.request_id).await?;
                     }
                 }
             }
             crate::type_checking::TypedRpcCall::Unary {
                 name,
-                request: _,
-                response: _,
+                request,
+                request_stream,
+                options,
+                ..
             } => {
-                let name_ident = format_ident!("{}", name);
+                let call =
+                    generate_rpc_server_call(name, request, *request_stream, &options.requires);
+                let name = self::method_name(service, name);
 
                 quote! {
                     #name => {
-                        let result = rpc.lock().await.#name_ident(serde_json::from_str(&payload_line)?, metadata, Arc::downgrade(&client)).await;
+                        #call
 
-                        send_response(client.clone(), result, request_id, false).await?;
+                        send_response(client.clone(), result, call.request_id, false).await?;
                     }
                 }
             }
@@ -138,36 +407,54 @@ fn generate_rpc_server_method_match(rpc: &TypedRpc) -> TokenStream {
         method_cases.append_all(case);
     }
 
+    // Only the requests of a stream are taken out of the call
+    let call_binding = if rpc.calls.iter().any(TypedRpcCall::request_stream) {
+        quote!(let mut call = call;)
+    } else {
+        quote!()
+    };
+
     quote! {
+        #call_binding
+        let method_name = call.method_name.clone();
         match method_name.as_str() {
             #method_cases
 
-            // fixme do not panic here!
-            _ => panic!("Unknown method name: {method_name}"),
+            _ => {
+                send_error(client.clone(), RpcError::UnknownMethod(method_name), call.request_id).await?;
+            }
         }
     }
 }
 
-fn generate_rpc_server(rpc: &TypedRpc) -> TokenStream {
-    let method_match = generate_rpc_server_method_match(rpc);
+// The methods with a stream of requests or responses, whose calls are aborted when the client
+// disconnects
+fn generate_stream_methods(rpc: &TypedRpc, service: Option<&str>) -> TokenStream {
+    let names = rpc
+        .calls
+        .iter()
+        .filter(|method| matches!(method, TypedRpcCall::Stream { .. }) || method.request_stream())
+        .map(|method| self::method_name(service, method.name()));
 
-    let result = quote! {
+    quote! {
+        const STREAM_METHODS: &[&str] = &[#(#names),*];
+    }
+}
+
+// The imports, errors and responses shared by the `Server` of the `rpc` block and the ones of the
+// services. `description` is the JSON the `__describe` method is answered with.
+fn generate_server_support(description: &str) -> TokenStream {
+    quote! {
         use tracing::info;
         use thiserror::Error;
         use std::sync::Arc;
         use tokio::sync::Mutex;
+        use tokio::io::{AsyncRead, AsyncWrite};
         use tokio::net::TcpListener;
-        use rpc_support::send_response;
+        use rpc_support::{send_response, Call, CallError};
         #[allow(unused)] use rpc_support::send_stream_response;
-        use rpc_support::read_request;
-
-        pub struct Server<TRpc>
-        where
-            TRpc: RpcServer + Send + Sync,
-        {
-            tcp: Arc<Mutex<TcpListener>>,
-            rpc: Arc<Mutex<TRpc>>,
-        }
+        #[allow(unused)] use rpc_support::validation::Validate;
+        #[allow(unused)] use futures::StreamExt;
 
         #[derive(Debug, Error)]
         pub enum ClientError {
@@ -185,26 +472,101 @@ fn generate_rpc_server(rpc: &TypedRpc) -> TokenStream {
             IoError(#[from] tokio::io::Error),
         }
 
+        /// The methods of the server and the schemas of their requests and responses, as the
+        /// `methods` and `components` of an OpenRPC document
+        pub const SERVER_DESCRIPTION: &str = #description;
+
+        async fn send_description(client: Arc<Mutex<dyn rpc_support::Client>>, request_id: u64) -> Result<(), ClientError> {
+            let description: serde_json::Value = serde_json::from_str(SERVER_DESCRIPTION)?;
+            send_response(client, Ok(description), request_id, false).await?;
+
+            Ok(())
+        }
+
+        // The error ends the call, also when the client waits for a stream of responses
+        async fn send_error(client: Arc<Mutex<dyn rpc_support::Client>>, error: RpcError, request_id: u64) -> Result<(), ClientError> {
+            send_response(client, Result::<(), _>::Err(error), request_id, true).await?;
+
+            Ok(())
+        }
+
+        // Calls that cannot be served are answered with an error, the connection is only closed
+        // when it cannot be read anymore
+        async fn next_call(reader: &mut rpc_support::CallReader<impl tokio::io::AsyncBufRead + Unpin + Send>, client: &Arc<Mutex<dyn rpc_support::Client>>) -> Result<Call<Metadata>, ClientError> {
+            loop {
+                match reader.next_call().await {
+                    Ok(call) if call.method_name == rpc_support::DESCRIBE_METHOD => {
+                        send_description(client.clone(), call.request_id).await?;
+                    }
+                    Ok(call) => return Ok(call),
+                    Err(CallError::Invalid { request_id, error }) => {
+                        send_error(client.clone(), error, request_id).await?;
+                    }
+                    Err(CallError::Connection(error)) => return Err(error.into()),
+                }
+            }
+        }
+    }
+}
+
+fn generate_rpc_server(rpc: &TypedRpc, description: &str) -> TokenStream {
+    let method_match = generate_rpc_server_method_match(rpc, None);
+    let stream_methods = generate_stream_methods(rpc, None);
+
+    let mut result = generate_server_support(description);
+    result.append_all(quote! {
+        #stream_methods
+
+        pub struct Server<TRpc>
+        where
+            TRpc: RpcServer + Send + Sync,
+        {
+            tcp: Arc<Mutex<TcpListener>>,
+            rpc: Arc<TRpc>,
+        }
+
         impl<T> Server<T>
         where
             T: RpcServer + Send + Sync + 'static,
         {
             /// # Errors
             /// Will return an error when establishing the TCP Listener fails
-            pub async fn new(addr: &str, rpc: Arc<Mutex<T>>) -> Result<Self, RpcError> {
+            pub async fn new(addr: &str, rpc: Arc<T>) -> Result<Self, RpcError> {
                 let tcp = Arc::new(Mutex::new(TcpListener::bind(addr).await?));
                 Ok(Server { tcp, rpc })
             }
 
-            async fn handle_client(client: Arc<Mutex<dyn rpc_support::Client>>, rpc: Arc<Mutex<T>>) -> Result<(), ClientError> {
-                loop {
-                    let (payload_line, method_name, request_id, metadata): (String, String, u64, Metadata) =
-                        read_request(client.clone()).await?;
+            // Every call is handled by its own task, so that the calls of a connection are served
+            // concurrently and the requests of streams keep being read. The calls with streams are
+            // aborted when the connection is closed.
+            async fn handle_client(socket: impl AsyncRead + AsyncWrite + Send + 'static, rpc: Arc<T>) -> Result<(), ClientError> {
+                let (mut reader, writer) = rpc_support::split_connection(socket);
+                let client: Arc<Mutex<dyn rpc_support::Client>> = Arc::new(Mutex::new(writer));
+                let calls = rpc_support::CallTasks::default();
 
-                    #method_match
+                loop {
+                    let call = next_call(&mut reader, &client).await?;
+
+                    let stream = STREAM_METHODS.contains(&call.method_name.as_str());
+                    let task = platform::async_infra::run_with_error_handling(
+                        Self::handle_call(call, client.clone(), rpc.clone()),
+                    );
+                    if stream {
+                        calls.spawn_stream(task);
+                    } else {
+                        calls.spawn(task);
+                    }
                 }
             }
 
+            // Deprecated methods still have to be served to the clients that use them
+            #[allow(deprecated)]
+            async fn handle_call(call: Call<Metadata>, client: Arc<Mutex<dyn rpc_support::Client>>, rpc: Arc<T>) -> Result<(), ClientError> {
+                #method_match
+
+                Ok(())
+            }
+
             /// # Errors
             /// Will return an error if the connection fails
             pub async fn run(self) -> Result<(), RunError> {
@@ -215,51 +577,290 @@ fn generate_rpc_server(rpc: &TypedRpc) -> TokenStream {
                     let rpc = self.rpc.clone();
 
                     tokio::spawn(platform::async_infra::run_with_error_handling(
-                        Self::handle_client(Arc::new(Mutex::new(rpc_support::DefaultClient::new(socket))), rpc),
+                        Self::handle_client(socket, rpc),
                     ));
                 }
             }
         }
-    };
+    });
+
+    result
+}
+
+// Each service handles the requests for its methods, the `CombinedServer` routes them to the
+// service named before the "." of the method name
+fn generate_combined_server(description: &str) -> TokenStream {
+    let mut result = generate_server_support(description);
+    result.append_all(quote! {
+        use std::collections::HashMap;
+
+        /// A service that can be hosted by a `CombinedServer`
+        #[async_trait::async_trait]
+        pub trait Service: Send + Sync {
+            /// The name before the "." in the names of the methods of the service
+            fn name(&self) -> &'static str;
+
+            /// Whether the method has a stream of requests or responses
+            fn is_stream_method(&self, method_name: &str) -> bool;
+
+            /// # Errors
+            /// Will return an error if the request cannot be read or the response cannot be sent
+            async fn handle_request(
+                &self,
+                client: Arc<Mutex<dyn rpc_support::Client>>,
+                call: Call<Metadata>,
+            ) -> Result<(), ClientError>;
+        }
+
+        /// Hosts several services on one TCP listener
+        pub struct CombinedServer {
+            tcp: Arc<Mutex<TcpListener>>,
+            services: HashMap<&'static str, Arc<dyn Service>>,
+        }
+
+        impl CombinedServer {
+            /// # Errors
+            /// Will return an error when establishing the TCP Listener fails
+            pub async fn new(addr: &str) -> Result<Self, RpcError> {
+                let tcp = Arc::new(Mutex::new(TcpListener::bind(addr).await?));
+                Ok(CombinedServer { tcp, services: HashMap::new() })
+            }
+
+            #[must_use]
+            pub fn with_service(mut self, service: impl Service + 'static) -> Self {
+                self.services.insert(service.name(), Arc::new(service));
+                self
+            }
+
+            // Every call is handled by its own task, so that the calls of a connection are served
+            // concurrently and the requests of streams keep being read. The calls with streams are
+            // aborted when the connection is closed.
+            async fn handle_client(socket: impl AsyncRead + AsyncWrite + Send + 'static, services: Arc<HashMap<&'static str, Arc<dyn Service>>>) -> Result<(), ClientError> {
+                let (mut reader, writer) = rpc_support::split_connection(socket);
+                let client: Arc<Mutex<dyn rpc_support::Client>> = Arc::new(Mutex::new(writer));
+                let calls = rpc_support::CallTasks::default();
+
+                loop {
+                    let call = next_call(&mut reader, &client).await?;
+
+                    match call.method_name.split_once('.').and_then(|(service, _)| services.get(service)) {
+                        Some(service) => {
+                            let stream = service.is_stream_method(&call.method_name);
+                            let (service, client) = (service.clone(), client.clone());
+                            let task = platform::async_infra::run_with_error_handling(async move {
+                                service.handle_request(client, call).await
+                            });
+                            if stream {
+                                calls.spawn_stream(task);
+                            } else {
+                                calls.spawn(task);
+                            }
+                        }
+
+                        None => {
+                            send_error(client.clone(), RpcError::UnknownMethod(call.method_name), call.request_id).await?;
+                        }
+                    }
+                }
+            }
+
+            /// # Errors
+            /// Will return an error if the connection fails
+            pub async fn run(self) -> Result<(), RunError> {
+                let services = Arc::new(self.services);
+                loop {
+                    let (socket, address) = self.tcp.lock().await.accept().await?;
+                    info!("New client connected: {}", address);
+
+                    let services = services.clone();
+
+                    tokio::spawn(platform::async_infra::run_with_error_handling(
+                        Self::handle_client(socket, services),
+                    ));
+                }
+            }
+        }
+    });
 
     result
 }
 
+fn generate_service(service: &TypedService) -> TokenStream {
+    let module = format_ident!("{}", snake_case(service.name()));
+    let docs = generate_docs(service.docs());
+    let name = service.name();
+    let traits = generate_rpc_trait(&service.rpc);
+    let client = generate_rpc_client(&service.rpc, Some(name));
+    let method_match = generate_rpc_server_method_match(&service.rpc, Some(name));
+    let stream_methods = generate_stream_methods(&service.rpc, Some(name));
+    let testing = generate_testing(
+        &service.rpc,
+        &quote! {
+            let handler: Arc<dyn Service> = Arc::new(Handler::new(rpc));
+            let services = Arc::new(HashMap::from([(#name, handler)]));
+            tokio::spawn(platform::async_infra::run_with_error_handling(
+                CombinedServer::handle_client(connection, services),
+            ));
+        },
+    );
+
+    quote! {
+        #docs
+        pub mod #module {
+            use super::*;
+
+            #traits
+            #client
+            #stream_methods
+
+            /// Handles the requests for the methods of the service with an `RpcServer`
+            pub struct Handler<TRpc>
+            where
+                TRpc: RpcServer + Send + Sync,
+            {
+                rpc: Arc<TRpc>,
+            }
+
+            impl<T> Handler<T>
+            where
+                T: RpcServer + Send + Sync + 'static,
+            {
+                pub fn new(rpc: Arc<T>) -> Self {
+                    Handler { rpc }
+                }
+            }
+
+            #[async_trait::async_trait]
+            impl<T> Service for Handler<T>
+            where
+                T: RpcServer + Send + Sync + 'static,
+            {
+                fn name(&self) -> &'static str {
+                    #name
+                }
+
+                fn is_stream_method(&self, method_name: &str) -> bool {
+                    STREAM_METHODS.contains(&method_name)
+                }
+
+                // Deprecated methods still have to be served to the clients that use them
+                #[allow(deprecated)]
+                async fn handle_request(
+                    &self,
+                    client: Arc<Mutex<dyn rpc_support::Client>>,
+                    call: Call<Metadata>,
+                ) -> Result<(), ClientError> {
+                    let rpc = &self.rpc;
+
+                    #method_match
+
+                    Ok(())
+                }
+            }
+
+            /// Serves only this service, use a `CombinedServer` to serve it together with others
+            pub struct Server {
+                combined: CombinedServer,
+            }
+
+            impl Server {
+                /// # Errors
+                /// Will return an error when establishing the TCP Listener fails
+                pub async fn new<T>(addr: &str, rpc: Arc<T>) -> Result<Self, RpcError>
+                where
+                    T: RpcServer + Send + Sync + 'static,
+                {
+                    let combined = CombinedServer::new(addr).await?.with_service(Handler::new(rpc));
+                    Ok(Server { combined })
+                }
+
+                /// # Errors
+                /// Will return an error if the connection fails
+                pub async fn run(self) -> Result<(), RunError> {
+                    self.combined.run().await
+                }
+            }
+
+            #testing
+        }
+    }
+}
+
 #[must_use]
 /// # Panics
 /// TODO make this not panic
 pub fn compile(file: TypedFile) -> String {
+    let description = compiler_openrpc::describe(&file).to_string();
     let TypedFile {
+        constants,
         structs,
         meta,
         rpc,
         enums,
+        services,
     } = file;
 
     let mut result = generate_header();
 
+    result.append_all(generate_constants(&constants));
     result.append_all(generate_metadata(&meta));
     result.append_all(generate_structs(&structs));
     result.append_all(generate_enums(&enums));
-    result.append_all(generate_rpc_trait(&rpc));
 
-    result.append_all(generate_rpc_client(&rpc));
-    result.append_all(generate_rpc_server(&rpc));
+    // The type checker only allows an `rpc` block in files without services
+    if services.is_empty() {
+        result.append_all(generate_rpc_trait(&rpc));
+        result.append_all(generate_rpc_client(&rpc, None));
+        result.append_all(generate_rpc_server(&rpc, &description));
+        result.append_all(generate_testing(
+            &rpc,
+            &quote! {
+                tokio::spawn(platform::async_infra::run_with_error_handling(
+                    Server::handle_client(connection, rpc),
+                ));
+            },
+        ));
+    } else {
+        result.append_all(generate_combined_server(&description));
+        for service in &services {
+            result.append_all(generate_service(service));
+        }
+    }
 
     prettyplease::unparse(&syn::parse_file(&result.to_string()).unwrap())
 }
 
+/// Whether values of the type contain structs or enums, which implement `rpc_support::validation::Validate`
+fn contains_definitions(type_: &TypedFieldType) -> bool {
+    match type_ {
+        TypedFieldType::OtherStruct(_)
+        | TypedFieldType::Enum(_)
+        | TypedFieldType::Imported { .. } => true,
+        TypedFieldType::Optional(inner)
+        | TypedFieldType::Array(inner)
+        | TypedFieldType::Map(_, inner)
+        | TypedFieldType::Indirect(inner) => contains_definitions(inner),
+        _ => false,
+    }
+}
+
 fn to_rust_type(type_: &TypedFieldType) -> String {
     match type_ {
         TypedFieldType::U8 => "u8".to_string(),
         TypedFieldType::U16 => "u16".to_string(),
         TypedFieldType::U32 => "u32".to_string(),
         TypedFieldType::U64 => "u64".to_string(),
-        TypedFieldType::S8 => "s8".to_string(),
-        TypedFieldType::S16 => "s16".to_string(),
-        TypedFieldType::S32 => "s32".to_string(),
-        TypedFieldType::S64 => "s64".to_string(),
+        TypedFieldType::S8 => "i8".to_string(),
+        TypedFieldType::S16 => "i16".to_string(),
+        TypedFieldType::S32 => "i32".to_string(),
+        TypedFieldType::S64 => "i64".to_string(),
+        TypedFieldType::F32 => "f32".to_string(),
+        TypedFieldType::F64 => "f64".to_string(),
+        TypedFieldType::Bool => "bool".to_string(),
+        TypedFieldType::Decimal => "rust_decimal::Decimal".to_string(),
         TypedFieldType::Instant => "std::time::SystemTime".to_string(),
+        TypedFieldType::Duration => "std::time::Duration".to_string(),
+        TypedFieldType::Date => "time::Date".to_string(),
         TypedFieldType::Guid => "uuid::Uuid".to_string(),
         TypedFieldType::String => "String".to_string(),
         TypedFieldType::Void => "()".to_string(),
@@ -267,12 +868,72 @@ fn to_rust_type(type_: &TypedFieldType) -> String {
         TypedFieldType::OtherStruct(name) | TypedFieldType::Enum(name) => name.clone(),
         TypedFieldType::Optional(type_) => format!("Option<{}>", to_rust_type(type_)),
         TypedFieldType::Array(type_) => format!("Vec<{}>", to_rust_type(type_)),
+        // Ordered, so that serializing the same value always gives the same result
+        TypedFieldType::Map(key, value) => format!(
+            "std::collections::BTreeMap<{}, {}>",
+            to_rust_type(key),
+            to_rust_type(value)
+        ),
+        TypedFieldType::Indirect(type_) => format!("Box<{}>", to_rust_type(type_)),
+        TypedFieldType::Imported { module, name } => {
+            format!("::{}::{name}", module.replace('-', "_"))
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parsing;
+    use crate::type_checking::TypeChecker;
+
+    #[test]
+    pub fn compile_test() {
+        let input = "\
+            metadata { correlation_id: guid, source: string = \"unknown\" } \
+            const default_port: u16 = 7654; const VERSION: string = \"1.0\"; \
+            @derive(\"PartialEq\") @rename_all(\"camelCase\") struct Track { title: string @non_empty @max_length(200), id: guid, artists: Artist[], album: Album?, plays: u32 = 0, tags: map<string, string[]>, length: duration, price: decimal, gain: f32 @min(-60) @max(12) = -6.5, explicit: bool = false } \
+            struct Release { date: date, reissues: date[], announced: date? } \
+            @derive(\"Eq\", \"Hash\") @non_exhaustive struct Artist { name: string, id: guid, @deprecated(\"use the name\") nickname: string? @pattern(\"[a-z0-9_]+\") } \
+            @derive(\"PartialEq\", \"Default\") struct Album { title: string, tracks: Track[], previous: Album?, rating: s8? = -1 } \
+            enum Event { Played(track: Track, at: instant, volume: u8 @max(100) = 100), @deprecated(\"use Skipped\") Paused, Skipped(from: Track, to: Track) } \
+            @derive(\"PartialEq\") @rename_all(\"snake_case\") @tag(\"type\") enum Command { Play(track: Track), Stop } \
+            rpc { @idempotent @timeout(5s) play(Track) -> void; events(void) -> stream Event; @deprecated(\"use events\") @requires(\"library\") album(guid) -> Album?; @timeout(1m) queue(stream Track) -> u32; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let typed_file = TypeChecker::new().check(&ast).unwrap();
+
+        insta::assert_snapshot!(compile(typed_file));
+    }
+
+    #[test]
+    pub fn compile_services_test() {
+        let input = "\
+            metadata { correlation_id: guid } \
+            struct Track { title: string @non_empty } \
+            /// Browses the tracks\n service Library { tracks(void) -> stream Track; track(guid) -> Track?; } \
+            service Playback { @requires(\"playback\", \"write\") play(Track) -> void; @deprecated(\"use play\") resume(void) -> void; chat(stream guid) -> stream Track; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let typed_file = TypeChecker::new().check(&ast).unwrap();
+
+        insta::assert_snapshot!(compile(typed_file));
+    }
+
+    #[test]
+    pub fn compile_recursive_definitions_test() {
+        let input = "\
+            struct A { next: A??, x: u8 } \
+            struct Node { parent: Node?, children: Node[] } \
+            enum List { Cons(head: u8, tail: List??), Nil }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let typed_file = TypeChecker::new().check(&ast).unwrap();
+
+        let mut definitions = generate_structs(&typed_file.structs);
+        definitions.append_all(generate_enums(&typed_file.enums));
+
+        insta::assert_snapshot!(prettyplease::unparse(
+            &syn::parse_file(&definitions.to_string()).unwrap()
+        ));
+    }
 
     #[test]
     pub fn to_rust_type_tests() {
@@ -280,10 +941,22 @@ mod tests {
         assert_eq!(to_rust_type(&TypedFieldType::U16), "u16");
         assert_eq!(to_rust_type(&TypedFieldType::U32), "u32");
         assert_eq!(to_rust_type(&TypedFieldType::U64), "u64");
-        assert_eq!(to_rust_type(&TypedFieldType::S8), "s8");
-        assert_eq!(to_rust_type(&TypedFieldType::S16), "s16");
-        assert_eq!(to_rust_type(&TypedFieldType::S32), "s32");
-        assert_eq!(to_rust_type(&TypedFieldType::S64), "s64");
+        assert_eq!(to_rust_type(&TypedFieldType::S8), "i8");
+        assert_eq!(to_rust_type(&TypedFieldType::S16), "i16");
+        assert_eq!(to_rust_type(&TypedFieldType::S32), "i32");
+        assert_eq!(to_rust_type(&TypedFieldType::S64), "i64");
+        assert_eq!(to_rust_type(&TypedFieldType::F32), "f32");
+        assert_eq!(to_rust_type(&TypedFieldType::F64), "f64");
+        assert_eq!(to_rust_type(&TypedFieldType::Bool), "bool");
+        assert_eq!(
+            to_rust_type(&TypedFieldType::Decimal),
+            "rust_decimal::Decimal"
+        );
+        assert_eq!(
+            to_rust_type(&TypedFieldType::Duration),
+            "std::time::Duration"
+        );
+        assert_eq!(to_rust_type(&TypedFieldType::Date), "time::Date");
         assert_eq!(
             to_rust_type(&TypedFieldType::Instant),
             "std::time::SystemTime"
@@ -308,5 +981,25 @@ mod tests {
             to_rust_type(&TypedFieldType::Array(Box::new(TypedFieldType::U8))),
             "Vec<u8>"
         );
+        assert_eq!(
+            to_rust_type(&TypedFieldType::Map(
+                Box::new(TypedFieldType::String),
+                Box::new(TypedFieldType::Array(Box::new(TypedFieldType::String)))
+            )),
+            "std::collections::BTreeMap<String, Vec<String>>"
+        );
+        assert_eq!(
+            to_rust_type(&TypedFieldType::Optional(Box::new(
+                TypedFieldType::Indirect(Box::new(TypedFieldType::OtherStruct("Foo".to_string())))
+            ))),
+            "Option<Box<Foo>>"
+        );
+        assert_eq!(
+            to_rust_type(&TypedFieldType::Imported {
+                module: "lib-events".to_string(),
+                name: "Foo".to_string()
+            }),
+            "::lib_events::Foo"
+        );
     }
 }
@@ -1,9 +1,292 @@
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote, TokenStreamExt};
 
-use crate::type_checking::{TypedEnum, TypedMetadata, TypedRpc, TypedRpcCall, TypedStruct};
+use crate::naming::{constant_case, snake_case};
+use crate::type_checking::{
+    TypedConstant, TypedDefinitionOptions, TypedDerive, TypedEnum, TypedEnumTagging, TypedField,
+    TypedFieldType, TypedMetadata, TypedRpc, TypedRpcCall, TypedStruct, TypedValue,
+};
 
-use super::to_rust_type;
+use super::{contains_definitions, to_rust_type};
+
+fn generate_deprecation(reason: Option<&str>) -> TokenStream {
+    reason.map_or_else(|| quote!(), |reason| quote!(#[deprecated(note = #reason)]))
+}
+
+/// Generates the `///` comments of a definition as `#[doc]` attributes, keeping the space that
+/// follows the slashes as rustdoc expects
+pub(crate) fn generate_docs(docs: &[String]) -> TokenStream {
+    let mut result = quote!();
+
+    for line in docs {
+        let line = if line.is_empty() {
+            String::new()
+        } else {
+            format!(" {line}")
+        };
+        result.append_all(quote!(#[doc = #line]));
+    }
+
+    result
+}
+
+/// Generates the `derive` and the other attributes of a struct or an enum set by its options.
+/// `Default` is left out when `manual_default` is set, as it is implemented by `generate_default_impl`.
+fn generate_type_attributes(options: &TypedDefinitionOptions, manual_default: bool) -> TokenStream {
+    let derives = options
+        .derives
+        .iter()
+        .filter(|derive| !(manual_default && **derive == TypedDerive::Default))
+        .map(|derive| format_ident!("{}", derive.name()));
+    let mut result = quote!(#[derive(Serialize, Deserialize, Debug, Clone #(, #derives)*)]);
+
+    if let Some(rule) = options.rename_all {
+        let rule = rule.name();
+        result.append_all(quote!(#[serde(rename_all = #rule)]));
+    }
+    if options.non_exhaustive {
+        result.append_all(quote!(#[non_exhaustive]));
+    }
+
+    result
+}
+
+/// Implements `Default` for a struct with fields that have default values, which the derived
+/// implementation would not use
+fn generate_default_impl(s: &TypedStruct, deprecated: bool) -> TokenStream {
+    let struct_name = format_ident!("{}", s.name());
+    let fields = s.fields().iter().map(|f| {
+        let name = format_ident!("{}", f.name());
+        if f.default().is_some() {
+            let function = format_ident!("default_{}", f.name());
+            quote!(#name: Self::#function())
+        } else {
+            quote!(#name: Default::default())
+        }
+    });
+    let allow = if deprecated {
+        quote!(#[allow(deprecated)])
+    } else {
+        quote!()
+    };
+
+    quote!(
+        #allow
+        impl Default for #struct_name {
+            fn default() -> Self {
+                Self {
+                    #(#fields,)*
+                }
+            }
+        }
+    )
+}
+
+fn generate_value(value: &TypedValue) -> TokenStream {
+    match value {
+        TypedValue::Integer(value) => {
+            let value = proc_macro2::Literal::i128_unsuffixed(*value);
+            quote!(#value)
+        }
+        TypedValue::Float(value) => {
+            let value = proc_macro2::Literal::f64_unsuffixed(*value);
+            quote!(#value)
+        }
+        TypedValue::Boolean(value) => quote!(#value),
+        TypedValue::String(value) => quote!(#value.to_string()),
+    }
+}
+
+fn generate_default_value(field: &TypedField) -> Option<TokenStream> {
+    let value = generate_value(field.default()?);
+
+    Some(match field.type_name() {
+        TypedFieldType::Optional(_) => quote!(Some(#value)),
+        _ => value,
+    })
+}
+
+fn generate_bound<T>(bound: Option<T>, generate: impl Fn(T) -> TokenStream) -> TokenStream {
+    bound.map_or_else(
+        || quote!(None),
+        |bound| {
+            let bound = generate(bound);
+            quote!(Some(#bound))
+        },
+    )
+}
+
+/// Generates the checks of the constraints of a field, and the validation of the structs and enums
+/// it contains, for the value that `value` refers to. Nothing is generated if there is nothing to check.
+fn generate_field_validation(field: &TypedField, value: &TokenStream) -> TokenStream {
+    let constraints = field.constraints();
+    let mut checks = quote!();
+
+    let mut value_type = field.type_name();
+    let mut optional_depth = 0;
+    while let TypedFieldType::Optional(inner) = value_type {
+        value_type = inner;
+        optional_depth += 1;
+    }
+
+    if let Some(pattern) = &constraints.pattern {
+        checks.append_all(quote!(
+            static PATTERN: rpc_support::validation::Pattern = rpc_support::validation::Pattern::new(#pattern);
+            rpc_support::validation::check_pattern(value, &PATTERN, &path, errors);
+        ));
+    }
+    if constraints.min_length.is_some() || constraints.max_length.is_some() {
+        let length = match value_type {
+            TypedFieldType::String => quote!(value.chars().count()),
+            _ => quote!(value.len()),
+        };
+        let generate_length = |length| {
+            let length = proc_macro2::Literal::u64_unsuffixed(length);
+            quote!(#length)
+        };
+        let min = generate_bound(constraints.min_length, generate_length);
+        let max = generate_bound(constraints.max_length, generate_length);
+        checks.append_all(quote!(
+            rpc_support::validation::check_length(#length, #min, #max, &path, errors);
+        ));
+    }
+    if constraints.min.is_some() || constraints.max.is_some() {
+        let min = generate_bound(constraints.min.as_ref(), generate_value);
+        let max = generate_bound(constraints.max.as_ref(), generate_value);
+        checks.append_all(quote!(
+            rpc_support::validation::check_range(*value, #min, #max, &path, errors);
+        ));
+    }
+
+    // The constraints apply to the value of an optional field when it is present
+    if !checks.is_empty() {
+        for _ in 0..optional_depth {
+            checks = quote!(if let Some(value) = value { #checks });
+        }
+    }
+
+    if contains_definitions(field.type_name()) {
+        checks.append_all(quote!(
+            rpc_support::validation::Validate::validate_at(value, &path, errors);
+        ));
+    }
+
+    if checks.is_empty() {
+        return quote!();
+    }
+
+    let name = field.serialized_name();
+    quote!({
+        let path = rpc_support::validation::Path::Field(path, #name);
+        let value = #value;
+        #checks
+    })
+}
+
+/// Implements `rpc_support::validation::Validate` for a generated type, with `checks` as the body of
+/// `validate_at`
+fn generate_validate_impl(owner: &str, checks: &TokenStream, deprecated: bool) -> TokenStream {
+    let owner = format_ident!("{}", owner);
+    let (path, errors) = if checks.is_empty() {
+        (quote!(_path), quote!(_errors))
+    } else {
+        (quote!(path), quote!(errors))
+    };
+    let allow = if deprecated {
+        quote!(#[allow(deprecated)])
+    } else {
+        quote!()
+    };
+
+    quote!(
+        #allow
+        impl rpc_support::validation::Validate for #owner {
+            fn validate_at(
+                &self,
+                #path: &rpc_support::validation::Path,
+                #errors: &mut Vec<rpc_support::validation::ValidationError>,
+            ) {
+                #checks
+            }
+        }
+    )
+}
+
+/// The module of `rpc_support` used to serialize a type that has no serde implementation in the
+/// format of the messages, also when it is nested in other types.
+fn serializer_module(type_: &TypedFieldType) -> Option<&'static str> {
+    match type_ {
+        TypedFieldType::Instant => Some("rpc_support::system_time_serializer"),
+        TypedFieldType::Duration => Some("rpc_support::duration_serializer"),
+        TypedFieldType::Date => Some("rpc_support::date_serializer"),
+        TypedFieldType::Decimal => Some("rpc_support::decimal_serializer"),
+        TypedFieldType::Optional(inner)
+        | TypedFieldType::Array(inner)
+        | TypedFieldType::Map(_, inner) => serializer_module(inner),
+        _ => None,
+    }
+}
+
+/// Generates the fields of a struct or of an enum variant, together with the functions providing
+/// their default values. `#[serde(default = ...)]` needs a path to a function, so these are generated
+/// as associated functions of `owner`, prefixed with the variant name to keep them unique in enums.
+fn generate_fields(
+    owner: &str,
+    variant: Option<&str>,
+    fields: &[TypedField],
+    visibility: &TokenStream,
+) -> (TokenStream, TokenStream) {
+    let mut render_fields = quote! {};
+    let mut default_functions = quote! {};
+
+    for f in fields {
+        let name = format_ident!("{}", f.name());
+        let ty: syn::Type = syn::parse_str(&to_rust_type(f.type_name())).unwrap();
+
+        let mut attributes = generate_docs(f.docs());
+        attributes.append_all(generate_deprecation(f.deprecated()));
+        if let Some(module) = serializer_module(f.type_name()) {
+            attributes.append_all(quote!(#[serde(with = #module)]));
+
+            // With a custom serializer, serde no longer treats missing optional fields as `None`
+            if matches!(f.type_name(), TypedFieldType::Optional(_)) && f.default().is_none() {
+                attributes.append_all(quote!(#[serde(default)]));
+            }
+        }
+        if let Some(value) = generate_default_value(f) {
+            let function_name = match variant {
+                Some(variant) => format!("default_{}_{}", snake_case(variant), f.name()),
+                None => format!("default_{}", f.name()),
+            };
+            let function = format_ident!("{}", function_name);
+            let path = format!("{owner}::{function_name}");
+
+            attributes.append_all(quote!(#[serde(default = #path)]));
+            default_functions.append_all(quote!(
+                fn #function() -> #ty {
+                    #value
+                }
+            ));
+        }
+
+        render_fields.append_all(quote!(#attributes #visibility #name: #ty,));
+    }
+
+    (render_fields, default_functions)
+}
+
+fn generate_default_functions(owner: &str, functions: &TokenStream) -> TokenStream {
+    if functions.is_empty() {
+        return quote!();
+    }
+
+    let owner = format_ident!("{}", owner);
+    quote!(
+        impl #owner {
+            #functions
+        }
+    )
+}
 
 pub(crate) fn generate_header() -> TokenStream {
     quote! {
@@ -14,22 +297,42 @@ pub(crate) fn generate_header() -> TokenStream {
     }
 }
 
+pub(crate) fn generate_constants(constants: &[TypedConstant]) -> TokenStream {
+    let mut result = quote! {};
+
+    for constant in constants {
+        let name = format_ident!("{}", constant_case(constant.name()));
+        let docs = generate_docs(constant.docs());
+        let (ty, value) = match constant.value() {
+            TypedValue::String(value) => (quote!(&str), quote!(#value)),
+            value => {
+                let ty: syn::Type = syn::parse_str(&to_rust_type(constant.type_name())).unwrap();
+                (quote!(#ty), generate_value(value))
+            }
+        };
+
+        result.append_all(quote!(
+            #docs
+            pub const #name: #ty = #value;
+        ));
+    }
+
+    result
+}
+
 pub(crate) fn generate_metadata(meta: &TypedMetadata) -> TokenStream {
     let mut result = quote!(
         #[derive(Serialize, Deserialize, Debug, Clone)]
     );
 
-    let mut meta_fields = quote! {};
-    for f in meta.fields() {
-        let name = format_ident!("{}", f.name());
-        let ty: syn::Type = syn::parse_str(&to_rust_type(f.type_name())).unwrap();
-        meta_fields.append_all(quote!(pub #name: #ty,));
-    }
+    let (meta_fields, default_functions) =
+        generate_fields("Metadata", None, meta.fields(), &quote!(pub));
     result.append_all(quote!(
         pub struct Metadata {
             #meta_fields
         }
     ));
+    result.append_all(generate_default_functions("Metadata", &default_functions));
 
     result
 }
@@ -39,18 +342,32 @@ pub(crate) fn generate_structs(structs: &Vec<TypedStruct>) -> TokenStream {
 
     for s in structs {
         let struct_name = format_ident!("{}", s.name());
-        let mut render_fields = quote! {};
-        for f in s.fields() {
-            let name = format_ident!("{}", f.name());
-            let ty: syn::Type = syn::parse_str(&to_rust_type(f.type_name())).unwrap();
-            render_fields.append_all(quote!(pub #name: #ty,));
-        }
+        let docs = generate_docs(s.docs());
+        let (render_fields, default_functions) =
+            generate_fields(s.name(), None, s.fields(), &quote!(pub));
+        let manual_default = s.options().derives.contains(&TypedDerive::Default)
+            && s.fields().iter().any(|f| f.default().is_some());
+        let attributes = generate_type_attributes(s.options(), manual_default);
         result.append_all(quote!(
-            #[derive(Serialize, Deserialize, Debug, Clone)]
+            #docs
+            #attributes
             pub struct #struct_name {
                 #render_fields
             }
         ));
+        result.append_all(generate_default_functions(s.name(), &default_functions));
+
+        let deprecated = s.fields().iter().any(|f| f.deprecated().is_some());
+        if manual_default {
+            result.append_all(generate_default_impl(s, deprecated));
+        }
+
+        let mut checks = quote!();
+        for field in s.fields() {
+            let name = format_ident!("{}", field.name());
+            checks.append_all(generate_field_validation(field, &quote!(&self.#name)));
+        }
+        result.append_all(generate_validate_impl(s.name(), &checks, deprecated));
     }
 
     result
@@ -65,52 +382,155 @@ pub(crate) fn generate_enums(enums: &Vec<TypedEnum>) -> TokenStream {
     for e in enums {
         let enum_name = format_ident!("{}", e.name());
         let mut render_variants = quote! {};
+        let mut default_functions = quote! {};
+        let mut checks = quote! {};
+        let mut any_checks = false;
         for v in e.variants() {
             let variant_name = format_ident!("{}", v.name());
-            let mut render_fields = quote! {};
-            for f in v.fields() {
-                let name = format_ident!("{}", f.name());
-                let ty: syn::Type = syn::parse_str(&to_rust_type(f.type_name())).unwrap();
-                render_fields.append_all(quote!(#name: #ty,));
+            let docs = generate_docs(v.docs());
+            let deprecation = generate_deprecation(v.deprecated());
+            if v.is_unit() {
+                render_variants.append_all(quote!(
+                    #docs
+                    #deprecation
+                    #variant_name,
+                ));
+            } else {
+                let (render_fields, variant_default_functions) =
+                    generate_fields(e.name(), Some(v.name()), v.fields(), &quote!());
+                render_variants.append_all(quote!(
+                    #docs
+                    #deprecation
+                    #variant_name {
+                        #render_fields
+                    },
+                ));
+                default_functions.append_all(variant_default_functions);
             }
-            render_variants.append_all(quote!(
-                #variant_name {
-                    #render_fields
-                },
-            ));
+
+            // The fields are bound to names of their own, as they could shadow the arguments
+            let mut bindings = vec![];
+            let mut variant_checks = quote!();
+            for (index, field) in v.fields().iter().enumerate() {
+                let binding = format_ident!("field_{}", index);
+                let field_checks = generate_field_validation(field, &quote!(#binding));
+                if !field_checks.is_empty() {
+                    let name = format_ident!("{}", field.name());
+                    variant_checks.append_all(field_checks);
+                    bindings.push(quote!(#name: #binding));
+                }
+            }
+            any_checks |= !bindings.is_empty();
+            checks.append_all(
+                quote!(Self::#variant_name { #(#bindings,)* .. } => { #variant_checks }),
+            );
         }
-        result.append_all(quote!(
-            #[derive(Serialize, Deserialize, Debug, Clone)]
+        let checks = if any_checks {
+            quote!(match self { #checks })
+        } else {
+            quote!()
+        };
+        let docs = generate_docs(e.docs());
+        let tagging = match e.tagging() {
+            TypedEnumTagging::External => quote!(),
+            TypedEnumTagging::Internal { tag } => quote!(#[serde(tag = #tag)]),
+            TypedEnumTagging::Adjacent { tag, content } => {
+                quote!(#[serde(tag = #tag, content = #content)])
+            }
+        };
+        let attributes = generate_type_attributes(e.options(), false);
+        let definition = quote!(
+            #docs
+            #attributes
+            #tagging
             pub enum #enum_name {
                 #render_variants
             }
-        ));
+        );
+        let default_functions = generate_default_functions(e.name(), &default_functions);
+        let deprecated_fields = e
+            .variants()
+            .iter()
+            .any(|v| v.fields().iter().any(|f| f.deprecated().is_some()));
+        let validate_impl = generate_validate_impl(e.name(), &checks, deprecated_fields);
+
+        // The derived implementations refer to deprecated variants, which would be reported as a use
+        // of them, so the enum is defined in a module that allows it. Users of the variants are still warned.
+        if e.variants().iter().any(|v| v.deprecated().is_some()) {
+            let module = format_ident!("{}", snake_case(e.name()));
+            result.append_all(quote!(
+                pub use #module::#enum_name;
+
+                #[allow(deprecated)]
+                mod #module {
+                    use super::*;
+
+                    #definition
+                    #default_functions
+                    #validate_impl
+                }
+            ));
+        } else {
+            result.append_all(definition);
+            result.append_all(default_functions);
+            result.append_all(validate_impl);
+        }
     }
 
     result
 }
 
+// A stream of requests is sent as it is, but the server receives an error for every request that
+// cannot be read or is invalid
+pub(crate) fn generate_request_param(
+    request: &TypedFieldType,
+    request_stream: bool,
+    client: bool,
+) -> TokenStream {
+    let request: syn::Type = syn::parse_str(&to_rust_type(request)).unwrap();
+
+    match (request_stream, client) {
+        (false, _) => quote!(request: #request),
+        (true, true) => {
+            quote!(requests: std::pin::Pin<Box<dyn Stream<Item = #request> + Unpin + Send>>)
+        }
+        (true, false) => quote!(
+            requests: std::pin::Pin<Box<dyn Stream<Item = Result<#request, RpcError>> + Unpin + Send>>
+        ),
+    }
+}
+
 fn generate_rpc_methods(call: &TypedRpcCall, client: bool) -> TokenStream {
-    let client_param = if client {
-        quote!()
+    // Servers are called concurrently, so only clients are borrowed mutably
+    let (receiver, client_param) = if client {
+        (quote!(&mut self), quote!())
     } else {
-        quote!(client: std::sync::Weak<tokio::sync::Mutex<dyn rpc_support::Client>>)
+        (
+            quote!(&self),
+            quote!(client: std::sync::Weak<tokio::sync::Mutex<dyn rpc_support::Client>>),
+        )
     };
+    let docs = generate_docs(call.docs());
+    let deprecation = generate_deprecation(call.deprecated());
 
     match call {
         crate::type_checking::TypedRpcCall::Stream {
             name,
             request,
+            request_stream,
             response,
+            ..
         } => {
             let name = format_ident!("{}", name);
-            let request: syn::Type = syn::parse_str(&to_rust_type(request)).unwrap();
+            let request = generate_request_param(request, *request_stream, client);
             let response: syn::Type = syn::parse_str(&to_rust_type(response)).unwrap();
 
             quote!(
+                #docs
+                #deprecation
                 async fn #name(
-                    &mut self,
-                    request: #request,
+                    #receiver,
+                    #request,
                     metadata: Metadata,
                     #client_param
                 ) -> Result<
@@ -122,16 +542,20 @@ fn generate_rpc_methods(call: &TypedRpcCall, client: bool) -> TokenStream {
         crate::type_checking::TypedRpcCall::Unary {
             name,
             request,
+            request_stream,
             response,
+            ..
         } => {
             let name = format_ident!("{}", name);
-            let request: syn::Type = syn::parse_str(&to_rust_type(request)).unwrap();
+            let request = generate_request_param(request, *request_stream, client);
             let response: syn::Type = syn::parse_str(&to_rust_type(response)).unwrap();
 
             quote!(
+                #docs
+                #deprecation
                 async fn #name(
-                    &mut self,
-                    request: #request,
+                    #receiver,
+                    #request,
                     metadata: Metadata,
                     #client_param
                 ) -> Result<#response, RpcError>;
@@ -153,6 +577,21 @@ pub(crate) fn generate_rpc_trait(rpc: &TypedRpc) -> TokenStream {
         client_rpc_methods.append_all(generate_rpc_methods(r, true));
     }
 
+    // Only generated when needed, so that servers without `@requires` do not have to implement it
+    if rpc
+        .calls()
+        .iter()
+        .any(|call| !call.options().requires.is_empty())
+    {
+        server_rpc_methods.append_all(quote! {
+            /// Called before the methods with `@requires`, which are only called if it returns `Ok`
+            ///
+            /// # Errors
+            /// Should return `RpcError::Unauthorized` if the caller lacks one of the `scopes`
+            async fn authorize(&self, method_name: &str, scopes: &[&str], metadata: &Metadata) -> Result<(), RpcError>;
+        });
+    }
+
     quote!(
         #[async_trait::async_trait]
         pub trait RpcServer {
@@ -170,7 +609,9 @@ pub(crate) fn generate_rpc_trait(rpc: &TypedRpc) -> TokenStream {
 mod tests {
     use super::*;
     use crate::type_checking::{
-        TypedEnum, TypedEnumVariant, TypedField, TypedFieldType, TypedMetadata, TypedStruct,
+        TypedConstant, TypedConstraints, TypedDefinitionOptions, TypedDerive, TypedEnum,
+        TypedEnumTagging, TypedEnumVariant, TypedField, TypedFieldType, TypedMetadata,
+        TypedRenameRule, TypedStruct, TypedValue,
     };
 
     #[test]
@@ -186,11 +627,21 @@ mod tests {
             fields: vec![
                 TypedField {
                     name: "foo".to_string(),
+                    serialized_name: "foo".to_string(),
                     type_id: TypedFieldType::U8,
+                    default: None,
+                    deprecated: None,
+                    constraints: TypedConstraints::default(),
+                    docs: vec![],
                 },
                 TypedField {
                     name: "bar".to_string(),
+                    serialized_name: "bar".to_string(),
                     type_id: TypedFieldType::U64,
+                    default: None,
+                    deprecated: None,
+                    constraints: TypedConstraints::default(),
+                    docs: vec![],
                 },
             ],
         });
@@ -208,26 +659,64 @@ mod tests {
                 fields: vec![
                     TypedField {
                         name: "foo".to_string(),
+                        serialized_name: "foo".to_string(),
                         type_id: TypedFieldType::U8,
+                        default: None,
+                        deprecated: None,
+                        constraints: TypedConstraints::default(),
+                        docs: vec!["The first field".to_string()],
                     },
                     TypedField {
                         name: "bar".to_string(),
+                        serialized_name: "bar".to_string(),
                         type_id: TypedFieldType::U64,
+                        default: None,
+                        deprecated: None,
+                        constraints: TypedConstraints::default(),
+                        docs: vec![],
                     },
                 ],
+                docs: vec![
+                    "A struct".to_string(),
+                    String::new(),
+                    "with two paragraphs".to_string(),
+                ],
+                options: TypedDefinitionOptions::default(),
             },
             TypedStruct {
                 name: "Bar".to_string(),
                 fields: vec![
                     TypedField {
                         name: "foo".to_string(),
+                        serialized_name: "foo".to_string(),
                         type_id: TypedFieldType::U8,
+                        default: None,
+                        deprecated: None,
+                        constraints: TypedConstraints::default(),
+                        docs: vec![],
                     },
                     TypedField {
-                        name: "bar".to_string(),
+                        name: "bar_count".to_string(),
+                        serialized_name: "barCount".to_string(),
                         type_id: TypedFieldType::U64,
+                        default: Some(TypedValue::Integer(1)),
+                        deprecated: None,
+                        constraints: TypedConstraints::default(),
+                        docs: vec![],
                     },
                 ],
+                docs: vec![],
+                options: TypedDefinitionOptions {
+                    derives: [
+                        TypedDerive::PartialEq,
+                        TypedDerive::Eq,
+                        TypedDerive::Hash,
+                        TypedDerive::Default,
+                    ]
+                    .into(),
+                    rename_all: Some(TypedRenameRule::CamelCase),
+                    non_exhaustive: true,
+                },
             },
         ]);
 
@@ -236,6 +725,34 @@ mod tests {
         ));
     }
 
+    #[test]
+    pub fn generate_constants_test() {
+        let constants = generate_constants(&[
+            TypedConstant {
+                name: "default_port".to_string(),
+                type_id: TypedFieldType::U16,
+                value: TypedValue::Integer(7654),
+                docs: vec!["The port the service listens on".to_string()],
+            },
+            TypedConstant {
+                name: "VERSION".to_string(),
+                type_id: TypedFieldType::String,
+                value: TypedValue::String("1.0".to_string()),
+                docs: vec![],
+            },
+            TypedConstant {
+                name: "ratio".to_string(),
+                type_id: TypedFieldType::F32,
+                value: TypedValue::Float(2.0),
+                docs: vec![],
+            },
+        ]);
+
+        insta::assert_snapshot!(prettyplease::unparse(
+            &syn::parse_file(&constants.to_string()).unwrap()
+        ));
+    }
+
     #[test]
     pub fn generate_enums_test() {
         let enums = generate_enums(&vec![TypedEnum {
@@ -243,25 +760,66 @@ mod tests {
             variants: vec![
                 TypedEnumVariant {
                     name: "A".to_string(),
+                    serialized_name: "a".to_string(),
                     fields: vec![TypedField {
                         name: "field_a".to_string(),
+                        serialized_name: "field_a".to_string(),
                         type_id: TypedFieldType::String,
+                        default: None,
+                        deprecated: None,
+                        constraints: TypedConstraints::default(),
+                        docs: vec![],
                     }],
+                    deprecated: None,
+                    docs: vec!["The first variant".to_string()],
+                    unit: false,
                 },
                 TypedEnumVariant {
                     name: "B".to_string(),
+                    serialized_name: "b".to_string(),
                     fields: vec![
                         TypedField {
                             name: "a".to_string(),
+                            serialized_name: "a".to_string(),
                             type_id: TypedFieldType::S16,
+                            default: None,
+                            deprecated: None,
+                            constraints: TypedConstraints::default(),
+                            docs: vec![],
                         },
                         TypedField {
                             name: "b".to_string(),
+                            serialized_name: "b".to_string(),
                             type_id: TypedFieldType::String,
+                            default: None,
+                            deprecated: None,
+                            constraints: TypedConstraints::default(),
+                            docs: vec![],
                         },
                     ],
+                    deprecated: None,
+                    docs: vec![],
+                    unit: false,
+                },
+                TypedEnumVariant {
+                    name: "C".to_string(),
+                    serialized_name: "c".to_string(),
+                    fields: vec![],
+                    deprecated: None,
+                    docs: vec![],
+                    unit: true,
                 },
             ],
+            docs: vec!["An enum".to_string()],
+            tagging: TypedEnumTagging::Adjacent {
+                tag: "type".to_string(),
+                content: "data".to_string(),
+            },
+            options: TypedDefinitionOptions {
+                derives: [TypedDerive::PartialEq].into(),
+                rename_all: Some(TypedRenameRule::SnakeCase),
+                non_exhaustive: false,
+            },
         }]);
 
         insta::assert_snapshot!(prettyplease::unparse(
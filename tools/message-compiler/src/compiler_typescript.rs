@@ -0,0 +1,467 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::naming::{camel_case, constant_case};
+use crate::type_checking::{
+    TypedConstant, TypedEnum, TypedEnumTagging, TypedField, TypedFieldType, TypedFile,
+    TypedRpcCall, TypedValue,
+};
+
+mod runtime;
+
+fn to_typescript_type(type_: &TypedFieldType) -> String {
+    match type_ {
+        TypedFieldType::U8
+        | TypedFieldType::U16
+        | TypedFieldType::U32
+        | TypedFieldType::U64
+        | TypedFieldType::S8
+        | TypedFieldType::S16
+        | TypedFieldType::S32
+        | TypedFieldType::S64
+        | TypedFieldType::F32
+        | TypedFieldType::F64 => "number".to_string(),
+        TypedFieldType::Bool => "boolean".to_string(),
+        TypedFieldType::Instant => "Instant".to_string(),
+        TypedFieldType::Duration => "Duration".to_string(),
+        // Dates are left as the `YYYY-MM-DD` strings they are sent as, as `Date` always has a time
+        TypedFieldType::Date => "string".to_string(),
+        // Decimals are left as the strings they are sent as, as a `number` would round them
+        TypedFieldType::Decimal | TypedFieldType::Guid | TypedFieldType::String => {
+            "string".to_string()
+        }
+        TypedFieldType::Void => "null".to_string(),
+        TypedFieldType::Binary => "number[]".to_string(),
+        TypedFieldType::OtherStruct(name)
+        | TypedFieldType::Enum(name)
+        | TypedFieldType::Imported { name, .. } => name.clone(),
+        TypedFieldType::Optional(type_) => format!("{} | null", to_typescript_type(type_)),
+        TypedFieldType::Array(type_) => match to_typescript_type(type_) {
+            inner if inner.contains(' ') => format!("({inner})[]"),
+            inner => format!("{inner}[]"),
+        },
+        // Object keys are always strings in JSON, even when they are numbers in the definition
+        TypedFieldType::Map(_, value) => format!("Record<string, {}>", to_typescript_type(value)),
+        TypedFieldType::Indirect(type_) => to_typescript_type(type_),
+    }
+}
+
+fn collect_imports<'a>(type_: &'a TypedFieldType, imports: &mut BTreeMap<&'a str, Vec<&'a str>>) {
+    match type_ {
+        TypedFieldType::Imported { module, name } => {
+            let names = imports.entry(module).or_default();
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        TypedFieldType::Optional(type_)
+        | TypedFieldType::Array(type_)
+        | TypedFieldType::Map(_, type_)
+        | TypedFieldType::Indirect(type_) => collect_imports(type_, imports),
+        _ => {}
+    }
+}
+
+fn generate_imports(file: &TypedFile) -> String {
+    let mut imports = BTreeMap::new();
+
+    let fields = file
+        .structs
+        .iter()
+        .flat_map(|s| s.fields())
+        .chain(
+            file.enums
+                .iter()
+                .flat_map(|e| e.variants())
+                .flat_map(|v| v.fields()),
+        )
+        .chain(file.meta.fields());
+    for field in fields {
+        collect_imports(field.type_name(), &mut imports);
+    }
+
+    for (_, call) in file.methods() {
+        let (TypedRpcCall::Stream {
+            request, response, ..
+        }
+        | TypedRpcCall::Unary {
+            request, response, ..
+        }) = call;
+
+        collect_imports(request, &mut imports);
+        collect_imports(response, &mut imports);
+    }
+
+    let mut result = String::new();
+    for (module, names) in imports {
+        writeln!(
+            result,
+            "import type {{ {} }} from \"./{module}\";",
+            names.join(", ")
+        )
+        .unwrap();
+    }
+    if !result.is_empty() {
+        result.push('\n');
+    }
+
+    result
+}
+
+/// A doc comment with the `///` comments of a definition followed by its `@deprecated` tag, on a
+/// single line when there is only one line. Every line but the first is indented with `indent`.
+fn doc_comment(docs: &[String], deprecated: Option<&str>, indent: &str) -> Option<String> {
+    let mut lines = docs.to_vec();
+    if let Some(reason) = deprecated {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(format!("@deprecated {reason}"));
+    }
+
+    match lines.as_slice() {
+        [] => None,
+        [line] => Some(format!("/** {line} */")),
+        lines => {
+            let mut result = "/**\n".to_string();
+            for line in lines {
+                if line.is_empty() {
+                    writeln!(result, "{indent} *").unwrap();
+                } else {
+                    writeln!(result, "{indent} * {line}").unwrap();
+                }
+            }
+            write!(result, "{indent} */").unwrap();
+
+            Some(result)
+        }
+    }
+}
+
+fn generate_fields(fields: &[TypedField], indent: &str) -> String {
+    let mut result = String::new();
+
+    for field in fields {
+        if let Some(comment) = doc_comment(field.docs(), field.deprecated(), indent) {
+            writeln!(result, "{indent}{comment}").unwrap();
+        }
+        writeln!(
+            result,
+            "{indent}{}: {};",
+            property_name(field.serialized_name()),
+            to_typescript_type(field.type_name())
+        )
+        .unwrap();
+    }
+
+    result
+}
+
+fn generate_interface(name: &str, fields: &[TypedField], docs: &[String]) -> String {
+    let comment = doc_comment(docs, None, "").map_or_else(String::new, |comment| comment + "\n");
+
+    format!(
+        "{comment}export interface {name} {{\n{}}}\n",
+        generate_fields(fields, "    ")
+    )
+}
+
+// The names of tags are not checked like the other names, so they are quoted when they have to be
+fn property_name(name: &str) -> String {
+    let mut characters = name.chars();
+    let identifier = characters
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && characters.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+
+    if identifier {
+        name.to_string()
+    } else {
+        serde_json::to_string(name).unwrap()
+    }
+}
+
+fn generate_constant(constant: &TypedConstant) -> String {
+    let comment =
+        doc_comment(constant.docs(), None, "").map_or_else(String::new, |comment| comment + "\n");
+    let value = match constant.value() {
+        TypedValue::Integer(value) => value.to_string(),
+        TypedValue::Float(value) => value.to_string(),
+        TypedValue::Boolean(value) => value.to_string(),
+        TypedValue::String(value) => serde_json::to_string(value).unwrap(),
+    };
+
+    format!(
+        "{comment}export const {}: {} = {value};\n",
+        constant_case(constant.name()),
+        to_typescript_type(constant.type_name())
+    )
+}
+
+fn generate_enum(enum_: &TypedEnum) -> String {
+    let comment =
+        doc_comment(enum_.docs(), None, "").map_or_else(String::new, |comment| comment + "\n");
+    let mut result = format!("{comment}export type {} =", enum_.name());
+
+    if enum_.variants().is_empty() {
+        result.push_str(" never;\n");
+        return result;
+    }
+
+    for variant in enum_.variants() {
+        let name = variant.serialized_name();
+        let fields: Vec<_> = variant
+            .fields()
+            .iter()
+            .map(|field| {
+                format!(
+                    "{}: {}",
+                    property_name(field.serialized_name()),
+                    to_typescript_type(field.type_name())
+                )
+            })
+            .collect();
+        let contents = if fields.is_empty() {
+            "Record<string, never>".to_string()
+        } else {
+            format!("{{ {} }}", fields.join("; "))
+        };
+
+        // Matches the representation serde uses for the tagging of the enum
+        let type_ = match enum_.tagging() {
+            TypedEnumTagging::External if variant.is_unit() => format!("\"{name}\""),
+            TypedEnumTagging::External => format!("{{ {name}: {contents} }}"),
+            TypedEnumTagging::Internal { tag } => {
+                let tag = format!("{}: \"{name}\"", property_name(tag));
+                format!("{{ {} }}", [vec![tag], fields].concat().join("; "))
+            }
+            TypedEnumTagging::Adjacent { tag, .. } if variant.is_unit() => {
+                format!("{{ {}: \"{name}\" }}", property_name(tag))
+            }
+            TypedEnumTagging::Adjacent { tag, content } => format!(
+                "{{ {}: \"{name}\"; {}: {contents} }}",
+                property_name(tag),
+                property_name(content)
+            ),
+        };
+
+        let comment = doc_comment(variant.docs(), variant.deprecated(), "      ")
+            .map_or_else(String::new, |comment| comment + " ");
+        write!(result, "\n    | {comment}{type_}").unwrap();
+    }
+    result.push_str(";\n");
+
+    result
+}
+
+// `method_name` is the name the method is called by on the wire
+fn generate_client_method(method_name: &str, call: &TypedRpcCall) -> String {
+    let (name, request, response, stream) = match call {
+        TypedRpcCall::Stream {
+            name,
+            request,
+            response,
+            ..
+        } => (name, request, response, true),
+        TypedRpcCall::Unary {
+            name,
+            request,
+            response,
+            ..
+        } => (name, request, response, false),
+    };
+
+    let (parameters, request_argument) = if call.request_stream() {
+        (
+            format!(
+                "requests: AsyncIterable<{}>, metadata: Metadata",
+                to_typescript_type(request)
+            ),
+            "requests",
+        )
+    } else if request == &TypedFieldType::Void {
+        ("metadata: Metadata".to_string(), "null")
+    } else {
+        (
+            format!(
+                "request: {}, metadata: Metadata",
+                to_typescript_type(request)
+            ),
+            "request",
+        )
+    };
+    let response = to_typescript_type(response);
+    let comment = doc_comment(call.docs(), call.deprecated(), "    ")
+        .map_or_else(String::new, |comment| format!("    {comment}\n"));
+
+    let method = if stream {
+        let raw_method = if call.request_stream() {
+            "bidirectionalStream"
+        } else {
+            "stream"
+        };
+        format!(
+            "    {}({parameters}): AsyncGenerator<{response}, void, undefined> {{\n        return this.raw.{raw_method}(\"{method_name}\", {request_argument}, metadata) as AsyncGenerator<{response}, void, undefined>;\n    }}\n",
+            camel_case(name)
+        )
+    } else {
+        let raw_method = if call.request_stream() {
+            "clientStream"
+        } else {
+            "call"
+        };
+        format!(
+            "    {}({parameters}): Promise<{response}> {{\n        return this.raw.{raw_method}(\"{method_name}\", {request_argument}, metadata) as Promise<{response}>;\n    }}\n",
+            camel_case(name)
+        )
+    };
+
+    comment + &method
+}
+
+fn generate_client(name: &str, docs: &[String], methods: &[(String, &TypedRpcCall)]) -> String {
+    let methods: Vec<_> = methods
+        .iter()
+        .map(|(method_name, call)| generate_client_method(method_name, call))
+        .collect();
+    let comment = doc_comment(docs, None, "").map_or_else(String::new, |comment| comment + "\n");
+
+    format!(
+        "{comment}export class {name} {{\n    constructor(private readonly raw: RawRpcClient) {{}}\n{}}}\n",
+        methods
+            .iter()
+            .map(|method| format!("\n{method}"))
+            .collect::<String>()
+    )
+}
+
+/// Compiles the file into a single TypeScript module. Imported types are imported from a sibling
+/// module named after the Rust module they come from.
+#[must_use]
+pub fn compile(file: &TypedFile) -> String {
+    let mut result = generate_imports(file);
+
+    result.push_str(runtime::RUNTIME);
+    result.push('\n');
+    for constant in &file.constants {
+        result.push_str(&generate_constant(constant));
+        result.push('\n');
+    }
+    result.push_str(&generate_interface("Metadata", file.meta.fields(), &[]));
+
+    for struct_ in &file.structs {
+        result.push('\n');
+        result.push_str(&generate_interface(
+            struct_.name(),
+            struct_.fields(),
+            struct_.docs(),
+        ));
+    }
+
+    for enum_ in &file.enums {
+        result.push('\n');
+        result.push_str(&generate_enum(enum_));
+    }
+
+    // The `rpc` block and services are never used together
+    if file.services.is_empty() {
+        let methods: Vec<_> = file
+            .rpc
+            .calls()
+            .iter()
+            .map(|call| (call.name().to_string(), call))
+            .collect();
+        result.push('\n');
+        result.push_str(&generate_client("Client", &[], &methods));
+    }
+    for service in &file.services {
+        let methods: Vec<_> = service
+            .calls()
+            .iter()
+            .map(|call| (service.method_name(call), call))
+            .collect();
+        result.push('\n');
+        result.push_str(&generate_client(
+            &format!("{}Client", service.name()),
+            service.docs(),
+            &methods,
+        ));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing;
+    use crate::type_checking::TypeChecker;
+
+    #[test]
+    pub fn compile_test() {
+        let events = parsing::grammar::RFileParser::new()
+            .parse("struct FileOnMountPath { path: string, mount_id: string }")
+            .unwrap();
+        let events = TypeChecker::new().check(&events).unwrap();
+        let input = "\
+            import \"events.evd\" { FileOnMountPath } \
+            metadata { correlation_id: guid } \
+            /// The version of the protocol\n const VERSION: string = \"1.0\"; const default_volume: f32 = 50; \
+            struct Track { title: string, id: guid, artists: Artist[], album: Album?, path: FileOnMountPath, tags: map<string, string[]>, paths: map<guid, FileOnMountPath>, length: duration, price: decimal, gain: f32, explicit: bool, released: date } \
+            struct Artist { name: string, @deprecated(\"use name\") nickname: string? } \
+            /// A collection of tracks\n struct Album { title: string, /// The album this one continues\n previous: Album? } \
+            /// Something that happened in the player\n enum Event { /// A track was played\n Played(track: Track, at: instant), /// The player was paused\n @deprecated(\"use Played\") Paused } \
+            @tag(\"type\") enum Command { Play(track: Track), Stop } \
+            @rename_all(\"SCREAMING-KEBAB-CASE\") @tag(\"kind\", \"data-1\") enum Change { Renamed(name: string), Cleared() , Removed } \
+            rpc { @idempotent @timeout(5s) play(Track) -> void; /// Everything that happens from now on\n events(void) -> stream Event; @deprecated(\"use events\") album(guid) -> Album?; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let typed_file = TypeChecker::new()
+            .with_import("events.evd", "events", &events)
+            .check(&ast)
+            .unwrap();
+
+        let compiled = compile(&typed_file);
+
+        insta::assert_snapshot!(compiled.replace(runtime::RUNTIME, "// runtime\n"));
+    }
+
+    #[test]
+    pub fn compile_services_test() {
+        let input = "\
+            struct Track { title: string } \
+            /// Browses the tracks\n service Library { tracks(void) -> stream Track; track(guid) -> Track?; } \
+            service Playback { play(Track) -> void; queue(stream Track) -> u32; chat(stream guid) -> stream Track; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let typed_file = TypeChecker::new().check(&ast).unwrap();
+
+        let compiled = compile(&typed_file);
+
+        insta::assert_snapshot!(compiled.replace(runtime::RUNTIME, "// runtime\n"));
+    }
+
+    #[test]
+    pub fn to_typescript_type_tests() {
+        assert_eq!(to_typescript_type(&TypedFieldType::U64), "number");
+        assert_eq!(to_typescript_type(&TypedFieldType::Guid), "string");
+        assert_eq!(to_typescript_type(&TypedFieldType::Binary), "number[]");
+        assert_eq!(
+            to_typescript_type(&TypedFieldType::Optional(Box::new(
+                TypedFieldType::OtherStruct("Foo".to_string())
+            ))),
+            "Foo | null"
+        );
+        assert_eq!(
+            to_typescript_type(&TypedFieldType::Array(Box::new(TypedFieldType::Optional(
+                Box::new(TypedFieldType::Instant)
+            )))),
+            "(Instant | null)[]"
+        );
+        assert_eq!(
+            to_typescript_type(&TypedFieldType::Map(
+                Box::new(TypedFieldType::U8),
+                Box::new(TypedFieldType::Array(Box::new(TypedFieldType::String)))
+            )),
+            "Record<string, string[]>"
+        );
+    }
+}
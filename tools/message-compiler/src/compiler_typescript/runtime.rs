@@ -0,0 +1,239 @@
+// Support code emitted at the top of every compiled file, so that the generated code has no dependencies
+
+pub(crate) const RUNTIME: &str = r#"export interface Instant {
+    secs_since_epoch: number;
+    nanos_since_epoch: number;
+}
+
+export interface Duration {
+    secs: number;
+    nanos: number;
+}
+
+/** A value of a request that breaks one of the constraints of its field. */
+export interface ValidationError {
+    path: string;
+    message: string;
+}
+
+export class RpcError extends Error {
+    constructor(
+        public readonly kind: string,
+        message: string,
+        public readonly validationErrors: ValidationError[] = [],
+    ) {
+        super(message);
+    }
+
+    static fromJson(json: Record<string, unknown>): RpcError {
+        const kind = Object.keys(json)[0] ?? "Custom";
+        const details = json[kind];
+
+        // Requests that break the constraints of their fields are rejected with an error for every value
+        if (Array.isArray(details)) {
+            const errors = details as ValidationError[];
+
+            return new RpcError(kind, errors.map((error) => `${error.path}: ${error.message}`).join(", "), errors);
+        }
+
+        return new RpcError(kind, typeof details === "string" ? details : "");
+    }
+}
+
+/** Carries the lines of the newline-delimited protocol, without the line terminators. */
+export interface Transport {
+    send(line: string): void;
+    onLine(handler: (line: string) => void): void;
+}
+
+/** Reassembles lines from chunks of a byte stream, e.g. a TCP socket. */
+export class LineTransport implements Transport {
+    private buffer = "";
+    private handler: (line: string) => void = () => undefined;
+
+    constructor(private readonly write: (data: string) => void) {}
+
+    send(line: string): void {
+        this.write(`${line}\n`);
+    }
+
+    onLine(handler: (line: string) => void): void {
+        this.handler = handler;
+    }
+
+    receive(chunk: string): void {
+        this.buffer += chunk;
+
+        let newline = this.buffer.indexOf("\n");
+        while (newline !== -1) {
+            const line = this.buffer.slice(0, newline);
+            this.buffer = this.buffer.slice(newline + 1);
+            this.handler(line);
+            newline = this.buffer.indexOf("\n");
+        }
+    }
+}
+
+/** Sends every line as a separate message, incoming messages can contain any number of lines. */
+export class WebSocketTransport implements Transport {
+    private readonly lines: LineTransport;
+
+    constructor(socket: WebSocket) {
+        this.lines = new LineTransport((data) => socket.send(data));
+        socket.addEventListener("message", (event: MessageEvent) => {
+            const data = String(event.data);
+            this.lines.receive(data.endsWith("\n") ? data : `${data}\n`);
+        });
+    }
+
+    send(line: string): void {
+        this.lines.send(line);
+    }
+
+    onLine(handler: (line: string) => void): void {
+        this.lines.onLine(handler);
+    }
+}
+
+interface ResponseEnvelope {
+    request_id: number;
+    error: Record<string, unknown> | null;
+    stream_end: boolean;
+}
+
+type ResponseHandler = (envelope: ResponseEnvelope, payload: unknown) => void;
+
+type StreamItem = { payload: unknown } | { error: RpcError } | { end: true };
+
+/**
+ * Speaks the newline-delimited JSON protocol of `rpc_support`: every request is an envelope line, a metadata line
+ * and a payload line, every response is an envelope line followed by a payload line unless the envelope has an error.
+ * A stream of requests is sent between a "start" and an "end" frame, with an "item" frame for every request.
+ */
+export class RawRpcClient {
+    private nextRequestId = 0;
+    private envelope: ResponseEnvelope | null = null;
+    private readonly handlers = new Map<number, ResponseHandler>();
+
+    constructor(private readonly transport: Transport) {
+        transport.onLine((line) => this.receive(line));
+    }
+
+    call(methodName: string, request: unknown, metadata: unknown): Promise<unknown> {
+        return this.response((requestId) => this.send(requestId, methodName, request, metadata));
+    }
+
+    clientStream(methodName: string, requests: AsyncIterable<unknown>, metadata: unknown): Promise<unknown> {
+        return this.response((requestId) => this.sendStream(requestId, methodName, requests, metadata));
+    }
+
+    stream(methodName: string, request: unknown, metadata: unknown): AsyncGenerator<unknown, void, undefined> {
+        return this.responses((requestId) => this.send(requestId, methodName, request, metadata));
+    }
+
+    bidirectionalStream(
+        methodName: string,
+        requests: AsyncIterable<unknown>,
+        metadata: unknown,
+    ): AsyncGenerator<unknown, void, undefined> {
+        return this.responses((requestId) => this.sendStream(requestId, methodName, requests, metadata));
+    }
+
+    private response(send: (requestId: number) => void): Promise<unknown> {
+        return new Promise((resolve, reject) => {
+            const requestId = this.nextRequestId++;
+            this.handlers.set(requestId, (envelope, payload) => {
+                this.handlers.delete(requestId);
+
+                if (envelope.error !== null) {
+                    reject(RpcError.fromJson(envelope.error));
+                } else {
+                    resolve(payload);
+                }
+            });
+
+            send(requestId);
+        });
+    }
+
+    private async *responses(send: (requestId: number) => void): AsyncGenerator<unknown, void, undefined> {
+        const requestId = this.nextRequestId++;
+        const queue: StreamItem[] = [];
+        let wake: (() => void) | null = null;
+
+        this.handlers.set(requestId, (envelope, payload) => {
+            if (envelope.error !== null) {
+                queue.push({ error: RpcError.fromJson(envelope.error) });
+            } else if (envelope.stream_end) {
+                queue.push({ end: true });
+            } else {
+                queue.push({ payload });
+            }
+
+            wake?.();
+        });
+
+        send(requestId);
+
+        try {
+            for (;;) {
+                const item = queue.shift();
+
+                if (item === undefined) {
+                    await new Promise<void>((resolve) => {
+                        wake = resolve;
+                    });
+                    wake = null;
+                } else if ("end" in item) {
+                    return;
+                } else if ("error" in item) {
+                    throw item.error;
+                } else {
+                    yield item.payload;
+                }
+            }
+        } finally {
+            this.handlers.delete(requestId);
+        }
+    }
+
+    private send(requestId: number, methodName: string, request: unknown, metadata: unknown, stream?: string): void {
+        this.transport.send(JSON.stringify({ method_name: methodName, request_id: requestId, stream }));
+        this.transport.send(JSON.stringify(metadata));
+        this.transport.send(JSON.stringify(request));
+    }
+
+    // The requests are sent as they are produced, the responses are received in the meantime
+    private sendStream(requestId: number, methodName: string, requests: AsyncIterable<unknown>, metadata: unknown): void {
+        this.send(requestId, methodName, null, metadata, "start");
+
+        void (async () => {
+            try {
+                for await (const request of requests) {
+                    this.send(requestId, methodName, request, null, "item");
+                }
+            } finally {
+                this.send(requestId, methodName, null, null, "end");
+            }
+        })();
+    }
+
+    private receive(line: string): void {
+        if (this.envelope === null) {
+            const envelope = JSON.parse(line) as ResponseEnvelope;
+
+            if (envelope.error !== null) {
+                this.handlers.get(envelope.request_id)?.(envelope, null);
+            } else {
+                this.envelope = envelope;
+            }
+
+            return;
+        }
+
+        const envelope = this.envelope;
+        this.envelope = null;
+        this.handlers.get(envelope.request_id)?.(envelope, JSON.parse(line));
+    }
+}
+"#;
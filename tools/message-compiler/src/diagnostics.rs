@@ -0,0 +1,230 @@
+use crate::parsing::Span;
+use crate::type_checking::TypeCheckError;
+use lalrpop_util::ParseError;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter, Write};
+
+/// A named source text that diagnostics can be rendered against.
+pub struct SourceFile<'a> {
+    name: &'a str,
+    contents: &'a str,
+}
+
+impl<'a> SourceFile<'a> {
+    #[must_use]
+    pub fn new(name: &'a str, contents: &'a str) -> Self {
+        Self { name, contents }
+    }
+
+    /// Returns the 1-based line and column of a byte offset.
+    #[must_use]
+    pub fn location(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.contents.len());
+        let before = &self.contents[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |ix| ix + 1);
+        let column = self.contents[line_start..offset].chars().count() + 1;
+
+        (line, column)
+    }
+
+    fn render(&self, output: &mut String, message: &str, span: Span) -> std::fmt::Result {
+        let start = span.start.min(self.contents.len());
+        let (line, column) = self.location(start);
+        let line_start = self.contents[..start].rfind('\n').map_or(0, |ix| ix + 1);
+        let line_text = self.contents[line_start..]
+            .lines()
+            .next()
+            .unwrap_or_default();
+        // Spans reaching past the end of the line are underlined up to the end of it
+        let underline_end = span.end.min(line_start + line_text.len()).max(start);
+        let underline_length = self.contents[start..underline_end].chars().count().max(1);
+        let gutter = " ".repeat(line.to_string().len());
+
+        writeln!(output, "error: {message}")?;
+        writeln!(output, "{gutter}--> {}:{line}:{column}", self.name)?;
+        writeln!(output, "{gutter} |")?;
+        writeln!(output, "{line} | {line_text}")?;
+        writeln!(
+            output,
+            "{gutter} | {}{}",
+            " ".repeat(column - 1),
+            "^".repeat(underline_length)
+        )
+    }
+
+    /// # Panics
+    /// Will not panic, writing to a `String` cannot fail
+    #[must_use]
+    pub fn type_check_errors(&self, errors: &[TypeCheckError]) -> Diagnostics {
+        let mut rendered = String::new();
+        let mut errors: Vec<_> = errors.iter().collect();
+        errors.sort_by_key(|error| error.span().start);
+
+        for error in &errors {
+            self.render(&mut rendered, &error.to_string(), error.span())
+                .unwrap();
+            rendered.push('\n');
+        }
+
+        Diagnostics {
+            rendered,
+            count: errors.len(),
+        }
+    }
+
+    /// # Panics
+    /// Will not panic, writing to a `String` cannot fail
+    #[must_use]
+    pub fn parse_error<T, E>(&self, error: &ParseError<usize, T, E>) -> Diagnostics
+    where
+        T: Display,
+        E: Display,
+    {
+        let (message, span) = parse_error_message(error);
+
+        let mut rendered = String::new();
+        self.render(&mut rendered, &message, span).unwrap();
+        rendered.push('\n');
+
+        Diagnostics { rendered, count: 1 }
+    }
+}
+
+/// The message of a parse error, together with the span of the token it was found at.
+#[must_use]
+pub fn parse_error_message<T, E>(error: &ParseError<usize, T, E>) -> (String, Span)
+where
+    T: Display,
+    E: Display,
+{
+    match error {
+        ParseError::InvalidToken { location } => {
+            ("Invalid token".to_string(), Span::new(*location, *location))
+        }
+        ParseError::UnrecognizedEOF { location, expected } => (
+            format!(
+                "Unexpected end of file, expected one of: {}",
+                expected.join(", ")
+            ),
+            Span::new(*location, *location),
+        ),
+        ParseError::UnrecognizedToken {
+            token: (start, token, end),
+            expected,
+        } => (
+            format!(
+                "Unexpected token \"{token}\", expected one of: {}",
+                expected.join(", ")
+            ),
+            Span::new(*start, *end),
+        ),
+        ParseError::ExtraToken {
+            token: (start, token, end),
+        } => (
+            format!("Unexpected extra token \"{token}\""),
+            Span::new(*start, *end),
+        ),
+        ParseError::User { error } => (error.to_string(), Span::new(0, 0)),
+    }
+}
+
+/// A set of rendered, human-readable errors, one per problem found in a source file.
+pub struct Diagnostics {
+    rendered: String,
+    count: usize,
+}
+
+impl Diagnostics {
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.rendered)?;
+        write!(
+            f,
+            "error: could not compile due to {} previous error{}",
+            self.count,
+            if self.count == 1 { "" } else { "s" }
+        )
+    }
+}
+
+// Build scripts return errors from `main`, which prints them with `Debug`, so this has to be readable
+impl Debug for Diagnostics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\n{self}")
+    }
+}
+
+impl Error for Diagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing;
+    use crate::type_checking::TypeChecker;
+
+    #[test]
+    pub fn location_test() {
+        let source = SourceFile::new("test.evd", "struct A {\n    f: u8\n}");
+
+        assert_eq!(source.location(0), (1, 1));
+        assert_eq!(source.location(7), (1, 8));
+        assert_eq!(source.location(15), (2, 5));
+        assert_eq!(source.location(21), (3, 1));
+    }
+
+    #[test]
+    pub fn renders_type_check_errors() {
+        let input = "struct A {\n    f1: Foo,\n    f2: Bar[],\n}\n";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let errors = TypeChecker::new().check(&ast).err().unwrap();
+        let diagnostics = SourceFile::new("test.evd", input).type_check_errors(&errors);
+
+        assert_eq!(diagnostics.count(), 2);
+        assert_eq!(
+            diagnostics.to_string(),
+            "\
+error: A struct with name \"Foo\" does not exist
+ --> test.evd:2:9
+  |
+2 |     f1: Foo,
+  |         ^^^
+
+error: The type \"Bar\" used in \"Bar[]\" does not exist
+ --> test.evd:3:9
+  |
+3 |     f2: Bar[],
+  |         ^^^
+
+error: could not compile due to 2 previous errors"
+        );
+    }
+
+    #[test]
+    pub fn renders_parse_errors() {
+        let input = "struct A {\n    f1 u8\n}\n";
+        let error = parsing::grammar::RFileParser::new()
+            .parse(input)
+            .err()
+            .unwrap();
+        let diagnostics = SourceFile::new("test.evd", input).parse_error(&error);
+
+        assert_eq!(
+            diagnostics.to_string(),
+            "\
+error: Unexpected token \"u8\", expected one of: \":\"
+ --> test.evd:2:8
+  |
+2 |     f1 u8
+  |        ^^
+
+error: could not compile due to 1 previous error"
+        );
+    }
+}
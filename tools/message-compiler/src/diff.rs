@@ -0,0 +1,641 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+use crate::type_checking::{
+    TypedConstraints, TypedField, TypedFieldType, TypedFile, TypedRpcCall, TypedValue,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    /// Data and calls of the old version are still accepted by code generated from the new one
+    Compatible,
+    Breaking,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Change {
+    pub compatibility: Compatibility,
+    pub description: String,
+}
+
+impl Change {
+    fn compatible(description: String) -> Self {
+        Self {
+            compatibility: Compatibility::Compatible,
+            description,
+        }
+    }
+
+    fn breaking(description: String) -> Self {
+        Self {
+            compatibility: Compatibility::Breaking,
+            description,
+        }
+    }
+
+    #[must_use]
+    pub fn is_breaking(&self) -> bool {
+        self.compatibility == Compatibility::Breaking
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.compatibility {
+            Compatibility::Compatible => write!(f, "compatible: {}", self.description),
+            Compatibility::Breaking => write!(f, "breaking: {}", self.description),
+        }
+    }
+}
+
+// `Indirect` only changes how the type is stored in the generated code, not how it is serialized
+fn without_indirection(type_: &TypedFieldType) -> &TypedFieldType {
+    match type_ {
+        TypedFieldType::Indirect(type_) => without_indirection(type_),
+        type_ => type_,
+    }
+}
+
+// Instants are also read from whole seconds since the epoch, which is how they used to be sent
+fn reads_old_values(old: &TypedFieldType, new: &TypedFieldType) -> bool {
+    match (without_indirection(old), without_indirection(new)) {
+        (TypedFieldType::Optional(old), TypedFieldType::Optional(new))
+        | (TypedFieldType::Array(old), TypedFieldType::Array(new)) => reads_old_values(old, new),
+        (
+            TypedFieldType::U8
+            | TypedFieldType::U16
+            | TypedFieldType::U32
+            | TypedFieldType::U64
+            | TypedFieldType::S8
+            | TypedFieldType::S16
+            | TypedFieldType::S32
+            | TypedFieldType::S64,
+            TypedFieldType::Instant,
+        ) => true,
+        (old, new) => same_type(old, new),
+    }
+}
+
+fn same_type(old: &TypedFieldType, new: &TypedFieldType) -> bool {
+    match (without_indirection(old), without_indirection(new)) {
+        (TypedFieldType::Optional(old), TypedFieldType::Optional(new))
+        | (TypedFieldType::Array(old), TypedFieldType::Array(new)) => same_type(old, new),
+        (TypedFieldType::Map(old_key, old_value), TypedFieldType::Map(new_key, new_value)) => {
+            same_type(old_key, new_key) && same_type(old_value, new_value)
+        }
+        (old, new) => old == new,
+    }
+}
+
+// Whether the new bound is the same as the old one or lets through more values, `looser` being the
+// direction in which it does
+fn bound_accepts_old_values(
+    old: Option<&TypedValue>,
+    new: Option<&TypedValue>,
+    looser: Ordering,
+) -> bool {
+    let ordering = match (old, new) {
+        (_, None) => return true,
+        (None, Some(_)) => return false,
+        (Some(TypedValue::Integer(old)), Some(TypedValue::Integer(new))) => new.partial_cmp(old),
+        (Some(TypedValue::Float(old)), Some(TypedValue::Float(new))) => new.partial_cmp(old),
+        _ => None,
+    };
+
+    ordering.is_some_and(|ordering| ordering == Ordering::Equal || ordering == looser)
+}
+
+// Whether every value that satisfies the old constraints satisfies the new ones. Different patterns
+// cannot be compared, so any change to a pattern other than removing it is assumed to reject old values.
+fn constraints_accept_old_values(old: &TypedConstraints, new: &TypedConstraints) -> bool {
+    let max_length = match (old.max_length, new.max_length) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(old), Some(new)) => new >= old,
+    };
+
+    (new.pattern.is_none() || new.pattern == old.pattern)
+        && new.min_length.unwrap_or(0) <= old.min_length.unwrap_or(0)
+        && max_length
+        && bound_accepts_old_values(old.min.as_ref(), new.min.as_ref(), Ordering::Less)
+        && bound_accepts_old_values(old.max.as_ref(), new.max.as_ref(), Ordering::Greater)
+}
+
+// A renamed field shows up as a removed and an added one, as the serialized name changes either way
+fn diff_fields(
+    owner: &str,
+    old: &[TypedField],
+    new: &[TypedField],
+    // Metadata is sent with every call, so adding to it breaks the calls of older clients too
+    additions_break: bool,
+    changes: &mut Vec<Change>,
+) {
+    for old_field in old {
+        match new.iter().find(|field| field.name() == old_field.name()) {
+            None => changes.push(Change::breaking(format!(
+                "the field \"{}\" was removed from {owner}",
+                old_field.name()
+            ))),
+            Some(new_field) if new_field.serialized_name() != old_field.serialized_name() => {
+                changes.push(Change::breaking(format!(
+                    "the field \"{}\" in {owner} is serialized as \"{}\" instead of \"{}\"",
+                    old_field.name(),
+                    new_field.serialized_name(),
+                    old_field.serialized_name()
+                )));
+            }
+            Some(new_field) if !same_type(old_field.type_name(), new_field.type_name()) => {
+                let description = format!(
+                    "the type of the field \"{}\" in {owner} was changed from {} to {}",
+                    old_field.name(),
+                    old_field.type_name(),
+                    new_field.type_name()
+                );
+
+                if reads_old_values(old_field.type_name(), new_field.type_name()) {
+                    changes.push(Change::compatible(description));
+                } else {
+                    changes.push(Change::breaking(description));
+                }
+            }
+            Some(new_field) if old_field.constraints() != new_field.constraints() => {
+                let description = format!(
+                    "the constraints of the field \"{}\" in {owner} were changed",
+                    old_field.name()
+                );
+
+                if constraints_accept_old_values(old_field.constraints(), new_field.constraints()) {
+                    changes.push(Change::compatible(description));
+                } else {
+                    changes.push(Change::breaking(description));
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    for new_field in new {
+        if old.iter().any(|field| field.name() == new_field.name()) {
+            continue;
+        }
+
+        let description = format!("the field \"{}\" was added to {owner}", new_field.name());
+        // Missing optional fields are deserialized as `None` and others as their default, so old data can still be read
+        let can_be_missing = new_field.default().is_some()
+            || matches!(new_field.type_name(), TypedFieldType::Optional(_));
+        if !additions_break && can_be_missing {
+            changes.push(Change::compatible(description));
+        } else {
+            changes.push(Change::breaking(description));
+        }
+    }
+}
+
+fn call_parts(call: &TypedRpcCall) -> (&TypedFieldType, &TypedFieldType, bool) {
+    match call {
+        TypedRpcCall::Stream {
+            request, response, ..
+        } => (request, response, true),
+        TypedRpcCall::Unary {
+            request, response, ..
+        } => (request, response, false),
+    }
+}
+
+fn describe_call_type(type_name: &TypedFieldType, stream: bool) -> String {
+    if stream {
+        format!("stream {type_name}")
+    } else {
+        type_name.to_string()
+    }
+}
+
+// Methods are identified by the name they are called by, so moving one into a service removes it
+fn diff_calls(
+    old: &[(String, &TypedRpcCall)],
+    new: &[(String, &TypedRpcCall)],
+    changes: &mut Vec<Change>,
+) {
+    for (name, old_call) in old {
+        let Some((_, new_call)) = new.iter().find(|(new_name, _)| new_name == name) else {
+            changes.push(Change::breaking(format!(
+                "the method \"{name}\" was removed"
+            )));
+            continue;
+        };
+        let (old_request, old_response, old_stream) = call_parts(old_call);
+        let (new_request, new_response, new_stream) = call_parts(new_call);
+
+        // A stream is framed differently from a single value, so neither can replace the other
+        if !same_type(old_request, new_request)
+            || old_call.request_stream() != new_call.request_stream()
+        {
+            changes.push(Change::breaking(format!(
+                "the request of the method \"{name}\" was changed from {} to {}",
+                describe_call_type(old_request, old_call.request_stream()),
+                describe_call_type(new_request, new_call.request_stream())
+            )));
+        }
+
+        if !same_type(old_response, new_response) || old_stream != new_stream {
+            changes.push(Change::breaking(format!(
+                "the response of the method \"{name}\" was changed from {} to {}",
+                describe_call_type(old_response, old_stream),
+                describe_call_type(new_response, new_stream)
+            )));
+        }
+    }
+
+    for (name, _) in new {
+        if !old.iter().any(|(old_name, _)| old_name == name) {
+            changes.push(Change::compatible(format!(
+                "the method \"{name}\" was added"
+            )));
+        }
+    }
+}
+
+/// Compares two versions of a file and lists the differences between them, in the order of the old file.
+/// Any change that makes data or calls of the old version invalid for the new one is breaking.
+#[must_use]
+pub fn diff(old: &TypedFile, new: &TypedFile) -> Vec<Change> {
+    let mut changes = vec![];
+
+    diff_fields(
+        "the metadata",
+        old.meta.fields(),
+        new.meta.fields(),
+        true,
+        &mut changes,
+    );
+
+    for old_struct in &old.structs {
+        match new.structs.iter().find(|s| s.name() == old_struct.name()) {
+            None => changes.push(Change::breaking(format!(
+                "the struct \"{}\" was removed",
+                old_struct.name()
+            ))),
+            Some(new_struct) => diff_fields(
+                &format!("the struct \"{}\"", old_struct.name()),
+                old_struct.fields(),
+                new_struct.fields(),
+                false,
+                &mut changes,
+            ),
+        }
+    }
+
+    for new_struct in &new.structs {
+        if !old.structs.iter().any(|s| s.name() == new_struct.name()) {
+            changes.push(Change::compatible(format!(
+                "the struct \"{}\" was added",
+                new_struct.name()
+            )));
+        }
+    }
+
+    for old_enum in &old.enums {
+        let Some(new_enum) = new.enums.iter().find(|e| e.name() == old_enum.name()) else {
+            changes.push(Change::breaking(format!(
+                "the enum \"{}\" was removed",
+                old_enum.name()
+            )));
+            continue;
+        };
+
+        for old_variant in old_enum.variants() {
+            match new_enum
+                .variants()
+                .iter()
+                .find(|variant| variant.name() == old_variant.name())
+            {
+                None => changes.push(Change::breaking(format!(
+                    "the variant \"{}\" was removed from the enum \"{}\"",
+                    old_variant.name(),
+                    old_enum.name()
+                ))),
+                Some(new_variant)
+                    if new_variant.serialized_name() != old_variant.serialized_name() =>
+                {
+                    changes.push(Change::breaking(format!(
+                        "the variant \"{}\" of the enum \"{}\" is serialized as \"{}\" instead of \"{}\"",
+                        old_variant.name(),
+                        old_enum.name(),
+                        new_variant.serialized_name(),
+                        old_variant.serialized_name()
+                    )));
+                }
+                // Unit variants are serialized differently from the ones with fields
+                Some(new_variant) if new_variant.is_unit() != old_variant.is_unit() => {
+                    changes.push(Change::breaking(format!(
+                        "the variant \"{}\" of the enum \"{}\" was changed {}",
+                        old_variant.name(),
+                        old_enum.name(),
+                        if new_variant.is_unit() {
+                            "to a unit variant"
+                        } else {
+                            "from a unit variant"
+                        }
+                    )));
+                }
+                Some(new_variant) => diff_fields(
+                    &format!(
+                        "the variant \"{}\" of the enum \"{}\"",
+                        old_variant.name(),
+                        old_enum.name()
+                    ),
+                    old_variant.fields(),
+                    new_variant.fields(),
+                    false,
+                    &mut changes,
+                ),
+            }
+        }
+
+        if old_enum.tagging() != new_enum.tagging() {
+            changes.push(Change::breaking(format!(
+                "the tag of the enum \"{}\" was changed",
+                old_enum.name()
+            )));
+        }
+
+        for new_variant in new_enum.variants() {
+            if !old_enum
+                .variants()
+                .iter()
+                .any(|variant| variant.name() == new_variant.name())
+            {
+                changes.push(Change::compatible(format!(
+                    "the variant \"{}\" was added to the enum \"{}\"",
+                    new_variant.name(),
+                    old_enum.name()
+                )));
+            }
+        }
+    }
+
+    for new_enum in &new.enums {
+        if !old.enums.iter().any(|e| e.name() == new_enum.name()) {
+            changes.push(Change::compatible(format!(
+                "the enum \"{}\" was added",
+                new_enum.name()
+            )));
+        }
+    }
+
+    diff_constants(old, new, &mut changes);
+    diff_calls(&old.methods(), &new.methods(), &mut changes);
+
+    changes
+}
+
+// Constants are not a part of the data, so changing them does not break it
+fn diff_constants(old: &TypedFile, new: &TypedFile, changes: &mut Vec<Change>) {
+    for old_constant in &old.constants {
+        match new
+            .constants
+            .iter()
+            .find(|c| c.name() == old_constant.name())
+        {
+            None => changes.push(Change::compatible(format!(
+                "the constant \"{}\" was removed",
+                old_constant.name()
+            ))),
+            Some(new_constant)
+                if !same_type(old_constant.type_name(), new_constant.type_name())
+                    || old_constant.value() != new_constant.value() =>
+            {
+                changes.push(Change::compatible(format!(
+                    "the constant \"{}\" was changed",
+                    old_constant.name()
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for new_constant in &new.constants {
+        if !old
+            .constants
+            .iter()
+            .any(|c| c.name() == new_constant.name())
+        {
+            changes.push(Change::compatible(format!(
+                "the constant \"{}\" was added",
+                new_constant.name()
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing;
+    use crate::type_checking::TypeChecker;
+
+    fn check(input: &str) -> TypedFile {
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        TypeChecker::new().check(&ast).unwrap()
+    }
+
+    fn describe(old: &str, new: &str) -> Vec<String> {
+        diff(&check(old), &check(new))
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    pub fn identical_files_have_no_changes() {
+        let input =
+            "metadata { id: guid } struct A { a: A? } enum E { X(a: A) } rpc { x(A) -> stream E; }";
+
+        assert!(describe(input, input).is_empty());
+    }
+
+    #[test]
+    pub fn classifies_field_changes() {
+        assert_eq!(
+            describe(
+                "struct A { a: u8, b: string, c: guid }",
+                "struct A { a: u16, c: guid, d: string?, e: string, f: u32 = 0 }"
+            ),
+            vec![
+                "breaking: the type of the field \"a\" in the struct \"A\" was changed from u8 to u16",
+                "breaking: the field \"b\" was removed from the struct \"A\"",
+                "compatible: the field \"d\" was added to the struct \"A\"",
+                "breaking: the field \"e\" was added to the struct \"A\"",
+                "compatible: the field \"f\" was added to the struct \"A\"",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn any_metadata_change_is_breaking() {
+        assert_eq!(
+            describe(
+                "metadata { source: string } struct A {}",
+                "metadata { source: string, id: guid? } struct A {}"
+            ),
+            vec!["breaking: the field \"id\" was added to the metadata"]
+        );
+    }
+
+    #[test]
+    pub fn classifies_enum_changes() {
+        assert_eq!(
+            describe(
+                "struct A {} enum E { X(a: A), Y(), Z(z: A), V } enum F { X }",
+                "struct A {} enum E { X(a: A, b: u8), Y(y: string?), W, V() } @tag(\"type\") enum F { X }"
+            ),
+            vec![
+                "breaking: the field \"b\" was added to the variant \"X\" of the enum \"E\"",
+                "compatible: the field \"y\" was added to the variant \"Y\" of the enum \"E\"",
+                "breaking: the variant \"Z\" was removed from the enum \"E\"",
+                "breaking: the variant \"V\" of the enum \"E\" was changed from a unit variant",
+                "compatible: the variant \"W\" was added to the enum \"E\"",
+                "breaking: the tag of the enum \"F\" was changed",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn flags_variants_changed_between_unit_and_struct() {
+        assert_eq!(
+            describe(
+                "struct A {} enum FilesystemEventKind { Created(), Deleted, Moved(to: string) }",
+                "struct A {} enum FilesystemEventKind { Created, Deleted(), Moved(to: string) }"
+            ),
+            vec![
+                "breaking: the variant \"Created\" of the enum \"FilesystemEventKind\" was changed to a unit variant",
+                "breaking: the variant \"Deleted\" of the enum \"FilesystemEventKind\" was changed from a unit variant",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn classifies_renaming() {
+        assert_eq!(
+            describe(
+                "struct A { mount_id: string, path: string } enum E { FileMoved, Deleted }",
+                "@rename_all(\"camelCase\") @derive(\"Eq\", \"Hash\") struct A { mount_id: string, path: string } @rename_all(\"snake_case\") @non_exhaustive enum E { FileMoved, Deleted }"
+            ),
+            vec![
+                "breaking: the field \"mount_id\" in the struct \"A\" is serialized as \"mountId\" instead of \"mount_id\"",
+                "breaking: the variant \"FileMoved\" of the enum \"E\" is serialized as \"file_moved\" instead of \"FileMoved\"",
+                "breaking: the variant \"Deleted\" of the enum \"E\" is serialized as \"deleted\" instead of \"Deleted\"",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn constant_changes_are_compatible() {
+        assert_eq!(
+            describe(
+                "const a: u16 = 1; const b: string = \"x\"; const c: u8 = 1; struct A {}",
+                "const a: u32 = 1; const b: string = \"y\"; const d: u8 = 1; struct A {}"
+            ),
+            vec![
+                "compatible: the constant \"a\" was changed",
+                "compatible: the constant \"b\" was changed",
+                "compatible: the constant \"c\" was removed",
+                "compatible: the constant \"d\" was added",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn classifies_method_changes() {
+        assert_eq!(
+            describe(
+                "struct A {} rpc { a(A) -> A; b(A) -> A; c(void) -> A; }",
+                "struct A {} rpc { a(A) -> stream A; c(A) -> A; d(A) -> void; }"
+            ),
+            vec![
+                "breaking: the response of the method \"a\" was changed from A to stream A",
+                "breaking: the method \"b\" was removed",
+                "breaking: the request of the method \"c\" was changed from void to A",
+                "compatible: the method \"d\" was added",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn request_streams_are_breaking() {
+        assert_eq!(
+            describe(
+                "struct A {} rpc { a(A) -> A; b(stream A) -> stream A; }",
+                "struct A {} rpc { a(stream A) -> A; b(A) -> stream A; }"
+            ),
+            vec![
+                "breaking: the request of the method \"a\" was changed from A to stream A",
+                "breaking: the request of the method \"b\" was changed from stream A to A",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn methods_are_compared_by_service() {
+        assert_eq!(
+            describe(
+                "struct A {} rpc { a(A) -> A; }",
+                "struct A {} service Library { a(A) -> A; } service Playback { b(A) -> A; }"
+            ),
+            vec![
+                "breaking: the method \"a\" was removed",
+                "compatible: the method \"Library.a\" was added",
+                "compatible: the method \"Playback.b\" was added",
+            ]
+        );
+        assert_eq!(
+            describe(
+                "struct A {} service Library { a(A) -> A; }",
+                "struct A {} service Library { a(A) -> stream A; }"
+            ),
+            vec![
+                "breaking: the response of the method \"Library.a\" was changed from A to stream A"
+            ]
+        );
+    }
+
+    #[test]
+    pub fn integer_to_instant_is_compatible() {
+        assert_eq!(
+            describe(
+                "struct A { a: u64, b: u64[] } rpc { x(A) -> void; }",
+                "struct A { a: instant, b: instant[] } rpc { x(A) -> void; }"
+            ),
+            vec![
+                "compatible: the type of the field \"a\" in the struct \"A\" was changed from u64 to instant",
+                "compatible: the type of the field \"b\" in the struct \"A\" was changed from u64[] to instant[]",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn classifies_constraint_changes() {
+        assert_eq!(
+            describe(
+                "struct A { a: string @max_length(10), b: u8 @min(1), c: string @pattern(\"[a-z]+\"), d: string[] }",
+                "struct A { a: string @max_length(20), b: u8 @min(2), c: string, d: string[] @non_empty }"
+            ),
+            vec![
+                "compatible: the constraints of the field \"a\" in the struct \"A\" were changed",
+                "breaking: the constraints of the field \"b\" in the struct \"A\" were changed",
+                "compatible: the constraints of the field \"c\" in the struct \"A\" were changed",
+                "breaking: the constraints of the field \"d\" in the struct \"A\" were changed",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn indirection_is_not_a_change() {
+        assert!(describe(
+            "struct A { b: B } struct B { c: u8 }",
+            "struct A { b: B } struct B { a: A?, c: u8 }"
+        )
+        .iter()
+        .all(|change| change.starts_with("compatible")));
+    }
+}
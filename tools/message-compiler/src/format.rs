@@ -0,0 +1,598 @@
+use std::fmt::Write;
+
+use crate::parsing::{
+    AttributeRaw, FieldRaw, FileRaw, LiteralRaw, RpcDefinitionRaw, RpcRaw, Span, TypeRaw,
+};
+
+const INDENT: &str = "    ";
+
+fn format_type(type_: &TypeRaw) -> String {
+    match type_ {
+        TypeRaw::Type(id) => id.name().to_string(),
+        TypeRaw::Optional(inner) => format!("{}?", format_type(inner)),
+        TypeRaw::Array(inner) => format!("{}[]", format_type(inner)),
+        TypeRaw::Map(key, value, _) => format!("map<{}, {}>", format_type(key), format_type(value)),
+    }
+}
+
+fn format_literal(literal: &LiteralRaw) -> String {
+    match literal {
+        LiteralRaw::Integer(value, _)
+        | LiteralRaw::Float(value, _)
+        | LiteralRaw::Duration(value, _) => (*value).to_string(),
+        LiteralRaw::Boolean(value, _) => value.to_string(),
+        LiteralRaw::String(value, _) => format!("\"{value}\""),
+    }
+}
+
+// Every attribute is followed by the separator, so that they can be put either on the line of the
+// annotated item or on lines of their own above it
+fn format_attributes(attributes: &[AttributeRaw], separator: &str) -> String {
+    let mut result = String::new();
+
+    for attribute in attributes {
+        result.push('@');
+        result.push_str(attribute.name.name());
+        if !attribute.arguments.is_empty() {
+            let arguments: Vec<_> = attribute.arguments.iter().map(format_literal).collect();
+            write!(result, "({})", arguments.join(", ")).unwrap();
+        }
+        result.push_str(separator);
+    }
+
+    result
+}
+
+fn format_field(field: &FieldRaw, attribute_separator: &str) -> String {
+    let mut result = format!(
+        "{}{}: {}",
+        format_attributes(&field.attributes, attribute_separator),
+        field.name.name(),
+        format_type(&field.type_name)
+    );
+    if !field.constraints.is_empty() {
+        write!(
+            result,
+            " {}",
+            format_attributes(&field.constraints, " ").trim_end()
+        )
+        .unwrap();
+    }
+    if let Some(default) = &field.default {
+        write!(result, " = {}", format_literal(default)).unwrap();
+    }
+
+    result
+}
+
+fn format_methods(rpc: &RpcRaw) -> Vec<(String, Span)> {
+    rpc.definitions
+        .iter()
+        .map(|definition| {
+            let line = match definition {
+                RpcDefinitionRaw::Stream {
+                    name,
+                    request,
+                    request_stream,
+                    response,
+                    ..
+                } => format!(
+                    "{}({}{}) -> stream {};",
+                    name.name(),
+                    request_stream_prefix(*request_stream),
+                    format_type(request),
+                    format_type(response)
+                ),
+                RpcDefinitionRaw::Unary {
+                    name,
+                    request,
+                    request_stream,
+                    response,
+                    ..
+                } => format!(
+                    "{}({}{}) -> {};",
+                    name.name(),
+                    request_stream_prefix(*request_stream),
+                    format_type(request),
+                    format_type(response)
+                ),
+            };
+
+            (
+                format_attributes(definition.attributes(), "\n") + &line,
+                definition.span(),
+            )
+        })
+        .collect()
+}
+
+fn request_stream_prefix(request_stream: bool) -> &'static str {
+    if request_stream {
+        "stream "
+    } else {
+        ""
+    }
+}
+
+struct Comment<'input> {
+    text: &'input str,
+    start: usize,
+}
+
+// The grammar skips comments, so they are found again here. `//` inside of a string is not a comment.
+fn comments(source: &str) -> Vec<Comment<'_>> {
+    let mut comments = vec![];
+    let mut in_string = false;
+    let mut position = 0;
+
+    while let Some(offset) = source[position..].find(['"', '/']) {
+        let start = position + offset;
+
+        if source[start..].starts_with('"') {
+            in_string = !in_string;
+            position = start + 1;
+        } else if !in_string && source[start..].starts_with("//") {
+            let end = source[start..]
+                .find(['\n', '\r'])
+                .map_or(source.len(), |length| start + length);
+            comments.push(Comment {
+                text: source[start..end].trim_end(),
+                start,
+            });
+            position = end;
+        } else {
+            position = start + 1;
+        }
+    }
+
+    comments
+}
+
+struct Printer<'input> {
+    source: &'input str,
+    comments: Vec<Comment<'input>>,
+    next_comment: usize,
+    output: String,
+}
+
+impl<'input> Printer<'input> {
+    fn new(source: &'input str) -> Self {
+        Self {
+            source,
+            comments: comments(source),
+            next_comment: 0,
+            output: String::new(),
+        }
+    }
+
+    fn has_comments_before(&self, position: usize) -> bool {
+        self.comments
+            .get(self.next_comment)
+            .is_some_and(|comment| comment.start < position)
+    }
+
+    // Comments that were not printed yet and start before the position, each on its own line
+    fn comments_before(&mut self, position: usize, indent: &str) {
+        while self.has_comments_before(position) {
+            let comment = &self.comments[self.next_comment];
+            writeln!(self.output, "{indent}{}", comment.text).unwrap();
+            self.next_comment += 1;
+        }
+    }
+
+    // A comment that follows the code ending at `end` on the same line, with nothing but a separator
+    // in between, stays at the end of the line
+    fn line(&mut self, indent: &str, text: &str, end: usize) {
+        write!(self.output, "{indent}{text}").unwrap();
+
+        if let Some(comment) = self.comments.get(self.next_comment) {
+            let between = self.source.get(end..comment.start).unwrap_or("\n");
+            if between.chars().all(|c| matches!(c, ' ' | '\t' | ',' | ';')) {
+                write!(self.output, " {}", comment.text).unwrap();
+                self.next_comment += 1;
+            }
+        }
+
+        self.output.push('\n');
+    }
+
+    fn block(&mut self, header: &str, span: Span, lines: &[(String, Span)]) {
+        self.comments_before(span.start, "");
+
+        if lines.is_empty() && !self.has_comments_before(span.end) {
+            self.line("", &format!("{header} {{}}"), span.end);
+            return;
+        }
+
+        writeln!(self.output, "{header} {{").unwrap();
+        for (item, item_span) in lines {
+            self.comments_before(item_span.start, INDENT);
+
+            // Attributes of the item are on the lines before it
+            let mut item_lines: Vec<_> = item.split('\n').collect();
+            let last_line = item_lines.pop().unwrap_or_default();
+            for line in item_lines {
+                writeln!(self.output, "{INDENT}{line}").unwrap();
+            }
+            self.line(INDENT, last_line, item_span.end);
+        }
+        self.comments_before(span.end, INDENT);
+        self.line("", "}", span.end);
+    }
+
+    fn separate_block(&mut self) {
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+    }
+}
+
+/// Prints the file in the canonical format: every field, variant and method on its own line,
+/// indented with four spaces and followed by a separator, with blocks separated by an empty line.
+///
+/// The `source` is the text the file was parsed from, its comments are kept in place.
+#[must_use]
+pub fn format(file: &FileRaw, source: &str) -> String {
+    let mut printer = Printer::new(source);
+
+    for import in file.imports() {
+        let names: Vec<_> = import.names.iter().map(|name| name.name()).collect();
+        printer.comments_before(import.span.start, "");
+        printer.line(
+            "",
+            &format!("import \"{}\" {{ {} }}", import.path, names.join(", ")),
+            import.span.end,
+        );
+    }
+
+    if let Some(metadata) = file.metadata() {
+        let fields: Vec<_> = metadata
+            .fields()
+            .iter()
+            .map(|field| (format_field(field, "\n") + ",", field.span))
+            .collect();
+        printer.separate_block();
+        printer.block("metadata", metadata.span(), &fields);
+    }
+
+    if !file.constants().is_empty() {
+        printer.separate_block();
+    }
+    for constant in file.constants() {
+        printer.comments_before(constant.span.start, "");
+        printer.line(
+            "",
+            &format!(
+                "const {}: {} = {};",
+                constant.name.name(),
+                format_type(&constant.type_name),
+                format_literal(&constant.value)
+            ),
+            constant.span.end,
+        );
+    }
+
+    for struct_ in file.structs() {
+        let fields: Vec<_> = struct_
+            .1
+            .iter()
+            .map(|field| (format_field(field, "\n") + ",", field.span))
+            .collect();
+        printer.separate_block();
+        printer.block(
+            &format!(
+                "{}struct {}",
+                format_attributes(&struct_.4, "\n"),
+                struct_.0.name()
+            ),
+            struct_.2,
+            &fields,
+        );
+    }
+
+    for enum_ in file.enums() {
+        let variants: Vec<_> = enum_
+            .variants
+            .iter()
+            .map(|variant| {
+                let attributes = format_attributes(&variant.attributes, "\n");
+                let line = if variant.unit {
+                    format!("{attributes}{},", variant.name.name())
+                } else {
+                    let fields: Vec<_> = variant
+                        .fields
+                        .iter()
+                        .map(|field| format_field(field, " "))
+                        .collect();
+                    format!(
+                        "{attributes}{}({}),",
+                        variant.name.name(),
+                        fields.join(", ")
+                    )
+                };
+
+                (line, variant.span)
+            })
+            .collect();
+        printer.separate_block();
+        printer.block(
+            &format!(
+                "{}enum {}",
+                format_attributes(&enum_.attributes, "\n"),
+                enum_.name.name()
+            ),
+            enum_.span,
+            &variants,
+        );
+    }
+
+    if let Some(rpc) = file.rpc() {
+        printer.separate_block();
+        printer.block("rpc", rpc.span, &format_methods(rpc));
+    }
+
+    for service in file.services() {
+        printer.separate_block();
+        printer.block(
+            &format!("service {}", service.name.name()),
+            service.span,
+            &format_methods(&service.rpc),
+        );
+    }
+
+    if printer.has_comments_before(source.len()) {
+        printer.separate_block();
+        printer.comments_before(source.len(), "");
+    }
+
+    printer.output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing;
+
+    #[test]
+    pub fn format_test() {
+        let input = "import \"../events/events.evd\" {FileOnMountPath,}
+metadata {}
+const DEFAULT_PORT:u16=7654; const  VERSION : string = \"1\";
+struct A { f1: u8, f2 : B?,
+f3:B[] }  @derive( \"Eq\",\"Hash\" ) @non_exhaustive struct B {}
+@tag( \"type\" ) enum E {  X, Y(a: u8,b:string) , Z() }
+rpc { call(A) -> B; listen(void)->stream E }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            format(&ast, input),
+            "\
+import \"../events/events.evd\" { FileOnMountPath }
+
+metadata {}
+
+const DEFAULT_PORT: u16 = 7654;
+const VERSION: string = \"1\";
+
+struct A {
+    f1: u8,
+    f2: B?,
+    f3: B[],
+}
+
+@derive(\"Eq\", \"Hash\")
+@non_exhaustive
+struct B {}
+
+@tag(\"type\")
+enum E {
+    X,
+    Y(a: u8, b: string),
+    Z(),
+}
+
+rpc {
+    call(A) -> B;
+    listen(void) -> stream E;
+}
+"
+        );
+    }
+
+    #[test]
+    pub fn formatting_is_idempotent() {
+        let input = "metadata { a: guid } struct A { b: string } rpc { x(A) -> A; }";
+        let formatted = format(
+            &parsing::grammar::RFileParser::new().parse(input).unwrap(),
+            input,
+        );
+        let reformatted = format(
+            &parsing::grammar::RFileParser::new()
+                .parse(&formatted)
+                .unwrap(),
+            &formatted,
+        );
+
+        assert_eq!(formatted, reformatted);
+    }
+
+    #[test]
+    pub fn preserves_comments() {
+        let input = "// Shared with the music service
+import \"events.evd\" { E } // not a // comment in \"quotes\"
+struct A { // the header
+    // before a field
+    f1: u8, // after a field
+    f2: string
+    // at the end
+} struct B { // inside an empty block
+}
+rpc { x(A) -> B; }
+// at the end of the file";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            format(&ast, input),
+            "\
+// Shared with the music service
+import \"events.evd\" { E } // not a // comment in \"quotes\"
+
+struct A {
+    // the header
+    // before a field
+    f1: u8, // after a field
+    f2: string,
+    // at the end
+}
+
+struct B {
+    // inside an empty block
+}
+
+rpc {
+    x(A) -> B;
+}
+
+// at the end of the file
+"
+        );
+    }
+
+    #[test]
+    pub fn formats_services() {
+        let input = "struct A {}\n/// The library\nservice Library { tracks(void)->stream A; // all of them\n@deprecated(\"no\") x(A) -> A } service Playback{}";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            format(&ast, input),
+            "\
+struct A {}
+
+/// The library
+service Library {
+    tracks(void) -> stream A; // all of them
+    @deprecated(\"no\")
+    x(A) -> A;
+}
+
+service Playback {}
+"
+        );
+    }
+
+    #[test]
+    pub fn formats_request_streams() {
+        let input = "struct A {} rpc { upload(  stream A)->A; chat(stream   A)  -> stream A; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            format(&ast, input),
+            "\
+struct A {}
+
+rpc {
+    upload(stream A) -> A;
+    chat(stream A) -> stream A;
+}
+"
+        );
+    }
+
+    #[test]
+    pub fn formats_method_options() {
+        let input = "struct A {} rpc { @idempotent  @timeout( 500ms ) get(A)->A; @requires(\"admin\",\"write\") set(A) -> A; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            format(&ast, input),
+            "\
+struct A {}
+
+rpc {
+    @idempotent
+    @timeout(500ms)
+    get(A) -> A;
+    @requires(\"admin\", \"write\")
+    set(A) -> A;
+}
+"
+        );
+    }
+
+    #[test]
+    pub fn preserves_docs() {
+        let input = "/// Where a file is\nstruct A { /// The mount\n  mount_id: string, \n/// The path\n  path: string } rpc {\n/// Subscribes\n  subscribe(A) -> stream A; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            format(&ast, input),
+            "\
+/// Where a file is
+struct A {
+    /// The mount
+    mount_id: string,
+    /// The path
+    path: string,
+}
+
+rpc {
+    /// Subscribes
+    subscribe(A) -> stream A;
+}
+"
+        );
+    }
+
+    #[test]
+    pub fn formats_attributes_and_defaults() {
+        let input = "struct A { @deprecated(\"old\")f1: u8=0, f2: string?@pattern( \"[a-z]+\" )@non_empty = \"x\", f3: bool=true, f4: f64 @min(-1) @max(1)= -0.5 }
+enum E { @deprecated(\"gone\") X, Y(@deprecated(\"b\") a: s8 = -1) }
+rpc { @deprecated(\"no\") @other x(A) -> A; }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            format(&ast, input),
+            "\
+struct A {
+    @deprecated(\"old\")
+    f1: u8 = 0,
+    f2: string? @pattern(\"[a-z]+\") @non_empty = \"x\",
+    f3: bool = true,
+    f4: f64 @min(-1) @max(1) = -0.5,
+}
+
+enum E {
+    @deprecated(\"gone\")
+    X,
+    Y(@deprecated(\"b\") a: s8 = -1),
+}
+
+rpc {
+    @deprecated(\"no\")
+    @other
+    x(A) -> A;
+}
+"
+        );
+    }
+
+    #[test]
+    pub fn comments_in_strings_are_ignored() {
+        let input = "import \"a//b.evd\" { A } // c";
+        let comments = comments(input);
+
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].text, "// c");
+    }
+
+    #[test]
+    pub fn formats_nested_types() {
+        let input = "struct A { f1: map < string,map<u8 ,B[]?> >[] }";
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            format(&ast, input),
+            "struct A {\n    f1: map<string, map<u8, B[]?>>[],\n}\n"
+        );
+    }
+}
@@ -1,4 +1,14 @@
+pub mod analysis;
+pub mod build;
+pub mod compiler_json_schema;
+pub mod compiler_openrpc;
+pub mod compiler_php;
 pub mod compiler_rust;
+pub mod compiler_typescript;
+pub mod diagnostics;
+pub mod diff;
+pub mod format;
+mod naming;
 pub mod parsing;
 pub mod type_checking;
 
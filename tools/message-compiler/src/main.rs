@@ -1,36 +1,360 @@
-mod compiler_rust;
-mod parsing;
-mod type_checking;
+use message_compiler::build::{self, CompileOptions, Target};
+use message_compiler::diagnostics::SourceFile;
+use message_compiler::diff::diff;
+use message_compiler::format::format;
+use message_compiler::parsing;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-#[macro_use]
-extern crate lalrpop_util;
+const USAGE: &str = "\
+Usage:
+    message-compiler compile --target <rust|php|ts|json-schema|openrpc> --out <DIR> [--php-namespace <NAMESPACE>] [--api-version <VERSION>] [--crate <IMPORTED FILE>=<CRATE>]... <FILE>...
+    message-compiler check <FILE>...
+    message-compiler diff [--allow <FILE>] <OLD FILE> <NEW FILE>
+    message-compiler fmt [--check] <FILE>...";
 
-use crate::type_checking::TypeChecker;
-use std::error::Error;
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Compile {
+        target: Target,
+        out: PathBuf,
+        php_namespace: Option<String>,
+        api_version: Option<String>,
+        crates: Vec<(String, String)>,
+        files: Vec<PathBuf>,
+    },
+    Check {
+        files: Vec<PathBuf>,
+    },
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        allowed: Option<PathBuf>,
+    },
+    Format {
+        check: bool,
+        files: Vec<PathBuf>,
+    },
+}
+
+fn parse_arguments(arguments: &[String]) -> Result<Command, String> {
+    let (command, arguments) = arguments.split_first().ok_or("No command given")?;
+
+    let mut target = None;
+    let mut out = None;
+    let mut php_namespace = None;
+    let mut api_version = None;
+    let mut crates = vec![];
+    let mut allowed = None;
+    let mut check = false;
+    let mut files = vec![];
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let mut value = || {
+            arguments
+                .next()
+                .cloned()
+                .ok_or_else(|| format!("Missing value for {argument}"))
+        };
+
+        match argument.as_str() {
+            "--target" if command == "compile" => target = Some(value()?.parse()?),
+            "--out" if command == "compile" => out = Some(PathBuf::from(value()?)),
+            "--php-namespace" if command == "compile" => php_namespace = Some(value()?),
+            "--api-version" if command == "compile" => api_version = Some(value()?),
+            "--crate" if command == "compile" => {
+                let value = value()?;
+                let (file, crate_) = value
+                    .split_once('=')
+                    .ok_or_else(|| format!("Expected <IMPORTED FILE>=<CRATE>, got \"{value}\""))?;
+                crates.push((file.to_string(), crate_.to_string()));
+            }
+            "--allow" if command == "diff" => allowed = Some(PathBuf::from(value()?)),
+            "--check" if command == "fmt" => check = true,
+            option if option.starts_with("--") => return Err(format!("Unknown option {option}")),
+            file => files.push(PathBuf::from(file)),
+        }
+    }
+
+    if files.is_empty() {
+        return Err("No files given".to_string());
+    }
+
+    match command.as_str() {
+        "compile" => Ok(Command::Compile {
+            target: target.ok_or("Missing --target")?,
+            out: out.ok_or("Missing --out")?,
+            php_namespace,
+            api_version,
+            crates,
+            files,
+        }),
+        "check" => Ok(Command::Check { files }),
+        "diff" => match <[PathBuf; 2]>::try_from(files) {
+            Ok([old, new]) => Ok(Command::Diff { old, new, allowed }),
+            Err(_) => Err("diff expects exactly two files".to_string()),
+        },
+        "fmt" => Ok(Command::Format { check, files }),
+        other => Err(format!("Unknown command \"{other}\"")),
+    }
+}
+
+#[derive(Debug)]
+struct NotFormatted(PathBuf);
+
+impl Display for NotFormatted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The file \"{}\" is not formatted", self.0.display())
+    }
+}
+
+impl Error for NotFormatted {}
+
+// With `check` the file is left as is, and an error is returned if formatting would change it
+fn format_file(path: &Path, check: bool) -> Result<(), Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let name = path.display().to_string();
+    let source = SourceFile::new(&name, &contents);
+    let ast = parsing::grammar::RFileParser::new()
+        .parse(&contents)
+        .map_err(|e| source.parse_error(&e))?;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let ast = parsing::grammar::RFileParser::new().parse(
-        "\
-        metadata { f0: u16, } \
-        \
-        struct A { f0:u8 } \
-        struct B { f1:u16, f2:A } \
-        enum Food {\
-            Carrot(length:u8),\
-            Apple(length:u8, roundness: u64),\
-        }\
-        \
-        rpc {\
-            method(A?) -> B?;\
-            method2(Food) -> B;\
-        }\
-        \
-        ",
-    )?;
-    let type_checker = TypeChecker::new();
-    let typed_file = type_checker.check(&ast)?;
-    let rust = compiler_rust::compile(typed_file);
-    println!("{rust}");
+    let formatted = format(&ast, &contents);
+    if formatted == contents {
+        return Ok(());
+    }
+
+    if check {
+        return Err(Box::new(NotFormatted(path.to_path_buf())));
+    }
+
+    std::fs::write(path, formatted)?;
 
     Ok(())
 }
+
+#[derive(Debug)]
+struct BreakingChanges;
+
+impl Display for BreakingChanges {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The new version contains breaking changes")
+    }
+}
+
+impl Error for BreakingChanges {}
+
+// The breaking changes that are intended, one description per line, lines starting with "#" are
+// comments
+fn allowed_changes(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+fn diff_files(old: &Path, new: &Path, allowed: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let allowed = allowed
+        .map(allowed_changes)
+        .transpose()?
+        .unwrap_or_default();
+    let changes = diff(&build::load(old)?, &build::load(new)?);
+
+    let mut breaking = false;
+    for change in &changes {
+        if change.is_breaking() && allowed.contains(&change.description) {
+            println!("allowed: {}", change.description);
+        } else {
+            breaking |= change.is_breaking();
+            println!("{change}");
+        }
+    }
+
+    if breaking {
+        return Err(Box::new(BreakingChanges));
+    }
+
+    Ok(())
+}
+
+// Every file is processed even if some fail, so that all the problems are reported at once
+fn run(command: Command) -> bool {
+    let mut success = true;
+    let mut report = |result: Result<(), Box<dyn Error>>| {
+        if let Err(error) = result {
+            eprintln!("{error}");
+            success = false;
+        }
+    };
+
+    match command {
+        Command::Compile {
+            target,
+            out,
+            php_namespace,
+            api_version,
+            crates,
+            files,
+        } => {
+            let crates: Vec<_> = crates
+                .iter()
+                .map(|(file, crate_)| (file.as_str(), crate_.as_str()))
+                .collect();
+            let mut options = CompileOptions {
+                php_namespace: php_namespace.as_deref(),
+                crates: &crates,
+                ..CompileOptions::default()
+            };
+            if let Some(api_version) = &api_version {
+                options.api_version = api_version;
+            }
+
+            for file in files {
+                report(
+                    build::compile(&file, target, options).and_then(|generated| {
+                        for (path, contents) in generated {
+                            let path = out.join(path);
+                            if let Some(parent) = path.parent() {
+                                std::fs::create_dir_all(parent)?;
+                            }
+                            std::fs::write(path, contents)?;
+                        }
+
+                        Ok(())
+                    }),
+                );
+            }
+        }
+        Command::Check { files } => {
+            for file in files {
+                report(build::load(&file).map(|_| ()));
+            }
+        }
+        Command::Diff { old, new, allowed } => {
+            report(diff_files(&old, &new, allowed.as_deref()));
+        }
+        Command::Format { check, files } => {
+            for file in files {
+                report(format_file(&file, check));
+            }
+        }
+    }
+
+    success
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+
+    let command = match parse_arguments(&arguments) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("error: {error}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if run(command) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(arguments: &str) -> Vec<String> {
+        arguments.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    pub fn parses_compile() {
+        assert_eq!(
+            parse_arguments(&arguments(
+                "compile --target php --out generated a.evd --php-namespace Ramona b.evd"
+            )),
+            Ok(Command::Compile {
+                target: Target::Php,
+                out: PathBuf::from("generated"),
+                php_namespace: Some("Ramona".to_string()),
+                api_version: None,
+                crates: vec![],
+                files: vec![PathBuf::from("a.evd"), PathBuf::from("b.evd")],
+            })
+        );
+    }
+
+    #[test]
+    pub fn parses_compile_crates() {
+        assert_eq!(
+            parse_arguments(&arguments(
+                "compile --target rust --out generated --crate directory-watcher.evd=lib-directory-watcher a.evd"
+            )),
+            Ok(Command::Compile {
+                target: Target::Rust,
+                out: PathBuf::from("generated"),
+                php_namespace: None,
+                api_version: None,
+                crates: vec![(
+                    "directory-watcher.evd".to_string(),
+                    "lib-directory-watcher".to_string()
+                )],
+                files: vec![PathBuf::from("a.evd")],
+            })
+        );
+        assert!(parse_arguments(&arguments(
+            "compile --target rust --out generated --crate lib-directory-watcher a.evd"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    pub fn parses_diff() {
+        assert_eq!(
+            parse_arguments(&arguments("diff old/a.evd a.evd")),
+            Ok(Command::Diff {
+                old: PathBuf::from("old/a.evd"),
+                new: PathBuf::from("a.evd"),
+                allowed: None,
+            })
+        );
+        assert_eq!(
+            parse_arguments(&arguments(
+                "diff --allow a.breaking-changes old/a.evd a.evd"
+            )),
+            Ok(Command::Diff {
+                old: PathBuf::from("old/a.evd"),
+                new: PathBuf::from("a.evd"),
+                allowed: Some(PathBuf::from("a.breaking-changes")),
+            })
+        );
+        assert!(parse_arguments(&arguments("diff a.evd")).is_err());
+    }
+
+    #[test]
+    pub fn parses_fmt_check() {
+        assert_eq!(
+            parse_arguments(&arguments("fmt --check a.evd")),
+            Ok(Command::Format {
+                check: true,
+                files: vec![PathBuf::from("a.evd")],
+            })
+        );
+    }
+
+    #[test]
+    pub fn rejects_invalid_arguments() {
+        assert!(parse_arguments(&arguments("compile --target php a.evd")).is_err());
+        assert!(parse_arguments(&arguments("check")).is_err());
+        assert!(parse_arguments(&arguments("check --target rust a.evd")).is_err());
+        assert!(parse_arguments(&arguments("check --check a.evd")).is_err());
+        assert!(parse_arguments(&arguments("build a.evd")).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+// Conversions from the snake_case names used in message definitions to the conventions of the target languages
+
+pub(crate) fn camel_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut uppercase_next = false;
+
+    for c in name.chars() {
+        if c == '_' && !result.is_empty() {
+            uppercase_next = true;
+        } else if uppercase_next {
+            result.push(c.to_ascii_uppercase());
+            uppercase_next = false;
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+pub(crate) fn pascal_case(name: &str) -> String {
+    name.split(['_', '-'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect()
+}
+
+// Variant names are PascalCase, unlike the rest of the names
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if !result.is_empty() && !result.ends_with('_') {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+// Constants are uppercase in all of the target languages
+pub(crate) fn constant_case(name: &str) -> String {
+    name.to_ascii_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn naming_tests() {
+        assert_eq!(camel_case("all_artists"), "allArtists");
+        assert_eq!(camel_case("_private_name"), "_privateName");
+        assert_eq!(pascal_case("lib-directory-watcher"), "LibDirectoryWatcher");
+        assert_eq!(snake_case("FileMoved"), "file_moved");
+        assert_eq!(constant_case("default_port"), "DEFAULT_PORT");
+        assert_eq!(snake_case("Already_Snake"), "already_snake");
+    }
+}
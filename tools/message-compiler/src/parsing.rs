@@ -1,39 +1,180 @@
-#[derive(Debug, PartialEq, Eq)]
-pub struct IdentifierRaw<'input>(pub(crate) &'input str);
+/// A range of byte offsets into the source text, used to point diagnostics at the code they refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    #[must_use]
+    pub const fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+/// The text of a `///` line, without the slashes, the space after them and the line break
+#[must_use]
+pub fn doc_line(line: &str) -> &str {
+    let text = line.trim_start_matches('/').trim_end();
+    text.strip_prefix(' ').unwrap_or(text)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentifierRaw<'input>(pub(crate) &'input str, pub(crate) Span);
 
 impl<'input> IdentifierRaw<'input> {
     #[must_use]
-    pub fn new(name: &'input str) -> Self {
-        Self(name)
+    pub fn new(name: &'input str, span: Span) -> Self {
+        Self(name, span)
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &'input str {
+        self.0
+    }
+
+    #[must_use]
+    pub fn span(&self) -> Span {
+        self.1
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum TypeRaw<'input> {
     Type(IdentifierRaw<'input>),
-    Optional(IdentifierRaw<'input>),
-    Array(IdentifierRaw<'input>),
+    Optional(Box<TypeRaw<'input>>),
+    Array(Box<TypeRaw<'input>>),
+    /// `map<key, value>`, the span covers the whole expression
+    Map(Box<TypeRaw<'input>>, Box<TypeRaw<'input>>, Span),
+}
+
+impl TypeRaw<'_> {
+    /// The span of the identifier the type is built from, or of the whole `map<key, value>`
+    #[must_use]
+    pub fn span(&self) -> Span {
+        match self {
+            TypeRaw::Type(id) => id.span(),
+            TypeRaw::Optional(inner) | TypeRaw::Array(inner) => inner.span(),
+            TypeRaw::Map(_, _, span) => *span,
+        }
+    }
+}
+
+/// A literal value, kept as written in the source until the type it is used for is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiteralRaw<'input> {
+    Integer(&'input str, Span),
+    Float(&'input str, Span),
+    Boolean(bool, Span),
+    /// A number followed by a unit, like `500ms`, `10s`, `5m` or `1h`
+    Duration(&'input str, Span),
+    /// The contents of a string, without the quotes
+    String(&'input str, Span),
+}
+
+impl LiteralRaw<'_> {
+    #[must_use]
+    pub fn span(&self) -> Span {
+        match self {
+            LiteralRaw::Integer(_, span)
+            | LiteralRaw::Float(_, span)
+            | LiteralRaw::Boolean(_, span)
+            | LiteralRaw::Duration(_, span)
+            | LiteralRaw::String(_, span) => *span,
+        }
+    }
+}
+
+/// An annotation like `@deprecated("reason")` on a field, an enum variant or an RPC method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeRaw<'input> {
+    pub(crate) name: IdentifierRaw<'input>,
+    pub(crate) arguments: Vec<LiteralRaw<'input>>,
+    pub(crate) span: Span,
+}
+
+impl<'input> AttributeRaw<'input> {
+    #[must_use]
+    pub fn new(
+        name: IdentifierRaw<'input>,
+        arguments: Vec<LiteralRaw<'input>>,
+        span: Span,
+    ) -> Self {
+        Self {
+            name,
+            arguments,
+            span,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct FieldRaw<'input> {
     pub(crate) name: IdentifierRaw<'input>,
     pub(crate) type_name: TypeRaw<'input>,
+    pub(crate) default: Option<LiteralRaw<'input>>,
+    pub(crate) attributes: Vec<AttributeRaw<'input>>,
+    /// The attributes following the type, like `@pattern("[a-z]+")`, which restrict the valid values
+    pub(crate) constraints: Vec<AttributeRaw<'input>>,
+    /// The lines of the `///` comments before the field
+    pub(crate) docs: Vec<&'input str>,
+    pub(crate) span: Span,
 }
 
 impl<'input> FieldRaw<'input> {
     #[must_use]
-    pub fn new(name: IdentifierRaw<'input>, type_name: TypeRaw<'input>) -> Self {
-        Self { name, type_name }
+    pub fn new(name: IdentifierRaw<'input>, type_name: TypeRaw<'input>, span: Span) -> Self {
+        Self {
+            name,
+            type_name,
+            default: None,
+            attributes: vec![],
+            constraints: vec![],
+            docs: vec![],
+            span,
+        }
+    }
+
+    #[must_use]
+    pub fn with_default(mut self, default: Option<LiteralRaw<'input>>) -> Self {
+        self.default = default;
+        self
+    }
+
+    #[must_use]
+    pub fn with_attributes(mut self, attributes: Vec<AttributeRaw<'input>>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    #[must_use]
+    pub fn with_constraints(mut self, constraints: Vec<AttributeRaw<'input>>) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    #[must_use]
+    pub fn with_docs(mut self, docs: Vec<&'input str>) -> Self {
+        self.docs = docs;
+        self
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct StructDefinitionRaw<'input>(pub IdentifierRaw<'input>, pub Vec<FieldRaw<'input>>);
+pub struct StructDefinitionRaw<'input>(
+    pub IdentifierRaw<'input>,
+    pub Vec<FieldRaw<'input>>,
+    pub Span,
+    /// The lines of the `///` comments before the struct
+    pub Vec<&'input str>,
+    /// The attributes before `struct`, like `@derive("Hash")`
+    pub Vec<AttributeRaw<'input>>,
+);
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct MetadataRaw<'input> {
     fields: Vec<FieldRaw<'input>>,
+    span: Span,
 }
 
 impl<'input> MetadataRaw<'input> {
@@ -41,12 +182,17 @@ impl<'input> MetadataRaw<'input> {
     pub(crate) fn fields(&self) -> &[FieldRaw<'input>] {
         &self.fields
     }
+
+    #[must_use]
+    pub fn span(&self) -> Span {
+        self.span
+    }
 }
 
 impl<'input> MetadataRaw<'input> {
     #[must_use]
-    pub fn new(fields: Vec<FieldRaw<'input>>) -> Self {
-        Self { fields }
+    pub fn new(fields: Vec<FieldRaw<'input>>, span: Span) -> Self {
+        Self { fields, span }
     }
 }
 
@@ -54,12 +200,44 @@ impl<'input> MetadataRaw<'input> {
 pub struct EnumVariantRaw<'input> {
     pub(crate) name: IdentifierRaw<'input>,
     pub(crate) fields: Vec<FieldRaw<'input>>,
+    pub(crate) attributes: Vec<AttributeRaw<'input>>,
+    pub(crate) docs: Vec<&'input str>,
+    /// Written without parentheses, which makes it serialized as just its name
+    pub(crate) unit: bool,
+    pub(crate) span: Span,
 }
 
 impl<'input> EnumVariantRaw<'input> {
     #[must_use]
-    pub fn new(name: IdentifierRaw<'input>, fields: Vec<FieldRaw<'input>>) -> Self {
-        Self { name, fields }
+    pub fn new(name: IdentifierRaw<'input>, fields: Vec<FieldRaw<'input>>, span: Span) -> Self {
+        Self {
+            name,
+            fields,
+            attributes: vec![],
+            docs: vec![],
+            unit: false,
+            span,
+        }
+    }
+
+    #[must_use]
+    pub fn unit(name: IdentifierRaw<'input>, span: Span) -> Self {
+        Self {
+            unit: true,
+            ..Self::new(name, vec![], span)
+        }
+    }
+
+    #[must_use]
+    pub fn with_attributes(mut self, attributes: Vec<AttributeRaw<'input>>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    #[must_use]
+    pub fn with_docs(mut self, docs: Vec<&'input str>) -> Self {
+        self.docs = docs;
+        self
     }
 }
 
@@ -67,12 +245,38 @@ impl<'input> EnumVariantRaw<'input> {
 pub struct EnumDefinitionRaw<'input> {
     pub(crate) name: IdentifierRaw<'input>,
     pub(crate) variants: Vec<EnumVariantRaw<'input>>,
+    /// The attributes before `enum`, like `@tag("type")`
+    pub(crate) attributes: Vec<AttributeRaw<'input>>,
+    pub(crate) docs: Vec<&'input str>,
+    pub(crate) span: Span,
 }
 
 impl<'input> EnumDefinitionRaw<'input> {
     #[must_use]
-    pub fn new(name: IdentifierRaw<'input>, variants: Vec<EnumVariantRaw<'input>>) -> Self {
-        Self { name, variants }
+    pub fn new(
+        name: IdentifierRaw<'input>,
+        variants: Vec<EnumVariantRaw<'input>>,
+        span: Span,
+    ) -> Self {
+        Self {
+            name,
+            variants,
+            attributes: vec![],
+            docs: vec![],
+            span,
+        }
+    }
+
+    #[must_use]
+    pub fn with_attributes(mut self, attributes: Vec<AttributeRaw<'input>>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    #[must_use]
+    pub fn with_docs(mut self, docs: Vec<&'input str>) -> Self {
+        self.docs = docs;
+        self
     }
 }
 
@@ -81,56 +285,211 @@ pub enum RpcDefinitionRaw<'input> {
     Stream {
         name: IdentifierRaw<'input>,
         request: TypeRaw<'input>,
+        request_stream: bool,
         response: TypeRaw<'input>,
+        attributes: Vec<AttributeRaw<'input>>,
+        docs: Vec<&'input str>,
+        span: Span,
     },
     Unary {
         name: IdentifierRaw<'input>,
         request: TypeRaw<'input>,
+        request_stream: bool,
         response: TypeRaw<'input>,
+        attributes: Vec<AttributeRaw<'input>>,
+        docs: Vec<&'input str>,
+        span: Span,
     },
 }
 
+impl<'input> RpcDefinitionRaw<'input> {
+    #[must_use]
+    pub fn name(&self) -> &IdentifierRaw<'input> {
+        match self {
+            RpcDefinitionRaw::Stream { name, .. } | RpcDefinitionRaw::Unary { name, .. } => name,
+        }
+    }
+
+    #[must_use]
+    pub fn span(&self) -> Span {
+        match self {
+            RpcDefinitionRaw::Stream { span, .. } | RpcDefinitionRaw::Unary { span, .. } => *span,
+        }
+    }
+
+    #[must_use]
+    pub fn attributes(&self) -> &[AttributeRaw<'input>] {
+        match self {
+            RpcDefinitionRaw::Stream { attributes, .. }
+            | RpcDefinitionRaw::Unary { attributes, .. } => attributes,
+        }
+    }
+
+    #[must_use]
+    pub fn docs(&self) -> &[&'input str] {
+        match self {
+            RpcDefinitionRaw::Stream { docs, .. } | RpcDefinitionRaw::Unary { docs, .. } => docs,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct RpcRaw<'input> {
     pub(crate) definitions: Vec<RpcDefinitionRaw<'input>>,
+    pub(crate) span: Span,
 }
 
 impl<'input> RpcRaw<'input> {
     #[must_use]
-    pub fn new(definitions: Vec<RpcDefinitionRaw<'input>>) -> Self {
-        Self { definitions }
+    pub fn new(definitions: Vec<RpcDefinitionRaw<'input>>, span: Span) -> Self {
+        Self { definitions, span }
+    }
+}
+
+/// A `service Name { ... }` block, whose methods are called as `Name.method`
+#[derive(Debug, PartialEq, Eq)]
+pub struct ServiceRaw<'input> {
+    pub(crate) name: IdentifierRaw<'input>,
+    pub(crate) rpc: RpcRaw<'input>,
+    pub(crate) docs: Vec<&'input str>,
+    pub(crate) span: Span,
+}
+
+impl<'input> ServiceRaw<'input> {
+    #[must_use]
+    pub fn new(name: IdentifierRaw<'input>, rpc: RpcRaw<'input>, span: Span) -> Self {
+        Self {
+            name,
+            rpc,
+            docs: vec![],
+            span,
+        }
+    }
+
+    #[must_use]
+    pub fn with_docs(mut self, docs: Vec<&'input str>) -> Self {
+        self.docs = docs;
+        self
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImportRaw<'input> {
+    pub(crate) path: &'input str,
+    pub(crate) path_span: Span,
+    pub(crate) names: Vec<IdentifierRaw<'input>>,
+    pub(crate) span: Span,
+}
+
+impl<'input> ImportRaw<'input> {
+    #[must_use]
+    pub fn new(
+        path: &'input str,
+        path_span: Span,
+        names: Vec<IdentifierRaw<'input>>,
+        span: Span,
+    ) -> Self {
+        Self {
+            path,
+            path_span,
+            names,
+            span,
+        }
+    }
+}
+
+/// A `const NAME: type = value;` declaration
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConstantRaw<'input> {
+    pub(crate) name: IdentifierRaw<'input>,
+    pub(crate) type_name: TypeRaw<'input>,
+    pub(crate) value: LiteralRaw<'input>,
+    pub(crate) docs: Vec<&'input str>,
+    pub(crate) span: Span,
+}
+
+impl<'input> ConstantRaw<'input> {
+    #[must_use]
+    pub fn new(
+        name: IdentifierRaw<'input>,
+        type_name: TypeRaw<'input>,
+        value: LiteralRaw<'input>,
+        span: Span,
+    ) -> Self {
+        Self {
+            name,
+            type_name,
+            value,
+            docs: vec![],
+            span,
+        }
+    }
+
+    #[must_use]
+    pub fn with_docs(mut self, docs: Vec<&'input str>) -> Self {
+        self.docs = docs;
+        self
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct FileRaw<'input> {
+    imports: Vec<ImportRaw<'input>>,
     metadata: Option<MetadataRaw<'input>>,
+    constants: Vec<ConstantRaw<'input>>,
     structs: Vec<StructDefinitionRaw<'input>>,
     enums: Vec<EnumDefinitionRaw<'input>>,
     rpc: Option<RpcRaw<'input>>,
+    services: Vec<ServiceRaw<'input>>,
 }
 
 impl<'input> FileRaw<'input> {
     #[must_use]
     pub fn new(
+        imports: Vec<ImportRaw<'input>>,
         metadata: Option<MetadataRaw<'input>>,
         structs: Vec<StructDefinitionRaw<'input>>,
         enums: Vec<EnumDefinitionRaw<'input>>,
         rpc: Option<RpcRaw<'input>>,
     ) -> Self {
         Self {
+            imports,
             metadata,
+            constants: vec![],
             structs,
             enums,
             rpc,
+            services: vec![],
         }
     }
 
+    #[must_use]
+    pub fn with_constants(mut self, constants: Vec<ConstantRaw<'input>>) -> Self {
+        self.constants = constants;
+        self
+    }
+
+    #[must_use]
+    pub fn with_services(mut self, services: Vec<ServiceRaw<'input>>) -> Self {
+        self.services = services;
+        self
+    }
+
+    #[must_use]
+    pub fn imports(&self) -> &[ImportRaw<'input>] {
+        &self.imports
+    }
+
     #[must_use]
     pub fn metadata(&self) -> Option<&MetadataRaw<'input>> {
         self.metadata.as_ref()
     }
 
+    #[must_use]
+    pub fn constants(&self) -> &[ConstantRaw<'input>] {
+        &self.constants
+    }
+
     #[must_use]
     pub fn structs(&self) -> &[StructDefinitionRaw<'input>] {
         &self.structs
@@ -145,6 +504,11 @@ impl<'input> FileRaw<'input> {
     pub fn rpc(&self) -> Option<&RpcRaw<'input>> {
         self.rpc.as_ref()
     }
+
+    #[must_use]
+    pub fn services(&self) -> &[ServiceRaw<'input>] {
+        &self.services
+    }
 }
 
 lalrpop_util::lalrpop_mod!(
@@ -163,6 +527,10 @@ mod test {
     use super::*;
     use crate::parsing;
 
+    fn id(name: &str, start: usize) -> IdentifierRaw<'_> {
+        IdentifierRaw::new(name, Span::new(start, start + name.len()))
+    }
+
     #[test]
     pub fn can_parse_empty_struct() {
         let input = "struct A {}";
@@ -170,8 +538,15 @@ mod test {
 
         assert_eq!(
             Ok(FileRaw::new(
+                vec![],
                 None,
-                vec![StructDefinitionRaw(IdentifierRaw::new("A"), vec![])],
+                vec![StructDefinitionRaw(
+                    id("A", 7),
+                    vec![],
+                    Span::new(0, 11),
+                    vec![],
+                    vec![]
+                )],
                 vec![],
                 None
             )),
@@ -186,46 +561,62 @@ mod test {
 
         assert_eq!(
             Ok(FileRaw::new(
+                vec![],
                 None,
                 vec![
                     StructDefinitionRaw(
-                        IdentifierRaw::new("A"),
+                        id("A", 7),
                         vec![
                             FieldRaw::new(
-                                IdentifierRaw::new("f1"),
-                                TypeRaw::Type(IdentifierRaw::new("u32"))
+                                id("f1", 11),
+                                TypeRaw::Type(id("u32", 15)),
+                                Span::new(11, 18)
                             ),
                             FieldRaw::new(
-                                IdentifierRaw::new("f2"),
-                                TypeRaw::Type(IdentifierRaw::new("u64"))
+                                id("f2", 20),
+                                TypeRaw::Type(id("u64", 24)),
+                                Span::new(20, 27)
                             ),
-                        ]
+                        ],
+                        Span::new(0, 28),
+                        vec![],
+                        vec![]
                     ),
                     StructDefinitionRaw(
-                        IdentifierRaw::new("B"),
+                        id("B", 36),
                         vec![
                             FieldRaw::new(
-                                IdentifierRaw::new("fx-1"),
-                                TypeRaw::Type(IdentifierRaw::new("A"))
+                                id("fx-1", 40),
+                                TypeRaw::Type(id("A", 46)),
+                                Span::new(40, 47)
                             ),
                             FieldRaw::new(
-                                IdentifierRaw::new("fx-2"),
-                                TypeRaw::Type(IdentifierRaw::new("instant"))
+                                id("fx-2", 49),
+                                TypeRaw::Type(id("instant", 55)),
+                                Span::new(49, 62)
                             ),
-                        ]
+                        ],
+                        Span::new(29, 64),
+                        vec![],
+                        vec![]
                     ),
                     StructDefinitionRaw(
-                        IdentifierRaw::new("CoolStruct29"),
+                        id("CoolStruct29", 72),
                         vec![
                             FieldRaw::new(
-                                IdentifierRaw::new("fx-1"),
-                                TypeRaw::Type(IdentifierRaw::new("B"))
+                                id("fx-1", 86),
+                                TypeRaw::Type(id("B", 92)),
+                                Span::new(86, 93)
                             ),
                             FieldRaw::new(
-                                IdentifierRaw::new("fx-3"),
-                                TypeRaw::Type(IdentifierRaw::new("u8"))
+                                id("fx-3", 95),
+                                TypeRaw::Type(id("u8", 100)),
+                                Span::new(95, 102)
                             ),
-                        ]
+                        ],
+                        Span::new(65, 104),
+                        vec![],
+                        vec![]
                     ),
                 ],
                 vec![],
@@ -242,29 +633,45 @@ mod test {
 
         assert_eq!(
             Ok(FileRaw::new(
+                vec![],
                 None,
                 vec![
                     StructDefinitionRaw(
-                        IdentifierRaw::new("request"),
+                        id("request", 7),
                         vec![FieldRaw::new(
-                            IdentifierRaw::new("f1"),
-                            TypeRaw::Type(IdentifierRaw::new("u32"))
-                        ),]
+                            id("f1", 17),
+                            TypeRaw::Type(id("u32", 21)),
+                            Span::new(17, 24)
+                        ),],
+                        Span::new(0, 26),
+                        vec![],
+                        vec![]
                     ),
                     StructDefinitionRaw(
-                        IdentifierRaw::new("response"),
+                        id("response", 34),
                         vec![FieldRaw::new(
-                            IdentifierRaw::new("f2"),
-                            TypeRaw::Type(IdentifierRaw::new("u64"))
-                        ),]
+                            id("f2", 45),
+                            TypeRaw::Type(id("u64", 49)),
+                            Span::new(45, 52)
+                        ),],
+                        Span::new(27, 54),
+                        vec![],
+                        vec![]
                     ),
                 ],
                 vec![],
-                Some(RpcRaw::new(vec![RpcDefinitionRaw::Unary {
-                    name: IdentifierRaw::new("call"),
-                    request: TypeRaw::Type(IdentifierRaw::new("request")),
-                    response: TypeRaw::Type(IdentifierRaw::new("response"))
-                }]))
+                Some(RpcRaw::new(
+                    vec![RpcDefinitionRaw::Unary {
+                        name: id("call", 61),
+                        request: TypeRaw::Type(id("request", 66)),
+                        request_stream: false,
+                        response: TypeRaw::Type(id("response", 78)),
+                        attributes: vec![],
+                        docs: vec![],
+                        span: Span::new(61, 86),
+                    }],
+                    Span::new(55, 89)
+                ))
             )),
             r
         );
@@ -277,31 +684,272 @@ mod test {
 
         assert_eq!(
             Ok(FileRaw::new(
+                vec![],
                 None,
                 vec![
                     StructDefinitionRaw(
-                        IdentifierRaw::new("request"),
+                        id("request", 7),
                         vec![FieldRaw::new(
-                            IdentifierRaw::new("f1"),
-                            TypeRaw::Type(IdentifierRaw::new("u32"))
-                        ),]
+                            id("f1", 17),
+                            TypeRaw::Type(id("u32", 21)),
+                            Span::new(17, 24)
+                        ),],
+                        Span::new(0, 26),
+                        vec![],
+                        vec![]
                     ),
                     StructDefinitionRaw(
-                        IdentifierRaw::new("response"),
+                        id("response", 34),
                         vec![FieldRaw::new(
-                            IdentifierRaw::new("f2"),
-                            TypeRaw::Type(IdentifierRaw::new("u64"))
-                        ),]
+                            id("f2", 45),
+                            TypeRaw::Type(id("u64", 49)),
+                            Span::new(45, 52)
+                        ),],
+                        Span::new(27, 54),
+                        vec![],
+                        vec![]
                     ),
                 ],
                 vec![],
-                Some(RpcRaw::new(vec![RpcDefinitionRaw::Stream {
-                    name: IdentifierRaw::new("call"),
-                    request: TypeRaw::Type(IdentifierRaw::new("request")),
-                    response: TypeRaw::Type(IdentifierRaw::new("response")),
-                }]))
+                Some(RpcRaw::new(
+                    vec![RpcDefinitionRaw::Stream {
+                        name: id("call", 61),
+                        request: TypeRaw::Type(id("request", 66)),
+                        request_stream: false,
+                        response: TypeRaw::Type(id("response", 85)),
+                        attributes: vec![],
+                        docs: vec![],
+                        span: Span::new(61, 93),
+                    }],
+                    Span::new(55, 96)
+                ))
+            )),
+            r
+        );
+    }
+
+    #[test]
+    pub fn can_parse_imports() {
+        let input = "import \"events.evd\" { FileOnMountPath, Event, } struct A {}";
+        let r = parsing::grammar::RFileParser::new().parse(input);
+
+        assert_eq!(
+            Ok(FileRaw::new(
+                vec![ImportRaw::new(
+                    "events.evd",
+                    Span::new(7, 19),
+                    vec![id("FileOnMountPath", 22), id("Event", 39)],
+                    Span::new(0, 47)
+                )],
+                None,
+                vec![StructDefinitionRaw(
+                    id("A", 55),
+                    vec![],
+                    Span::new(48, 59),
+                    vec![],
+                    vec![]
+                )],
+                vec![],
+                None
             )),
             r
         );
     }
+
+    #[test]
+    pub fn type_spans_cover_the_identifier() {
+        let input = "struct A { f1: B? }";
+        let r = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(r.structs()[0].1[0].type_name.span(), Span::new(15, 16));
+        assert_eq!(r.structs()[0].1[0].span, Span::new(11, 17));
+    }
+
+    #[test]
+    pub fn parses_nested_types() {
+        let input = "struct A { f1: map<string, B[]?>[], f2: u8[][] }";
+        let r = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            r.structs()[0].1[0].type_name,
+            TypeRaw::Array(Box::new(TypeRaw::Map(
+                Box::new(TypeRaw::Type(id("string", 19))),
+                Box::new(TypeRaw::Optional(Box::new(TypeRaw::Array(Box::new(
+                    TypeRaw::Type(id("B", 27))
+                ))))),
+                Span::new(15, 32)
+            )))
+        );
+        assert_eq!(r.structs()[0].1[0].type_name.span(), Span::new(15, 32));
+        assert_eq!(
+            r.structs()[0].1[1].type_name,
+            TypeRaw::Array(Box::new(TypeRaw::Array(Box::new(TypeRaw::Type(id(
+                "u8", 40
+            ))))))
+        );
+    }
+
+    #[test]
+    pub fn parses_constraints_after_the_type() {
+        let input =
+            "struct A { @deprecated(\"x\") f1: string? @pattern(\"[a-z]+\") @non_empty = \"a\" }";
+        let r = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let field = &r.structs()[0].1[0];
+
+        assert_eq!(field.attributes.len(), 1);
+        assert_eq!(
+            field.constraints,
+            vec![
+                AttributeRaw::new(
+                    id("pattern", 41),
+                    vec![LiteralRaw::String("[a-z]+", Span::new(49, 57))],
+                    Span::new(40, 58)
+                ),
+                AttributeRaw::new(id("non_empty", 60), vec![], Span::new(59, 69)),
+            ]
+        );
+        assert!(field.default.is_some());
+    }
+
+    #[test]
+    pub fn parses_constants_and_unit_variants() {
+        let input = "const PORT: u16 = 7654; struct A {} @tag(\"type\") enum E { X, Y() }";
+        let r = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            r.constants(),
+            [ConstantRaw::new(
+                id("PORT", 6),
+                TypeRaw::Type(id("u16", 12)),
+                LiteralRaw::Integer("7654", Span::new(18, 22)),
+                Span::new(0, 23)
+            )]
+        );
+        assert_eq!(
+            r.enums()[0].attributes,
+            vec![AttributeRaw::new(
+                id("tag", 37),
+                vec![LiteralRaw::String("type", Span::new(41, 47))],
+                Span::new(36, 48)
+            )]
+        );
+        assert!(r.enums()[0].variants[0].unit);
+        assert!(!r.enums()[0].variants[1].unit);
+    }
+
+    #[test]
+    pub fn parses_docs() {
+        let input = "/// A struct\r\n///\r\n/// with  spaces \r\nstruct A {\n    /// A field\n    // not a doc\n    f1: u8\n}\n//// not a doc either\nstruct B {}";
+        let r = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(r.structs()[0].3, vec!["A struct", "", "with  spaces"]);
+        assert_eq!(r.structs()[0].1[0].docs, vec!["A field"]);
+        assert!(r.structs()[1].3.is_empty());
+    }
+
+    #[test]
+    pub fn parses_struct_attributes() {
+        let input = "struct A {} /// B\n@non_exhaustive @derive(\"Eq\", \"Hash\") struct B {} @derive(\"Hash\") enum E {}";
+        let r = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert!(r.structs()[0].4.is_empty());
+        assert_eq!(r.structs()[1].3, vec!["B"]);
+        assert_eq!(
+            r.structs()[1].4,
+            vec![
+                AttributeRaw::new(id("non_exhaustive", 19), vec![], Span::new(18, 33)),
+                AttributeRaw::new(
+                    id("derive", 35),
+                    vec![
+                        LiteralRaw::String("Eq", Span::new(42, 46)),
+                        LiteralRaw::String("Hash", Span::new(48, 54))
+                    ],
+                    Span::new(34, 55)
+                )
+            ]
+        );
+        assert_eq!(r.enums()[0].attributes.len(), 1);
+    }
+
+    #[test]
+    pub fn parses_services() {
+        let input = "struct A {} /// The library\nservice Library { tracks(void) -> stream A; } service Playback {}";
+        let r = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert!(r.rpc().is_none());
+        assert_eq!(
+            r.services(),
+            [
+                ServiceRaw::new(
+                    id("Library", 36),
+                    RpcRaw::new(
+                        vec![RpcDefinitionRaw::Stream {
+                            name: id("tracks", 46),
+                            request: TypeRaw::Type(id("void", 53)),
+                            request_stream: false,
+                            response: TypeRaw::Type(id("A", 69)),
+                            attributes: vec![],
+                            docs: vec![],
+                            span: Span::new(46, 70),
+                        }],
+                        Span::new(28, 73)
+                    ),
+                    Span::new(28, 73)
+                )
+                .with_docs(vec!["The library"]),
+                ServiceRaw::new(
+                    id("Playback", 82),
+                    RpcRaw::new(vec![], Span::new(74, 93)),
+                    Span::new(74, 93)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn parses_request_streams() {
+        let input = "struct A {} rpc { upload(stream A) -> A; chat(stream A) -> stream A; }";
+        let r = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        assert_eq!(
+            r.rpc().unwrap().definitions,
+            [
+                RpcDefinitionRaw::Unary {
+                    name: id("upload", 18),
+                    request: TypeRaw::Type(id("A", 32)),
+                    request_stream: true,
+                    response: TypeRaw::Type(id("A", 38)),
+                    attributes: vec![],
+                    docs: vec![],
+                    span: Span::new(18, 39),
+                },
+                RpcDefinitionRaw::Stream {
+                    name: id("chat", 41),
+                    request: TypeRaw::Type(id("A", 53)),
+                    request_stream: true,
+                    response: TypeRaw::Type(id("A", 66)),
+                    attributes: vec![],
+                    docs: vec![],
+                    span: Span::new(41, 67),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn parses_durations() {
+        let input = "struct A {} rpc { @idempotent @timeout(10s) get(A) -> A; @timeout(500ms) set(A) -> A; }";
+        let r = parsing::grammar::RFileParser::new().parse(input).unwrap();
+        let definitions = &r.rpc().unwrap().definitions;
+
+        assert_eq!(definitions[0].attributes()[0].arguments, []);
+        assert_eq!(
+            definitions[0].attributes()[1].arguments,
+            [LiteralRaw::Duration("10s", Span::new(39, 42))]
+        );
+        assert_eq!(
+            definitions[1].attributes()[0].arguments,
+            [LiteralRaw::Duration("500ms", Span::new(66, 71))]
+        );
+    }
 }
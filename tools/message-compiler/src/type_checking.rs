@@ -1,46 +1,388 @@
+use crate::naming::snake_case;
 use crate::parsing::{
-    EnumDefinitionRaw, EnumVariantRaw, FieldRaw, FileRaw, IdentifierRaw, StructDefinitionRaw,
-    TypeRaw,
+    AttributeRaw, ConstantRaw, EnumDefinitionRaw, EnumVariantRaw, FieldRaw, FileRaw, IdentifierRaw,
+    ImportRaw, LiteralRaw, RpcRaw, Span, StructDefinitionRaw, TypeRaw,
 };
-use petgraph::algo::toposort;
-use petgraph::graph::DiGraph;
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
 
 #[derive(Debug)]
 pub enum TypeCheckError {
-    RepeatedName(String),
+    RepeatedName {
+        name: String,
+        span: Span,
+    },
     RepeatedFieldName {
         field_name: String,
         struct_name: String,
+        span: Span,
+    },
+    StructNotFound {
+        name: String,
+        span: Span,
+    },
+    UnknownTypeInWrapper {
+        name: String,
+        wrapped: String,
+        span: Span,
+    },
+    UnknownRpcType {
+        name: String,
+        method_name: String,
+        span: Span,
+    },
+    CyclicDefinition {
+        cycle: Vec<String>,
+        span: Span,
+    },
+    RepeatedEnumName {
+        name: String,
+        span: Span,
+    },
+    StructEnumNameConflict {
+        name: String,
+        span: Span,
+    },
+    RepeatedVariantName {
+        variant_name: String,
+        enum_name: String,
+        span: Span,
+    },
+    RepeatedRpcName {
+        name: String,
+        span: Span,
+    },
+    RepeatedServiceName {
+        name: String,
+        span: Span,
+    },
+    RpcOutsideOfService {
+        span: Span,
+    },
+    ReservedIdentifier {
+        name: String,
+        span: Span,
+    },
+    InvalidIdentifier {
+        name: String,
+        span: Span,
+    },
+    UnknownImport {
+        path: String,
+        span: Span,
+    },
+    ImportedTypeNotFound {
+        name: String,
+        path: String,
+        span: Span,
+    },
+    UnknownAttribute {
+        name: String,
+        span: Span,
+    },
+    RepeatedAttribute {
+        name: String,
+        span: Span,
+    },
+    InvalidAttributeArguments {
+        name: String,
+        expected: &'static str,
+        span: Span,
     },
-    StructNotFound(String),
+    InvalidDefault {
+        field_name: String,
+        type_name: String,
+        span: Span,
+    },
+    InvalidMapKey {
+        type_name: String,
+        span: Span,
+    },
+    UnsupportedRpcType {
+        type_name: String,
+        method_name: String,
+        span: Span,
+    },
+    VoidRequestStream {
+        method_name: String,
+        span: Span,
+    },
+    UnsupportedMethodOption {
+        name: String,
+        method_name: String,
+        reason: &'static str,
+        span: Span,
+    },
+    UnsupportedConstraint {
+        name: String,
+        type_name: String,
+        span: Span,
+    },
+    MisplacedConstraint {
+        name: String,
+        span: Span,
+    },
+    InvalidPattern {
+        pattern: String,
+        span: Span,
+    },
+    UnsatisfiableConstraints {
+        field_name: String,
+        span: Span,
+    },
+    TagConflict {
+        tag: String,
+        variant_name: String,
+        span: Span,
+    },
+    RepeatedConstantName {
+        name: String,
+        span: Span,
+    },
+    UnsupportedConstantType {
+        name: String,
+        type_name: String,
+        span: Span,
+    },
+    InvalidConstantValue {
+        name: String,
+        type_name: String,
+        span: Span,
+    },
+    UnsupportedDerive {
+        derive: String,
+        name: String,
+        span: Span,
+    },
+    MissingDerive {
+        derive: String,
+        name: String,
+        field_name: String,
+        type_name: String,
+        span: Span,
+    },
+}
+
+impl TypeCheckError {
+    #[must_use]
+    pub fn span(&self) -> Span {
+        match self {
+            TypeCheckError::RepeatedName { span, .. }
+            | TypeCheckError::RepeatedFieldName { span, .. }
+            | TypeCheckError::StructNotFound { span, .. }
+            | TypeCheckError::UnknownTypeInWrapper { span, .. }
+            | TypeCheckError::UnknownRpcType { span, .. }
+            | TypeCheckError::CyclicDefinition { span, .. }
+            | TypeCheckError::RepeatedEnumName { span, .. }
+            | TypeCheckError::StructEnumNameConflict { span, .. }
+            | TypeCheckError::RepeatedVariantName { span, .. }
+            | TypeCheckError::RepeatedRpcName { span, .. }
+            | TypeCheckError::RepeatedServiceName { span, .. }
+            | TypeCheckError::RpcOutsideOfService { span }
+            | TypeCheckError::ReservedIdentifier { span, .. }
+            | TypeCheckError::InvalidIdentifier { span, .. }
+            | TypeCheckError::UnknownImport { span, .. }
+            | TypeCheckError::ImportedTypeNotFound { span, .. }
+            | TypeCheckError::UnknownAttribute { span, .. }
+            | TypeCheckError::RepeatedAttribute { span, .. }
+            | TypeCheckError::InvalidAttributeArguments { span, .. }
+            | TypeCheckError::InvalidDefault { span, .. }
+            | TypeCheckError::InvalidMapKey { span, .. }
+            | TypeCheckError::UnsupportedRpcType { span, .. }
+            | TypeCheckError::VoidRequestStream { span, .. }
+            | TypeCheckError::UnsupportedMethodOption { span, .. }
+            | TypeCheckError::UnsupportedConstraint { span, .. }
+            | TypeCheckError::MisplacedConstraint { span, .. }
+            | TypeCheckError::InvalidPattern { span, .. }
+            | TypeCheckError::UnsatisfiableConstraints { span, .. }
+            | TypeCheckError::TagConflict { span, .. }
+            | TypeCheckError::RepeatedConstantName { span, .. }
+            | TypeCheckError::UnsupportedConstantType { span, .. }
+            | TypeCheckError::InvalidConstantValue { span, .. }
+            | TypeCheckError::UnsupportedDerive { span, .. }
+            | TypeCheckError::MissingDerive { span, .. } => *span,
+        }
+    }
 }
 
 impl Display for TypeCheckError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            TypeCheckError::RepeatedName(name) => {
+            TypeCheckError::RepeatedName { name, .. } => {
                 write!(f, "The type with name \"{name}\" already exists")
             }
             TypeCheckError::RepeatedFieldName {
                 field_name,
                 struct_name,
+                ..
             } => write!(
                 f,
                 "A field with name \"{field_name}\" already exists in struct \"{struct_name}\""
             ),
-            TypeCheckError::StructNotFound(name) => {
+            TypeCheckError::StructNotFound { name, .. } => {
                 write!(f, "A struct with name \"{name}\" does not exist")
             }
+            TypeCheckError::UnknownTypeInWrapper { name, wrapped, .. } => {
+                write!(
+                    f,
+                    "The type \"{name}\" used in \"{wrapped}\" does not exist"
+                )
+            }
+            TypeCheckError::UnknownRpcType {
+                name, method_name, ..
+            } => write!(
+                f,
+                "The type \"{name}\" used by RPC method \"{method_name}\" does not exist"
+            ),
+            TypeCheckError::CyclicDefinition { cycle, .. } => write!(
+                f,
+                "The definition of \"{}\" is cyclic ({}), use \"?\" or \"[]\" to break the cycle",
+                cycle.first().map_or("", String::as_str),
+                cycle.join(" -> ")
+            ),
+            TypeCheckError::RepeatedEnumName { name, .. } => {
+                write!(f, "An enum with name \"{name}\" already exists")
+            }
+            TypeCheckError::StructEnumNameConflict { name, .. } => {
+                write!(f, "The enum \"{name}\" has the same name as a struct")
+            }
+            TypeCheckError::RepeatedVariantName {
+                variant_name,
+                enum_name,
+                ..
+            } => write!(
+                f,
+                "A variant with name \"{variant_name}\" already exists in enum \"{enum_name}\""
+            ),
+            TypeCheckError::RepeatedRpcName { name, .. } => {
+                write!(f, "An RPC method with name \"{name}\" already exists")
+            }
+            TypeCheckError::RepeatedServiceName { name, .. } => {
+                write!(f, "A service with name \"{name}\" already exists")
+            }
+            TypeCheckError::RpcOutsideOfService { .. } => write!(
+                f,
+                "An \"rpc\" block cannot be used together with services, move its methods into a service"
+            ),
+            TypeCheckError::ReservedIdentifier { name, .. } => {
+                write!(f, "\"{name}\" is a reserved keyword and cannot be used as a name")
+            }
+            TypeCheckError::InvalidIdentifier { name, .. } => write!(
+                f,
+                "\"{name}\" is not a valid name, names must start with a letter or \"_\" and cannot contain \"-\""
+            ),
+            TypeCheckError::UnknownImport { path, .. } => {
+                write!(f, "The file \"{path}\" is not available for import")
+            }
+            TypeCheckError::ImportedTypeNotFound { name, path, .. } => {
+                write!(f, "The type \"{name}\" does not exist in \"{path}\"")
+            }
+            TypeCheckError::UnknownAttribute { name, .. } => {
+                write!(f, "The attribute \"@{name}\" does not exist")
+            }
+            TypeCheckError::RepeatedAttribute { name, .. } => {
+                write!(f, "The attribute \"@{name}\" is used more than once")
+            }
+            TypeCheckError::InvalidAttributeArguments { name, expected, .. } => {
+                write!(f, "The attribute \"@{name}\" expects {expected}")
+            }
+            TypeCheckError::InvalidDefault {
+                field_name,
+                type_name,
+                ..
+            } => write!(
+                f,
+                "The default value of the field \"{field_name}\" is not a valid {type_name}"
+            ),
+            TypeCheckError::InvalidMapKey { type_name, .. } => write!(
+                f,
+                "\"{type_name}\" cannot be used as a map key, only strings, integers and guids can"
+            ),
+            TypeCheckError::UnsupportedRpcType {
+                type_name,
+                method_name,
+                ..
+            } => write!(
+                f,
+                "The type \"{type_name}\" cannot be used directly by RPC method \"{method_name}\", wrap it in a struct"
+            ),
+            TypeCheckError::VoidRequestStream { method_name, .. } => write!(
+                f,
+                "The RPC method \"{method_name}\" cannot take a stream of void requests"
+            ),
+            TypeCheckError::UnsupportedMethodOption {
+                name,
+                method_name,
+                reason,
+                ..
+            } => write!(
+                f,
+                "The attribute \"@{name}\" cannot be used on the RPC method \"{method_name}\", {reason}"
+            ),
+            TypeCheckError::UnsupportedConstraint {
+                name, type_name, ..
+            } => write!(
+                f,
+                "The constraint \"@{name}\" cannot be used on a field of type \"{type_name}\""
+            ),
+            TypeCheckError::MisplacedConstraint { name, .. } => write!(
+                f,
+                "The constraint \"@{name}\" has to follow the type of a field in a struct or an enum"
+            ),
+            TypeCheckError::InvalidPattern { pattern, .. } => {
+                write!(f, "\"{pattern}\" is not a valid regular expression")
+            }
+            TypeCheckError::UnsatisfiableConstraints { field_name, .. } => write!(
+                f,
+                "The constraints of the field \"{field_name}\" cannot be satisfied by any value"
+            ),
+            TypeCheckError::TagConflict {
+                tag, variant_name, ..
+            } => write!(
+                f,
+                "The variant \"{variant_name}\" has a field with the same name as the tag \"{tag}\""
+            ),
+            TypeCheckError::RepeatedConstantName { name, .. } => {
+                write!(f, "A constant with name \"{name}\" already exists")
+            }
+            TypeCheckError::UnsupportedConstantType {
+                name, type_name, ..
+            } => write!(
+                f,
+                "The constant \"{name}\" cannot be of type \"{type_name}\", only numbers, booleans and strings can be constants"
+            ),
+            TypeCheckError::InvalidConstantValue {
+                name, type_name, ..
+            } => write!(
+                f,
+                "The value of the constant \"{name}\" is not a valid {type_name}"
+            ),
+            TypeCheckError::UnsupportedDerive { derive, name, .. } => {
+                write!(f, "\"{derive}\" cannot be derived for the enum \"{name}\"")
+            }
+            TypeCheckError::MissingDerive {
+                derive,
+                name,
+                field_name,
+                type_name,
+                ..
+            } => write!(
+                f,
+                "\"{name}\" cannot derive \"{derive}\", as the field \"{field_name}\" of type \"{type_name}\" does not implement it"
+            ),
         }
     }
 }
 
 impl Error for TypeCheckError {}
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum TypedFieldType {
     U8,
     U16,
@@ -50,7 +392,15 @@ pub enum TypedFieldType {
     S16,
     S32,
     S64,
+    F32,
+    F64,
+    Bool,
+    /// A decimal number, which is exact where a float would be rounded
+    Decimal,
     Instant,
+    Duration,
+    /// A calendar date, without a time or a time zone
+    Date,
     Guid,
     String,
     Void,
@@ -59,12 +409,98 @@ pub enum TypedFieldType {
     Enum(String),
     Optional(Box<TypedFieldType>),
     Array(Box<TypedFieldType>),
+    /// A map from the first type to the second one, serialized as an object, so the keys are always strings
+    Map(Box<TypedFieldType>, Box<TypedFieldType>),
+    /// A reference to a definition that (possibly indirectly) contains the referencing one,
+    /// which has to be stored behind a pointer for the definition to have a finite size.
+    Indirect(Box<TypedFieldType>),
+    /// A struct or an enum imported from another file, whose generated code lives in `module`.
+    Imported {
+        module: String,
+        name: String,
+    },
+}
+
+/// Prints the type the way it is written in the source.
+impl Display for TypedFieldType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypedFieldType::U8 => write!(f, "u8"),
+            TypedFieldType::U16 => write!(f, "u16"),
+            TypedFieldType::U32 => write!(f, "u32"),
+            TypedFieldType::U64 => write!(f, "u64"),
+            TypedFieldType::S8 => write!(f, "s8"),
+            TypedFieldType::S16 => write!(f, "s16"),
+            TypedFieldType::S32 => write!(f, "s32"),
+            TypedFieldType::S64 => write!(f, "s64"),
+            TypedFieldType::F32 => write!(f, "f32"),
+            TypedFieldType::F64 => write!(f, "f64"),
+            TypedFieldType::Bool => write!(f, "bool"),
+            TypedFieldType::Decimal => write!(f, "decimal"),
+            TypedFieldType::Instant => write!(f, "instant"),
+            TypedFieldType::Duration => write!(f, "duration"),
+            TypedFieldType::Date => write!(f, "date"),
+            TypedFieldType::Guid => write!(f, "guid"),
+            TypedFieldType::String => write!(f, "string"),
+            TypedFieldType::Void => write!(f, "void"),
+            TypedFieldType::Binary => write!(f, "binary"),
+            TypedFieldType::OtherStruct(name)
+            | TypedFieldType::Enum(name)
+            | TypedFieldType::Imported { name, .. } => write!(f, "{name}"),
+            TypedFieldType::Optional(type_) => write!(f, "{type_}?"),
+            TypedFieldType::Array(type_) => write!(f, "{type_}[]"),
+            TypedFieldType::Map(key, value) => write!(f, "map<{key}, {value}>"),
+            TypedFieldType::Indirect(type_) => write!(f, "{type_}"),
+        }
+    }
+}
+
+/// A value written in a message definition: the default value of a field, which is used when the
+/// field is missing from the serialized data, or the value of a constant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedValue {
+    Integer(i128),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+}
+
+/// The constraints written after the type of a field, which the values of the field have to satisfy.
+/// They apply to the value of an optional field when it is present.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TypedConstraints {
+    /// `@pattern("...")`, a regular expression that has to match the whole string
+    pub pattern: Option<String>,
+    /// `@min_length(n)` or `@non_empty`, in characters for strings, bytes for binary and entries for
+    /// arrays and maps
+    pub min_length: Option<u64>,
+    /// `@max_length(n)`
+    pub max_length: Option<u64>,
+    /// `@min(n)`, inclusive
+    pub min: Option<TypedValue>,
+    /// `@max(n)`, inclusive
+    pub max: Option<TypedValue>,
+}
+
+impl TypedConstraints {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
 }
 
 #[derive(Debug)]
 pub struct TypedField {
     pub name: String,
+    /// The name in the serialized data, which differs from `name` with `@rename_all`
+    pub serialized_name: String,
     pub type_id: TypedFieldType,
+    pub default: Option<TypedValue>,
+    /// The reason given in `@deprecated("reason")`
+    pub deprecated: Option<String>,
+    pub constraints: TypedConstraints,
+    /// The lines of the `///` comments before the field
+    pub docs: Vec<String>,
 }
 
 impl TypedField {
@@ -73,16 +509,191 @@ impl TypedField {
         &self.name
     }
 
+    #[must_use]
+    pub fn serialized_name(&self) -> &str {
+        &self.serialized_name
+    }
+
     #[must_use]
     pub fn type_name(&self) -> &TypedFieldType {
         &self.type_id
     }
+
+    #[must_use]
+    pub fn default(&self) -> Option<&TypedValue> {
+        self.default.as_ref()
+    }
+
+    #[must_use]
+    pub fn deprecated(&self) -> Option<&str> {
+        self.deprecated.as_deref()
+    }
+
+    #[must_use]
+    pub fn constraints(&self) -> &TypedConstraints {
+        &self.constraints
+    }
+
+    #[must_use]
+    pub fn docs(&self) -> &[String] {
+        &self.docs
+    }
+}
+
+/// A trait implemented by the generated Rust type besides `Serialize`, `Deserialize`, `Debug` and
+/// `Clone`, requested with `@derive`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TypedDerive {
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+}
+
+impl TypedDerive {
+    const ALL: [Self; 4] = [Self::PartialEq, Self::Eq, Self::Hash, Self::Default];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            TypedDerive::PartialEq => "PartialEq",
+            TypedDerive::Eq => "Eq",
+            TypedDerive::Hash => "Hash",
+            TypedDerive::Default => "Default",
+        }
+    }
+}
+
+/// How the names of the fields of a struct, or of the variants of an enum, are serialized, set with
+/// `@rename_all`. The rules are the ones of serde, which expects snake_case fields and PascalCase variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypedRenameRule {
+    Lowercase,
+    Uppercase,
+    PascalCase,
+    CamelCase,
+    SnakeCase,
+    ScreamingSnakeCase,
+    KebabCase,
+    ScreamingKebabCase,
+}
+
+impl TypedRenameRule {
+    const ALL: [Self; 8] = [
+        Self::Lowercase,
+        Self::Uppercase,
+        Self::PascalCase,
+        Self::CamelCase,
+        Self::SnakeCase,
+        Self::ScreamingSnakeCase,
+        Self::KebabCase,
+        Self::ScreamingKebabCase,
+    ];
+
+    /// The name of the rule in `@rename_all`, which is also the one serde uses
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            TypedRenameRule::Lowercase => "lowercase",
+            TypedRenameRule::Uppercase => "UPPERCASE",
+            TypedRenameRule::PascalCase => "PascalCase",
+            TypedRenameRule::CamelCase => "camelCase",
+            TypedRenameRule::SnakeCase => "snake_case",
+            TypedRenameRule::ScreamingSnakeCase => "SCREAMING_SNAKE_CASE",
+            TypedRenameRule::KebabCase => "kebab-case",
+            TypedRenameRule::ScreamingKebabCase => "SCREAMING-KEBAB-CASE",
+        }
+    }
+
+    #[must_use]
+    pub fn apply_to_field(self, field: &str) -> String {
+        match self {
+            TypedRenameRule::Lowercase | TypedRenameRule::SnakeCase => field.to_string(),
+            TypedRenameRule::Uppercase | TypedRenameRule::ScreamingSnakeCase => {
+                field.to_ascii_uppercase()
+            }
+            TypedRenameRule::PascalCase => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(c);
+                    }
+                }
+                pascal
+            }
+            TypedRenameRule::CamelCase => {
+                let pascal = TypedRenameRule::PascalCase.apply_to_field(field);
+                pascal[..1].to_ascii_lowercase() + &pascal[1..]
+            }
+            TypedRenameRule::KebabCase => field.replace('_', "-"),
+            TypedRenameRule::ScreamingKebabCase => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+
+    #[must_use]
+    pub fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            TypedRenameRule::PascalCase => variant.to_string(),
+            TypedRenameRule::Lowercase => variant.to_ascii_lowercase(),
+            TypedRenameRule::Uppercase => variant.to_ascii_uppercase(),
+            TypedRenameRule::CamelCase => variant[..1].to_ascii_lowercase() + &variant[1..],
+            TypedRenameRule::SnakeCase => {
+                let mut snake = String::new();
+                for (i, c) in variant.char_indices() {
+                    if i > 0 && c.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(c.to_ascii_lowercase());
+                }
+                snake
+            }
+            TypedRenameRule::ScreamingSnakeCase => TypedRenameRule::SnakeCase
+                .apply_to_variant(variant)
+                .to_ascii_uppercase(),
+            TypedRenameRule::KebabCase => TypedRenameRule::SnakeCase
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+            TypedRenameRule::ScreamingKebabCase => TypedRenameRule::ScreamingSnakeCase
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+}
+
+/// The attributes written before `struct` or `enum`, which customize the generated types
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypedDefinitionOptions {
+    /// `@derive("PartialEq", ...)`, deriving `Eq` also derives `PartialEq`
+    pub derives: BTreeSet<TypedDerive>,
+    /// `@rename_all("camelCase")`, applied to the fields of a struct or to the variants of an enum
+    pub rename_all: Option<TypedRenameRule>,
+    /// `@non_exhaustive`, which only affects the generated Rust code
+    pub non_exhaustive: bool,
+}
+
+/// The attributes of an RPC method, which change how its calls are made and handled
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypedMethodOptions {
+    /// `@idempotent`, the call can be sent again when the connection is lost before the response
+    pub idempotent: bool,
+    /// `@timeout(10s)`, how long the client waits for the response
+    pub timeout: Option<Duration>,
+    /// `@requires("scope", ...)`, the scopes that the server checks before handling a call
+    pub requires: Vec<String>,
 }
 
 #[derive(Debug)]
 pub struct TypedStruct {
     pub name: String,
     pub fields: Vec<TypedField>,
+    pub docs: Vec<String>,
+    pub options: TypedDefinitionOptions,
 }
 
 impl TypedStruct {
@@ -95,12 +706,28 @@ impl TypedStruct {
     pub fn fields(&self) -> &[TypedField] {
         &self.fields
     }
+
+    #[must_use]
+    pub fn docs(&self) -> &[String] {
+        &self.docs
+    }
+
+    #[must_use]
+    pub fn options(&self) -> &TypedDefinitionOptions {
+        &self.options
+    }
 }
 
 #[derive(Debug)]
 pub struct TypedEnumVariant {
     pub name: String,
+    /// The name in the serialized data, which differs from `name` with `@rename_all` on the enum
+    pub serialized_name: String,
     pub fields: Vec<TypedField>,
+    pub deprecated: Option<String>,
+    pub docs: Vec<String>,
+    /// Written without parentheses, serialized as just its name, or just the tag
+    pub unit: bool,
 }
 
 impl TypedEnumVariant {
@@ -109,16 +736,51 @@ impl TypedEnumVariant {
         &self.name
     }
 
+    #[must_use]
+    pub fn serialized_name(&self) -> &str {
+        &self.serialized_name
+    }
+
     #[must_use]
     pub fn fields(&self) -> &[TypedField] {
         &self.fields
     }
+
+    #[must_use]
+    pub fn deprecated(&self) -> Option<&str> {
+        self.deprecated.as_deref()
+    }
+
+    #[must_use]
+    pub fn docs(&self) -> &[String] {
+        &self.docs
+    }
+
+    #[must_use]
+    pub fn is_unit(&self) -> bool {
+        self.unit
+    }
+}
+
+/// How the variant of an enum is told apart in the serialized data, set with `@tag`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TypedEnumTagging {
+    /// `{"Variant": {"field": ...}}`, or `"Variant"` for a unit variant
+    #[default]
+    External,
+    /// `{"tag": "Variant", "field": ...}`
+    Internal { tag: String },
+    /// `{"tag": "Variant", "content": {"field": ...}}`, or `{"tag": "Variant"}` for a unit variant
+    Adjacent { tag: String, content: String },
 }
 
 #[derive(Debug)]
 pub struct TypedEnum {
     pub name: String,
     pub variants: Vec<TypedEnumVariant>,
+    pub docs: Vec<String>,
+    pub tagging: TypedEnumTagging,
+    pub options: TypedDefinitionOptions,
 }
 
 impl TypedEnum {
@@ -131,6 +793,21 @@ impl TypedEnum {
     pub fn variants(&self) -> &[TypedEnumVariant] {
         &self.variants
     }
+
+    #[must_use]
+    pub fn tagging(&self) -> &TypedEnumTagging {
+        &self.tagging
+    }
+
+    #[must_use]
+    pub fn options(&self) -> &TypedDefinitionOptions {
+        &self.options
+    }
+
+    #[must_use]
+    pub fn docs(&self) -> &[String] {
+        &self.docs
+    }
 }
 
 #[derive(Debug)]
@@ -143,40 +820,138 @@ enum TypeCheckableFieldType<'a> {
     S16,
     S32,
     S64,
+    F32,
+    F64,
+    Bool,
+    Decimal,
     Instant,
+    Duration,
+    Date,
     Guid,
     String,
     Void,
     Binary,
-    ToBeResolved(&'a str),
+    ToBeResolved(&'a str, Span),
     Optional(Box<TypeCheckableFieldType<'a>>),
     Array(Box<TypeCheckableFieldType<'a>>),
+    /// The span is the one of the key, which is checked once it is resolved
+    Map(
+        Box<TypeCheckableFieldType<'a>>,
+        Box<TypeCheckableFieldType<'a>>,
+        Span,
+    ),
+}
+
+impl Display for TypeCheckableFieldType<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeCheckableFieldType::U8 => write!(f, "u8"),
+            TypeCheckableFieldType::U16 => write!(f, "u16"),
+            TypeCheckableFieldType::U32 => write!(f, "u32"),
+            TypeCheckableFieldType::U64 => write!(f, "u64"),
+            TypeCheckableFieldType::S8 => write!(f, "s8"),
+            TypeCheckableFieldType::S16 => write!(f, "s16"),
+            TypeCheckableFieldType::S32 => write!(f, "s32"),
+            TypeCheckableFieldType::S64 => write!(f, "s64"),
+            TypeCheckableFieldType::F32 => write!(f, "f32"),
+            TypeCheckableFieldType::F64 => write!(f, "f64"),
+            TypeCheckableFieldType::Bool => write!(f, "bool"),
+            TypeCheckableFieldType::Decimal => write!(f, "decimal"),
+            TypeCheckableFieldType::Instant => write!(f, "instant"),
+            TypeCheckableFieldType::Duration => write!(f, "duration"),
+            TypeCheckableFieldType::Date => write!(f, "date"),
+            TypeCheckableFieldType::Guid => write!(f, "guid"),
+            TypeCheckableFieldType::String => write!(f, "string"),
+            TypeCheckableFieldType::Void => write!(f, "void"),
+            TypeCheckableFieldType::Binary => write!(f, "binary"),
+            TypeCheckableFieldType::ToBeResolved(name, _) => write!(f, "{name}"),
+            TypeCheckableFieldType::Optional(type_) => write!(f, "{type_}?"),
+            TypeCheckableFieldType::Array(type_) => write!(f, "{type_}[]"),
+            TypeCheckableFieldType::Map(key, value, _) => write!(f, "map<{key}, {value}>"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TypeCheckableField<'input> {
+    type_: TypeCheckableFieldType<'input>,
+    default: Option<LiteralRaw<'input>>,
+    deprecated: Option<String>,
+    constraints: Vec<AttributeRaw<'input>>,
+    docs: Vec<String>,
 }
 
 #[derive(Debug)]
 struct TypeCheckableStructDefinition<'input> {
     name: String,
-    fields: HashMap<&'input str, TypeCheckableFieldType<'input>>,
+    fields: IndexMap<&'input str, TypeCheckableField<'input>>,
+    docs: Vec<String>,
+    options: TypedDefinitionOptions,
+    // Where the derives are requested, which errors about fields not implementing them point at
+    derive_span: Span,
 }
 
 #[derive(Debug)]
 struct TypeCheckableEnumVariant<'input> {
     name: String,
-    fields: HashMap<&'input str, TypeCheckableFieldType<'input>>,
+    fields: IndexMap<&'input str, TypeCheckableField<'input>>,
+    deprecated: Option<String>,
+    docs: Vec<String>,
+    unit: bool,
 }
 
 #[derive(Debug)]
 struct TypeCheckableEnumDefinition<'input> {
     name: String,
-    variants: HashMap<&'input str, TypeCheckableEnumVariant<'input>>,
+    variants: IndexMap<&'input str, TypeCheckableEnumVariant<'input>>,
+    docs: Vec<String>,
+    tagging: TypedEnumTagging,
+    options: TypedDefinitionOptions,
+    derive_span: Span,
 }
 
-#[derive(Debug)]
-enum TypeCheckableDataDefinition<'input> {
-    Struct(&'input TypeCheckableStructDefinition<'input>),
-    Enum(&'input TypeCheckableEnumDefinition<'input>),
+/// The types that can be used without being declared, see `raw_field_type_to_typecheckable_type`
+pub const BUILTIN_TYPES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "s8", "s16", "s32", "s64", "f32", "f64", "bool", "decimal",
+    "instant", "duration", "date", "guid", "string", "void", "binary",
+];
+
+// The attributes that are written after the type of a field, see `TypedConstraints`
+const CONSTRAINTS: &[&str] = &[
+    "pattern",
+    "min_length",
+    "max_length",
+    "non_empty",
+    "min",
+    "max",
+];
+
+// The attributes of RPC methods besides `@deprecated`, see `TypedMethodOptions`
+const METHOD_OPTIONS: &[&str] = &["idempotent", "timeout", "requires"];
+
+const TIMEOUT_ARGUMENTS: &str = "a positive duration, like 500ms, 10s, 5m or 1h";
+
+const DERIVE_ARGUMENTS: &str =
+    "the traits to derive as strings, out of \"PartialEq\", \"Eq\", \"Hash\" and \"Default\"";
+
+// Names are used as-is in the generated code, so anything that the generated code cannot contain is rejected
+const RESERVED_IDENTIFIERS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReferenceKind {
+    Direct,
+    Optional,
+    Array,
 }
 
+type ReferenceGraph<'a> = DiGraph<&'a str, (ReferenceKind, Span)>;
+
 pub struct TypedMetadata {
     pub fields: Vec<TypedField>,
 }
@@ -192,37 +967,183 @@ pub enum TypedRpcCall {
     Stream {
         name: String,
         request: TypedFieldType,
+        request_stream: bool,
         response: TypedFieldType,
+        deprecated: Option<String>,
+        docs: Vec<String>,
+        options: TypedMethodOptions,
     },
     Unary {
         name: String,
         request: TypedFieldType,
+        request_stream: bool,
         response: TypedFieldType,
+        deprecated: Option<String>,
+        docs: Vec<String>,
+        options: TypedMethodOptions,
     },
 }
 
-pub struct TypedRpc {
-    pub calls: Vec<TypedRpcCall>,
-}
+impl TypedRpcCall {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            TypedRpcCall::Stream { name, .. } | TypedRpcCall::Unary { name, .. } => name,
+        }
+    }
 
-impl TypedRpc {
     #[must_use]
-    pub fn calls(&self) -> &[TypedRpcCall] {
-        &self.calls
+    pub fn deprecated(&self) -> Option<&str> {
+        match self {
+            TypedRpcCall::Stream { deprecated, .. } | TypedRpcCall::Unary { deprecated, .. } => {
+                deprecated.as_deref()
+            }
+        }
     }
-}
 
-pub struct TypedFile {
-    pub structs: Vec<TypedStruct>,
-    pub enums: Vec<TypedEnum>,
-    pub meta: TypedMetadata,
-    pub rpc: TypedRpc,
-}
+    #[must_use]
+    pub fn docs(&self) -> &[String] {
+        match self {
+            TypedRpcCall::Stream { docs, .. } | TypedRpcCall::Unary { docs, .. } => docs,
+        }
+    }
+
+    #[must_use]
+    pub fn request_stream(&self) -> bool {
+        match self {
+            TypedRpcCall::Stream { request_stream, .. }
+            | TypedRpcCall::Unary { request_stream, .. } => *request_stream,
+        }
+    }
+
+    #[must_use]
+    pub fn options(&self) -> &TypedMethodOptions {
+        match self {
+            TypedRpcCall::Stream { options, .. } | TypedRpcCall::Unary { options, .. } => options,
+        }
+    }
+}
+
+pub struct TypedRpc {
+    pub calls: Vec<TypedRpcCall>,
+}
+
+impl TypedRpc {
+    #[must_use]
+    pub fn calls(&self) -> &[TypedRpcCall] {
+        &self.calls
+    }
+}
+
+/// A `service` block, whose methods are sent over the wire as `Service.method`
+pub struct TypedService {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub rpc: TypedRpc,
+}
+
+impl TypedService {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn docs(&self) -> &[String] {
+        &self.docs
+    }
+
+    #[must_use]
+    pub fn calls(&self) -> &[TypedRpcCall] {
+        self.rpc.calls()
+    }
+
+    /// The name the method is called by on the wire
+    #[must_use]
+    pub fn method_name(&self, call: &TypedRpcCall) -> String {
+        format!("{}.{}", self.name, call.name())
+    }
+}
+
+/// A `const` declaration, which is generated as a constant of the target language
+#[derive(Debug)]
+pub struct TypedConstant {
+    pub name: String,
+    pub type_id: TypedFieldType,
+    pub value: TypedValue,
+    pub docs: Vec<String>,
+}
+
+impl TypedConstant {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn type_name(&self) -> &TypedFieldType {
+        &self.type_id
+    }
+
+    #[must_use]
+    pub fn value(&self) -> &TypedValue {
+        &self.value
+    }
+
+    #[must_use]
+    pub fn docs(&self) -> &[String] {
+        &self.docs
+    }
+}
+
+pub struct TypedFile {
+    pub constants: Vec<TypedConstant>,
+    pub structs: Vec<TypedStruct>,
+    pub enums: Vec<TypedEnum>,
+    pub meta: TypedMetadata,
+    pub rpc: TypedRpc,
+    pub services: Vec<TypedService>,
+}
+
+impl TypedFile {
+    /// Every RPC method of the file, of the `rpc` block and of all services, together with the
+    /// name it is called by on the wire
+    #[must_use]
+    pub fn methods(&self) -> Vec<(String, &TypedRpcCall)> {
+        let mut methods: Vec<_> = self
+            .rpc
+            .calls()
+            .iter()
+            .map(|call| (call.name().to_string(), call))
+            .collect();
+        for service in &self.services {
+            methods.extend(
+                service
+                    .calls()
+                    .iter()
+                    .map(|call| (service.method_name(call), call)),
+            );
+        }
+
+        methods
+    }
+}
+
+struct ImportableFile {
+    module: String,
+    // Type name -> the traits its generated code derives
+    names: HashMap<String, BTreeSet<TypedDerive>>,
+}
 
 #[derive(Default)]
 pub struct TypeChecker<'input> {
-    structs: HashMap<String, TypeCheckableStructDefinition<'input>>,
-    enums: HashMap<String, TypeCheckableEnumDefinition<'input>>,
+    structs: IndexMap<String, TypeCheckableStructDefinition<'input>>,
+    enums: IndexMap<String, TypeCheckableEnumDefinition<'input>>,
+    indirect_references: HashSet<(String, String)>,
+    importable_files: HashMap<String, ImportableFile>,
+    // Imported type name -> module containing its generated code
+    imported: HashMap<String, String>,
+    imported_derives: HashMap<String, BTreeSet<TypedDerive>>,
 }
 
 impl<'input> TypeChecker<'input> {
@@ -231,33 +1152,652 @@ impl<'input> TypeChecker<'input> {
         Self::default()
     }
 
-    fn check_duplicate(&'input self, name: &'input IdentifierRaw) -> Result<(), TypeCheckError> {
+    /// Makes the structs and enums of an already checked file available to `import "<path>" { ... }`,
+    /// the generated code will refer to them through `module`.
+    #[must_use]
+    pub fn with_import(mut self, path: &str, module: &str, file: &TypedFile) -> Self {
+        let names = file
+            .structs
+            .iter()
+            .map(|s| (s.name.clone(), s.options.derives.clone()))
+            .chain(
+                file.enums
+                    .iter()
+                    .map(|e| (e.name.clone(), e.options.derives.clone())),
+            )
+            .collect();
+
+        self.importable_files.insert(
+            path.to_string(),
+            ImportableFile {
+                module: module.to_string(),
+                names,
+            },
+        );
+
+        self
+    }
+
+    fn check_imports(&mut self, imports: &[ImportRaw], errors: &mut Vec<TypeCheckError>) {
+        for import in imports {
+            let Some(file) = self.importable_files.get(import.path) else {
+                errors.push(TypeCheckError::UnknownImport {
+                    path: import.path.to_string(),
+                    span: import.path_span,
+                });
+                continue;
+            };
+
+            for name in &import.names {
+                let Some(derives) = file.names.get(name.0) else {
+                    errors.push(TypeCheckError::ImportedTypeNotFound {
+                        name: name.0.to_string(),
+                        path: import.path.to_string(),
+                        span: name.1,
+                    });
+                    continue;
+                };
+
+                if self.imported.contains_key(name.0) {
+                    errors.push(TypeCheckError::RepeatedName {
+                        name: name.0.to_string(),
+                        span: name.1,
+                    });
+                    continue;
+                }
+
+                self.imported
+                    .insert(name.0.to_string(), file.module.clone());
+                self.imported_derives
+                    .insert(name.0.to_string(), derives.clone());
+            }
+        }
+    }
+
+    fn check_duplicate(&self, name: &IdentifierRaw) -> Result<(), TypeCheckError> {
+        if self.structs.contains_key(name.0) || self.imported.contains_key(name.0) {
+            return Err(TypeCheckError::RepeatedName {
+                name: name.0.to_string(),
+                span: name.1,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn check_duplicate_enum(&self, name: &IdentifierRaw) -> Result<(), TypeCheckError> {
+        if self.imported.contains_key(name.0) {
+            return Err(TypeCheckError::RepeatedName {
+                name: name.0.to_string(),
+                span: name.1,
+            });
+        }
+
+        if self.enums.contains_key(name.0) {
+            return Err(TypeCheckError::RepeatedEnumName {
+                name: name.0.to_string(),
+                span: name.1,
+            });
+        }
+
         if self.structs.contains_key(name.0) {
-            return Err(TypeCheckError::RepeatedName(name.0.to_string()));
+            return Err(TypeCheckError::StructEnumNameConflict {
+                name: name.0.to_string(),
+                span: name.1,
+            });
         }
 
         Ok(())
     }
 
+    fn check_identifier(name: &IdentifierRaw, errors: &mut Vec<TypeCheckError>) {
+        if RESERVED_IDENTIFIERS.contains(&name.0) {
+            errors.push(TypeCheckError::ReservedIdentifier {
+                name: name.0.to_string(),
+                span: name.1,
+            });
+            return;
+        }
+
+        let mut characters = name.0.chars();
+        let valid_start = characters
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+        let valid_rest = characters.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        if !valid_start || !valid_rest || name.0 == "_" {
+            errors.push(TypeCheckError::InvalidIdentifier {
+                name: name.0.to_string(),
+                span: name.1,
+            });
+        }
+    }
+
     fn map_fields(
         fields_raw: &[FieldRaw<'input>],
         struct_name: &str,
-    ) -> Result<HashMap<&'input str, TypeCheckableFieldType<'input>>, TypeCheckError> {
-        let mut fields = HashMap::new();
+        errors: &mut Vec<TypeCheckError>,
+    ) -> IndexMap<&'input str, TypeCheckableField<'input>> {
+        let mut fields = IndexMap::new();
 
         for field_raw in fields_raw {
-            let type_id = Self::resolve_raw_type(&field_raw.type_name);
+            let field = TypeCheckableField {
+                type_: Self::resolve_raw_type(&field_raw.type_name),
+                default: field_raw.default,
+                deprecated: Self::deprecation(&field_raw.attributes, &[], errors),
+                constraints: field_raw.constraints.clone(),
+                docs: Self::docs(&field_raw.docs),
+            };
+            Self::check_identifier(&field_raw.name, errors);
 
             if fields.contains_key(field_raw.name.0) {
-                return Err(TypeCheckError::RepeatedFieldName {
+                errors.push(TypeCheckError::RepeatedFieldName {
                     field_name: field_raw.name.0.to_string(),
                     struct_name: struct_name.to_string(),
+                    span: field_raw.name.1,
+                });
+                continue;
+            }
+            fields.insert(field_raw.name.0, field);
+        }
+
+        fields
+    }
+
+    fn docs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| (*line).to_string()).collect()
+    }
+
+    // `@deprecated("reason")`, the attributes in `checked_elsewhere` are skipped
+    fn deprecation(
+        attributes: &[AttributeRaw],
+        checked_elsewhere: &[&str],
+        errors: &mut Vec<TypeCheckError>,
+    ) -> Option<String> {
+        let mut deprecated = None;
+
+        for attribute in attributes {
+            if checked_elsewhere.contains(&attribute.name.0) {
+                continue;
+            }
+
+            if CONSTRAINTS.contains(&attribute.name.0) {
+                errors.push(TypeCheckError::MisplacedConstraint {
+                    name: attribute.name.0.to_string(),
+                    span: attribute.span,
+                });
+                continue;
+            }
+
+            if attribute.name.0 != "deprecated" {
+                errors.push(TypeCheckError::UnknownAttribute {
+                    name: attribute.name.0.to_string(),
+                    span: attribute.name.1,
+                });
+                continue;
+            }
+
+            if deprecated.is_some() {
+                errors.push(TypeCheckError::RepeatedAttribute {
+                    name: attribute.name.0.to_string(),
+                    span: attribute.span,
+                });
+                continue;
+            }
+
+            match attribute.arguments.as_slice() {
+                [LiteralRaw::String(reason, _)] => deprecated = Some((*reason).to_string()),
+                _ => errors.push(TypeCheckError::InvalidAttributeArguments {
+                    name: attribute.name.0.to_string(),
+                    expected: "the reason of the deprecation as a string",
+                    span: attribute.span,
+                }),
+            }
+        }
+
+        deprecated
+    }
+
+    // The attributes before `struct` and `enum`, besides `@tag` which only enums have and is checked by `tagging`
+    fn definition_options(
+        name: &str,
+        attributes: &[AttributeRaw],
+        is_enum: bool,
+        errors: &mut Vec<TypeCheckError>,
+    ) -> (TypedDefinitionOptions, Span) {
+        let mut options = TypedDefinitionOptions::default();
+        let mut derive_span = Span::default();
+        let mut seen = HashSet::new();
+
+        for attribute in attributes {
+            let attribute_name = attribute.name.0;
+
+            if CONSTRAINTS.contains(&attribute_name) {
+                errors.push(TypeCheckError::MisplacedConstraint {
+                    name: attribute_name.to_string(),
+                    span: attribute.span,
+                });
+                continue;
+            }
+
+            let known = match attribute_name {
+                "derive" | "rename_all" | "non_exhaustive" => true,
+                "tag" => is_enum,
+                _ => false,
+            };
+            if !known {
+                errors.push(TypeCheckError::UnknownAttribute {
+                    name: attribute_name.to_string(),
+                    span: attribute.name.1,
+                });
+                continue;
+            }
+
+            // Repeated tags are reported by `tagging`
+            if attribute_name == "tag" {
+                continue;
+            }
+
+            if !seen.insert(attribute_name) {
+                errors.push(TypeCheckError::RepeatedAttribute {
+                    name: attribute_name.to_string(),
+                    span: attribute.span,
+                });
+                continue;
+            }
+
+            let invalid_arguments = |expected| TypeCheckError::InvalidAttributeArguments {
+                name: attribute_name.to_string(),
+                expected,
+                span: attribute.span,
+            };
+
+            match (attribute_name, attribute.arguments.as_slice()) {
+                ("derive", arguments) if !arguments.is_empty() => {
+                    derive_span = attribute.span;
+
+                    for argument in arguments {
+                        let derive = match argument {
+                            LiteralRaw::String(value, _) => {
+                                TypedDerive::ALL.into_iter().find(|d| d.name() == *value)
+                            }
+                            _ => None,
+                        };
+
+                        match derive {
+                            Some(TypedDerive::Default) if is_enum => {
+                                errors.push(TypeCheckError::UnsupportedDerive {
+                                    derive: TypedDerive::Default.name().to_string(),
+                                    name: name.to_string(),
+                                    span: argument.span(),
+                                });
+                            }
+                            Some(derive) => {
+                                if derive == TypedDerive::Eq {
+                                    options.derives.insert(TypedDerive::PartialEq);
+                                }
+                                options.derives.insert(derive);
+                            }
+                            None => errors.push(invalid_arguments(DERIVE_ARGUMENTS)),
+                        }
+                    }
+                }
+                ("derive", _) => errors.push(invalid_arguments(DERIVE_ARGUMENTS)),
+                ("rename_all", [LiteralRaw::String(rule, _)]) => {
+                    match TypedRenameRule::ALL.into_iter().find(|r| r.name() == *rule) {
+                        Some(rule) => options.rename_all = Some(rule),
+                        None => errors.push(invalid_arguments(
+                            "one of \"lowercase\", \"UPPERCASE\", \"PascalCase\", \"camelCase\", \"snake_case\", \"SCREAMING_SNAKE_CASE\", \"kebab-case\" and \"SCREAMING-KEBAB-CASE\"",
+                        )),
+                    }
+                }
+                ("rename_all", _) => errors.push(invalid_arguments(
+                    "the case of the serialized names as a string, like \"camelCase\"",
+                )),
+                ("non_exhaustive", []) => options.non_exhaustive = true,
+                _ => errors.push(invalid_arguments("no arguments")),
+            }
+        }
+
+        (options, derive_span)
+    }
+
+    // `@tag("tag")` makes the enum internally tagged, `@tag("tag", "content")` adjacently tagged
+    fn tagging(
+        attributes: &[AttributeRaw],
+        variants: &[EnumVariantRaw],
+        errors: &mut Vec<TypeCheckError>,
+    ) -> TypedEnumTagging {
+        let mut tagging = None;
+
+        for attribute in attributes.iter().filter(|a| a.name.0 == "tag") {
+            if tagging.is_some() {
+                errors.push(TypeCheckError::RepeatedAttribute {
+                    name: attribute.name.0.to_string(),
+                    span: attribute.span,
+                });
+                continue;
+            }
+
+            tagging = match attribute.arguments.as_slice() {
+                [LiteralRaw::String(tag, _)] => Some(TypedEnumTagging::Internal {
+                    tag: (*tag).to_string(),
+                }),
+                [LiteralRaw::String(tag, _), LiteralRaw::String(content, _)] if tag != content => {
+                    Some(TypedEnumTagging::Adjacent {
+                        tag: (*tag).to_string(),
+                        content: (*content).to_string(),
+                    })
+                }
+                _ => {
+                    errors.push(TypeCheckError::InvalidAttributeArguments {
+                        name: attribute.name.0.to_string(),
+                        expected: "the name of the tag, and optionally a different name of the content, as strings",
+                        span: attribute.span,
+                    });
+                    continue;
+                }
+            };
+        }
+
+        // The fields of internally tagged variants are next to the tag
+        if let Some(TypedEnumTagging::Internal { tag }) = &tagging {
+            for variant in variants {
+                for field in variant.fields.iter().filter(|field| field.name.0 == tag) {
+                    errors.push(TypeCheckError::TagConflict {
+                        tag: tag.clone(),
+                        variant_name: variant.name.0.to_string(),
+                        span: field.name.1,
+                    });
+                }
+            }
+        }
+
+        tagging.unwrap_or_default()
+    }
+
+    fn check_constants(
+        &self,
+        constants: &[ConstantRaw<'input>],
+        errors: &mut Vec<TypeCheckError>,
+    ) -> Vec<TypedConstant> {
+        let mut names = HashSet::new();
+        let mut typed = vec![];
+
+        for constant in constants {
+            Self::check_identifier(&constant.name, errors);
+
+            if !names.insert(constant.name.0) {
+                errors.push(TypeCheckError::RepeatedConstantName {
+                    name: constant.name.0.to_string(),
+                    span: constant.name.1,
+                });
+                continue;
+            }
+
+            let type_id = match self.resolve_type(&Self::resolve_raw_type(&constant.type_name)) {
+                Ok(type_id) => type_id,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+
+            if !matches!(
+                type_id,
+                TypedFieldType::U8
+                    | TypedFieldType::U16
+                    | TypedFieldType::U32
+                    | TypedFieldType::U64
+                    | TypedFieldType::S8
+                    | TypedFieldType::S16
+                    | TypedFieldType::S32
+                    | TypedFieldType::S64
+                    | TypedFieldType::F32
+                    | TypedFieldType::F64
+                    | TypedFieldType::Bool
+                    | TypedFieldType::String
+            ) {
+                errors.push(TypeCheckError::UnsupportedConstantType {
+                    name: constant.name.0.to_string(),
+                    type_name: type_id.to_string(),
+                    span: constant.type_name.span(),
                 });
+                continue;
             }
-            fields.insert(field_raw.name.0, type_id);
+
+            let Some(value) = Self::check_literal(&type_id, &constant.value) else {
+                errors.push(TypeCheckError::InvalidConstantValue {
+                    name: constant.name.0.to_string(),
+                    type_name: type_id.to_string(),
+                    span: constant.value.span(),
+                });
+                continue;
+            };
+
+            typed.push(TypedConstant {
+                name: constant.name.0.to_string(),
+                type_id,
+                value,
+                docs: Self::docs(&constant.docs),
+            });
+        }
+
+        typed
+    }
+
+    fn check_default(
+        field_name: &str,
+        type_id: &TypedFieldType,
+        default: &LiteralRaw,
+    ) -> Result<TypedValue, TypeCheckError> {
+        Self::check_literal(type_id, default).ok_or_else(|| TypeCheckError::InvalidDefault {
+            field_name: field_name.to_string(),
+            type_name: type_id.to_string(),
+            span: default.span(),
+        })
+    }
+
+    // Converts the literal to a value of the type, if it is one
+    fn check_literal(type_id: &TypedFieldType, literal: &LiteralRaw) -> Option<TypedValue> {
+        let (minimum, maximum): (i128, i128) = match type_id {
+            // The value is wrapped in `Some` by the generated code
+            TypedFieldType::Optional(inner) => return Self::check_literal(inner, literal),
+            TypedFieldType::String => {
+                return match literal {
+                    LiteralRaw::String(value, _) => Some(TypedValue::String((*value).to_string())),
+                    _ => None,
+                };
+            }
+            TypedFieldType::Bool => {
+                return match literal {
+                    LiteralRaw::Boolean(value, _) => Some(TypedValue::Boolean(*value)),
+                    _ => None,
+                };
+            }
+            // Integers are accepted too, and converted so that the generated code has a float literal
+            TypedFieldType::F32 | TypedFieldType::F64 => {
+                let value = match literal {
+                    LiteralRaw::Integer(value, _) | LiteralRaw::Float(value, _) => {
+                        value.parse::<f64>().ok()?
+                    }
+                    _ => return None,
+                };
+
+                let fits = match type_id {
+                    #[allow(clippy::cast_possible_truncation)]
+                    TypedFieldType::F32 => (value as f32).is_finite(),
+                    _ => value.is_finite(),
+                };
+
+                return fits.then_some(TypedValue::Float(value));
+            }
+            TypedFieldType::U8 => (0, u8::MAX.into()),
+            TypedFieldType::U16 => (0, u16::MAX.into()),
+            TypedFieldType::U32 => (0, u32::MAX.into()),
+            TypedFieldType::U64 => (0, u64::MAX.into()),
+            TypedFieldType::S8 => (i8::MIN.into(), i8::MAX.into()),
+            TypedFieldType::S16 => (i16::MIN.into(), i16::MAX.into()),
+            TypedFieldType::S32 => (i32::MIN.into(), i32::MAX.into()),
+            TypedFieldType::S64 => (i64::MIN.into(), i64::MAX.into()),
+            _ => return None,
+        };
+
+        match literal {
+            LiteralRaw::Integer(value, _) => value
+                .parse::<i128>()
+                .ok()
+                .filter(|value| (minimum..=maximum).contains(value))
+                .map(TypedValue::Integer),
+            _ => None,
+        }
+    }
+
+    fn check_constraints(
+        field_name: &str,
+        type_id: &TypedFieldType,
+        attributes: &[AttributeRaw],
+        errors: &mut Vec<TypeCheckError>,
+    ) -> TypedConstraints {
+        let mut constraints = TypedConstraints::default();
+
+        let mut value_type = type_id;
+        while let TypedFieldType::Optional(inner) = value_type {
+            value_type = inner;
+        }
+
+        for attribute in attributes {
+            let name = attribute.name.0;
+            let applicable = match name {
+                "pattern" => matches!(value_type, TypedFieldType::String),
+                "min_length" | "max_length" | "non_empty" => matches!(
+                    value_type,
+                    TypedFieldType::String
+                        | TypedFieldType::Binary
+                        | TypedFieldType::Array(_)
+                        | TypedFieldType::Map(..)
+                ),
+                "min" | "max" => matches!(
+                    value_type,
+                    TypedFieldType::U8
+                        | TypedFieldType::U16
+                        | TypedFieldType::U32
+                        | TypedFieldType::U64
+                        | TypedFieldType::S8
+                        | TypedFieldType::S16
+                        | TypedFieldType::S32
+                        | TypedFieldType::S64
+                        | TypedFieldType::F32
+                        | TypedFieldType::F64
+                ),
+                _ => {
+                    errors.push(TypeCheckError::UnknownAttribute {
+                        name: name.to_string(),
+                        span: attribute.name.1,
+                    });
+                    continue;
+                }
+            };
+
+            if !applicable {
+                errors.push(TypeCheckError::UnsupportedConstraint {
+                    name: name.to_string(),
+                    type_name: type_id.to_string(),
+                    span: attribute.span,
+                });
+                continue;
+            }
+
+            let already_constrained = match name {
+                "pattern" => constraints.pattern.is_some(),
+                "min_length" | "non_empty" => constraints.min_length.is_some(),
+                "max_length" => constraints.max_length.is_some(),
+                "min" => constraints.min.is_some(),
+                _ => constraints.max.is_some(),
+            };
+
+            if already_constrained {
+                errors.push(TypeCheckError::RepeatedAttribute {
+                    name: name.to_string(),
+                    span: attribute.span,
+                });
+                continue;
+            }
+
+            let invalid_arguments = |expected| TypeCheckError::InvalidAttributeArguments {
+                name: name.to_string(),
+                expected,
+                span: attribute.span,
+            };
+
+            match (name, attribute.arguments.as_slice()) {
+                ("pattern", [LiteralRaw::String(pattern, span)]) => {
+                    // The generated code requires the whole value to match
+                    if regex::Regex::new(&format!("^(?:{pattern})$")).is_ok() {
+                        constraints.pattern = Some((*pattern).to_string());
+                    } else {
+                        errors.push(TypeCheckError::InvalidPattern {
+                            pattern: (*pattern).to_string(),
+                            span: *span,
+                        });
+                    }
+                }
+                ("pattern", _) => {
+                    errors.push(invalid_arguments("a regular expression as a string"));
+                }
+                ("non_empty", []) => constraints.min_length = Some(1),
+                ("non_empty", _) => errors.push(invalid_arguments("no arguments")),
+                ("min_length" | "max_length", arguments) => {
+                    let length = match arguments {
+                        [LiteralRaw::Integer(value, _)] => value.parse::<u64>().ok(),
+                        _ => None,
+                    };
+
+                    match (name, length) {
+                        ("min_length", Some(length)) => constraints.min_length = Some(length),
+                        (_, Some(length)) => constraints.max_length = Some(length),
+                        (_, None) => errors.push(invalid_arguments("a non-negative integer")),
+                    }
+                }
+                (_, arguments) => {
+                    let bound = match arguments {
+                        [literal] => Self::check_literal(value_type, literal),
+                        _ => None,
+                    };
+
+                    match (name, bound) {
+                        ("min", Some(bound)) => constraints.min = Some(bound),
+                        (_, Some(bound)) => constraints.max = Some(bound),
+                        (_, None) => {
+                            errors.push(invalid_arguments("a value of the type of the field"));
+                        }
+                    }
+                }
+            }
+        }
+
+        let empty_length = matches!(
+            (constraints.min_length, constraints.max_length),
+            (Some(min), Some(max)) if min > max
+        );
+        let empty_range = match (&constraints.min, &constraints.max) {
+            (Some(TypedValue::Integer(min)), Some(TypedValue::Integer(max))) => min > max,
+            (Some(TypedValue::Float(min)), Some(TypedValue::Float(max))) => min > max,
+            _ => false,
+        };
+
+        if let (true, Some(first), Some(last)) = (
+            empty_length || empty_range,
+            attributes.first(),
+            attributes.last(),
+        ) {
+            errors.push(TypeCheckError::UnsatisfiableConstraints {
+                field_name: field_name.to_string(),
+                span: Span::new(first.span.start, last.span.end),
+            });
         }
 
-        Ok(fields)
+        constraints
     }
 
     fn raw_field_type_to_typecheckable_type(
@@ -272,44 +1812,227 @@ impl<'input> TypeChecker<'input> {
             "s16" => TypeCheckableFieldType::S16,
             "s32" => TypeCheckableFieldType::S32,
             "s64" => TypeCheckableFieldType::S64,
+            "f32" => TypeCheckableFieldType::F32,
+            "f64" => TypeCheckableFieldType::F64,
+            "bool" => TypeCheckableFieldType::Bool,
+            "decimal" => TypeCheckableFieldType::Decimal,
             "instant" => TypeCheckableFieldType::Instant,
+            "duration" => TypeCheckableFieldType::Duration,
+            "date" => TypeCheckableFieldType::Date,
             "guid" => TypeCheckableFieldType::Guid,
             "string" => TypeCheckableFieldType::String,
             "void" => TypeCheckableFieldType::Void,
             "binary" => TypeCheckableFieldType::Binary,
-            other => TypeCheckableFieldType::ToBeResolved(other),
+            other => TypeCheckableFieldType::ToBeResolved(other, type_name.1),
         }
     }
 
     fn resolve_raw_type(type_raw: &TypeRaw<'input>) -> TypeCheckableFieldType<'input> {
         match type_raw {
             TypeRaw::Type(id) => Self::raw_field_type_to_typecheckable_type(id),
-            TypeRaw::Optional(id) => TypeCheckableFieldType::Optional(Box::new(
-                Self::raw_field_type_to_typecheckable_type(id),
-            )),
-            TypeRaw::Array(id) => TypeCheckableFieldType::Array(Box::new(
-                Self::raw_field_type_to_typecheckable_type(id),
-            )),
+            TypeRaw::Optional(inner) => {
+                TypeCheckableFieldType::Optional(Box::new(Self::resolve_raw_type(inner)))
+            }
+            TypeRaw::Array(inner) => {
+                TypeCheckableFieldType::Array(Box::new(Self::resolve_raw_type(inner)))
+            }
+            TypeRaw::Map(key, value, _) => TypeCheckableFieldType::Map(
+                Box::new(Self::resolve_raw_type(key)),
+                Box::new(Self::resolve_raw_type(value)),
+                key.span(),
+            ),
         }
     }
 
     fn type_check_fields(
         &self,
-        raw_fields: &HashMap<&str, TypeCheckableFieldType>,
-    ) -> Result<Vec<TypedField>, TypeCheckError> {
+        owner: Option<&str>,
+        rename_all: Option<TypedRenameRule>,
+        raw_fields: &IndexMap<&str, TypeCheckableField>,
+        errors: &mut Vec<TypeCheckError>,
+    ) -> Vec<TypedField> {
         let mut fields = vec![];
 
-        for (field_name, field_type) in raw_fields {
+        for (field_name, field) in raw_fields {
+            let type_id = match self.resolve_type(&field.type_) {
+                Ok(type_id) => type_id,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+
+            let default = match &field.default {
+                Some(default) => match Self::check_default(field_name, &type_id, default) {
+                    Ok(default) => Some(default),
+                    Err(error) => {
+                        errors.push(error);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+
+            let constraints =
+                Self::check_constraints(field_name, &type_id, &field.constraints, errors);
+
             fields.push(TypedField {
                 name: (*field_name).to_string(),
-                type_id: self.resolve_type(field_type)?,
+                serialized_name: rename_all.map_or_else(
+                    || (*field_name).to_string(),
+                    |rule| rule.apply_to_field(field_name),
+                ),
+                type_id: self.box_indirect_reference(owner, type_id),
+                default,
+                deprecated: field.deprecated.clone(),
+                constraints,
+                docs: field.docs.clone(),
             });
         }
 
-        Ok(fields)
+        fields
     }
 
-    fn resolve_type(
+    // Renaming can give different names the same serialized name, the repetitions are reported where they are defined
+    fn check_renamed_names<'a>(
+        names: impl Iterator<Item = &'a IdentifierRaw<'a>>,
+        rename: impl Fn(&str) -> String,
+        error: impl Fn(String, Span) -> TypeCheckError,
+        errors: &mut Vec<TypeCheckError>,
+    ) where
+        'input: 'a,
+    {
+        let mut renamed = HashMap::new();
+
+        for name in names {
+            let serialized = rename(name.0);
+            match renamed.get(&serialized) {
+                // Names repeated before renaming are already reported
+                Some(original) if *original != name.0 => errors.push(error(serialized, name.1)),
+                Some(_) => {}
+                None => {
+                    renamed.insert(serialized, name.0);
+                }
+            }
+        }
+    }
+
+    /// Whether the generated Rust type implements the trait
+    fn implements(&self, derive: TypedDerive, type_id: &TypedFieldType) -> bool {
+        match type_id {
+            TypedFieldType::F32 | TypedFieldType::F64 => {
+                !matches!(derive, TypedDerive::Eq | TypedDerive::Hash)
+            }
+            TypedFieldType::Instant | TypedFieldType::Date => derive != TypedDerive::Default,
+            TypedFieldType::OtherStruct(name) => self
+                .structs
+                .get(name)
+                .is_some_and(|s| s.options.derives.contains(&derive)),
+            TypedFieldType::Enum(name) => self
+                .enums
+                .get(name)
+                .is_some_and(|e| e.options.derives.contains(&derive)),
+            TypedFieldType::Imported { name, .. } => self
+                .imported_derives
+                .get(name)
+                .is_some_and(|derives| derives.contains(&derive)),
+            // Options, vectors and maps are empty by default, whatever they contain
+            TypedFieldType::Optional(_) | TypedFieldType::Array(_) | TypedFieldType::Map(..)
+                if derive == TypedDerive::Default =>
+            {
+                true
+            }
+            TypedFieldType::Optional(inner)
+            | TypedFieldType::Array(inner)
+            | TypedFieldType::Map(_, inner)
+            | TypedFieldType::Indirect(inner) => self.implements(derive, inner),
+            _ => true,
+        }
+    }
+
+    // Every field has to implement the derived traits, except that fields with a default value use it
+    // in the generated implementation of `Default`
+    fn check_derives(
+        &self,
+        name: &str,
+        fields: &[TypedField],
+        options: &TypedDefinitionOptions,
+        span: Span,
+        errors: &mut Vec<TypeCheckError>,
+    ) {
+        for derive in &options.derives {
+            let missing = fields.iter().find(|field| {
+                let uses_default_value =
+                    *derive == TypedDerive::Default && field.default().is_some();
+                !uses_default_value && !self.implements(*derive, field.type_name())
+            });
+
+            if let Some(field) = missing {
+                errors.push(TypeCheckError::MissingDerive {
+                    derive: derive.name().to_string(),
+                    name: name.to_string(),
+                    field_name: field.name().to_string(),
+                    type_name: field.type_name().to_string(),
+                    span,
+                });
+            }
+        }
+    }
+
+    fn box_indirect_reference(
+        &self,
+        owner: Option<&str>,
+        type_id: TypedFieldType,
+    ) -> TypedFieldType {
+        let Some(owner) = owner else {
+            return type_id;
+        };
+
+        match type_id {
+            TypedFieldType::Optional(inner) => {
+                TypedFieldType::Optional(Box::new(self.box_optional_reference(owner, *inner)))
+            }
+            other => other,
+        }
+    }
+
+    // Nested optionals are stored inline as well, so `A??` is boxed inside of both of them
+    fn box_optional_reference(&self, owner: &str, type_id: TypedFieldType) -> TypedFieldType {
+        match type_id {
+            TypedFieldType::Optional(inner) => {
+                TypedFieldType::Optional(Box::new(self.box_optional_reference(owner, *inner)))
+            }
+            TypedFieldType::OtherStruct(ref target) | TypedFieldType::Enum(ref target)
+                if self
+                    .indirect_references
+                    .contains(&(owner.to_string(), target.clone())) =>
+            {
+                TypedFieldType::Indirect(Box::new(type_id))
+            }
+            other => other,
+        }
+    }
+
+    // Errors about unknown types point at the outermost type expression containing them
+    fn resolve_wrapped_type(
+        &self,
+        field_type: &TypeCheckableFieldType,
+        wrapper: &TypeCheckableFieldType,
+    ) -> Result<TypedFieldType, TypeCheckError> {
+        self.resolve_type(field_type).map_err(|error| match error {
+            TypeCheckError::StructNotFound { name, span }
+            | TypeCheckError::UnknownTypeInWrapper { name, span, .. } => {
+                TypeCheckError::UnknownTypeInWrapper {
+                    name,
+                    wrapped: wrapper.to_string(),
+                    span,
+                }
+            }
+            other => other,
+        })
+    }
+
+    fn resolve_type(
         &self,
         field_type: &TypeCheckableFieldType,
     ) -> Result<TypedFieldType, TypeCheckError> {
@@ -322,209 +2045,1657 @@ impl<'input> TypeChecker<'input> {
             TypeCheckableFieldType::S16 => TypedFieldType::S16,
             TypeCheckableFieldType::S32 => TypedFieldType::S32,
             TypeCheckableFieldType::S64 => TypedFieldType::S64,
+            TypeCheckableFieldType::F32 => TypedFieldType::F32,
+            TypeCheckableFieldType::F64 => TypedFieldType::F64,
+            TypeCheckableFieldType::Bool => TypedFieldType::Bool,
+            TypeCheckableFieldType::Decimal => TypedFieldType::Decimal,
             TypeCheckableFieldType::Instant => TypedFieldType::Instant,
+            TypeCheckableFieldType::Duration => TypedFieldType::Duration,
+            TypeCheckableFieldType::Date => TypedFieldType::Date,
             TypeCheckableFieldType::Guid => TypedFieldType::Guid,
             TypeCheckableFieldType::String => TypedFieldType::String,
             TypeCheckableFieldType::Void => TypedFieldType::Void,
             TypeCheckableFieldType::Binary => TypedFieldType::Binary,
-            TypeCheckableFieldType::ToBeResolved(type_name) => {
+            TypeCheckableFieldType::ToBeResolved(type_name, span) => {
                 if self.structs.contains_key(*type_name) {
                     return Ok(TypedFieldType::OtherStruct((*type_name).to_string()));
                 } else if self.enums.contains_key(*type_name) {
                     return Ok(TypedFieldType::Enum((*type_name).to_string()));
+                } else if let Some(module) = self.imported.get(*type_name) {
+                    return Ok(TypedFieldType::Imported {
+                        module: module.clone(),
+                        name: (*type_name).to_string(),
+                    });
                 }
-                return Err(TypeCheckError::StructNotFound((*type_name).to_string()));
+                return Err(TypeCheckError::StructNotFound {
+                    name: (*type_name).to_string(),
+                    span: *span,
+                });
             }
             TypeCheckableFieldType::Optional(type_) => {
-                let type_id = self.resolve_type(type_)?;
+                let type_id = self.resolve_wrapped_type(type_, field_type)?;
                 TypedFieldType::Optional(Box::new(type_id))
             }
             TypeCheckableFieldType::Array(type_) => {
-                let type_id = self.resolve_type(type_)?;
+                let type_id = self.resolve_wrapped_type(type_, field_type)?;
                 TypedFieldType::Array(Box::new(type_id))
             }
+            TypeCheckableFieldType::Map(key, value, key_span) => {
+                let key = self.resolve_wrapped_type(key, field_type)?;
+                let value = self.resolve_wrapped_type(value, field_type)?;
+
+                if !matches!(
+                    key,
+                    TypedFieldType::U8
+                        | TypedFieldType::U16
+                        | TypedFieldType::U32
+                        | TypedFieldType::U64
+                        | TypedFieldType::S8
+                        | TypedFieldType::S16
+                        | TypedFieldType::S32
+                        | TypedFieldType::S64
+                        | TypedFieldType::Guid
+                        | TypedFieldType::String
+                ) {
+                    return Err(TypeCheckError::InvalidMapKey {
+                        type_name: key.to_string(),
+                        span: *key_span,
+                    });
+                }
+
+                TypedFieldType::Map(Box::new(key), Box::new(value))
+            }
         })
     }
 
     /// # Errors
-    /// May return an error when the type check fails
-    /// # Panics
-    /// TODO MAKE THIS NOT EVER PANIC
+    /// Returns every error found when the type check fails
     /// todo split into smaller functions
     #[allow(clippy::too_many_lines)]
-    pub fn check(mut self, file: &FileRaw<'input>) -> Result<TypedFile, TypeCheckError> {
-        for StructDefinitionRaw(name, fields) in file.structs() {
-            self.check_duplicate(name)?;
+    pub fn check(mut self, file: &FileRaw<'input>) -> Result<TypedFile, Vec<TypeCheckError>> {
+        let mut errors = vec![];
+
+        self.check_imports(file.imports(), &mut errors);
+
+        for StructDefinitionRaw(name, fields, _, docs, attributes) in file.structs() {
+            Self::check_identifier(name, &mut errors);
+
+            if let Err(error) = self.check_duplicate(name) {
+                errors.push(error);
+                continue;
+            }
 
-            let fields = Self::map_fields(fields, name.0)?;
+            let (options, derive_span) =
+                Self::definition_options(name.0, attributes, false, &mut errors);
+            if let Some(rule) = options.rename_all {
+                Self::check_renamed_names(
+                    fields.iter().map(|f| &f.name),
+                    |field| rule.apply_to_field(field),
+                    |field_name, span| TypeCheckError::RepeatedFieldName {
+                        field_name,
+                        struct_name: name.0.to_string(),
+                        span,
+                    },
+                    &mut errors,
+                );
+            }
+            let fields = Self::map_fields(fields, name.0, &mut errors);
 
             self.structs.insert(
                 name.0.to_string(),
                 TypeCheckableStructDefinition {
                     name: name.0.to_string(),
                     fields,
+                    docs: Self::docs(docs),
+                    options,
+                    derive_span,
                 },
             );
         }
 
-        for EnumDefinitionRaw { name, variants } in file.enums() {
-            // fixme check for name conflicts with structs
-            // fixme check for name conflicts with other enums
+        for EnumDefinitionRaw {
+            name,
+            variants,
+            attributes,
+            docs,
+            ..
+        } in file.enums()
+        {
+            Self::check_identifier(name, &mut errors);
+
+            if let Err(error) = self.check_duplicate_enum(name) {
+                errors.push(error);
+                continue;
+            }
 
-            let variants = Self::map_enum_variants(variants, name.0)?;
+            let (options, derive_span) =
+                Self::definition_options(name.0, attributes, true, &mut errors);
+            if let Some(rule) = options.rename_all {
+                Self::check_renamed_names(
+                    variants.iter().map(|v| &v.name),
+                    |variant| rule.apply_to_variant(variant),
+                    |variant_name, span| TypeCheckError::RepeatedVariantName {
+                        variant_name,
+                        enum_name: name.0.to_string(),
+                        span,
+                    },
+                    &mut errors,
+                );
+            }
+            let tagging = Self::tagging(attributes, variants, &mut errors);
+            let variants = Self::map_enum_variants(variants, name.0, &mut errors);
 
             self.enums.insert(
                 name.0.to_string(),
                 TypeCheckableEnumDefinition {
                     name: name.0.to_string(),
                     variants,
+                    docs: Self::docs(docs),
+                    tagging,
+                    options,
+                    derive_span,
                 },
             );
         }
 
-        let mut metadata_fields = HashMap::new();
+        let mut metadata_fields = IndexMap::new();
         if let Some(metadata) = file.metadata() {
-            metadata_fields = Self::map_fields(metadata.fields(), "metadata")?;
+            metadata_fields = Self::map_fields(metadata.fields(), "metadata", &mut errors);
+
+            // Only the requests are validated by the generated servers
+            for field in metadata_fields.values_mut() {
+                for constraint in field.constraints.drain(..) {
+                    errors.push(TypeCheckError::MisplacedConstraint {
+                        name: constraint.name.0.to_string(),
+                        span: constraint.span,
+                    });
+                }
+            }
         }
 
-        let mut graph = DiGraph::new();
-        let mut node_ids = HashMap::new();
-        for struct_definition in self.structs.values() {
-            let ix = graph.add_node(TypeCheckableDataDefinition::Struct(struct_definition));
-            node_ids.insert(struct_definition.name.to_string(), ix);
+        errors.append(&mut self.check_references());
+
+        let mut structs_typed = vec![];
+        let mut enums_typed = vec![];
+
+        for struct_node in self.structs.values() {
+            let typed_struct = TypedStruct {
+                name: struct_node.name.clone(),
+                fields: self.type_check_fields(
+                    Some(&struct_node.name),
+                    struct_node.options.rename_all,
+                    &struct_node.fields,
+                    &mut errors,
+                ),
+                docs: struct_node.docs.clone(),
+                options: struct_node.options.clone(),
+            };
+            self.check_derives(
+                &typed_struct.name,
+                &typed_struct.fields,
+                &struct_node.options,
+                struct_node.derive_span,
+                &mut errors,
+            );
+            structs_typed.push(typed_struct);
         }
 
-        for enum_definition in self.enums.values() {
-            let ix = graph.add_node(TypeCheckableDataDefinition::Enum(enum_definition));
-            node_ids.insert(enum_definition.name.to_string(), ix);
+        for enum_node in self.enums.values() {
+            let variants = enum_node
+                .variants
+                .values()
+                .map(|variant| {
+                    let fields = self.type_check_fields(
+                        Some(&enum_node.name),
+                        None,
+                        &variant.fields,
+                        &mut errors,
+                    );
+                    self.check_derives(
+                        &enum_node.name,
+                        &fields,
+                        &enum_node.options,
+                        enum_node.derive_span,
+                        &mut errors,
+                    );
+
+                    TypedEnumVariant {
+                        name: variant.name.to_string(),
+                        serialized_name: enum_node.options.rename_all.map_or_else(
+                            || variant.name.clone(),
+                            |rule| rule.apply_to_variant(&variant.name),
+                        ),
+                        fields,
+                        deprecated: variant.deprecated.clone(),
+                        docs: variant.docs.clone(),
+                        unit: variant.unit,
+                    }
+                })
+                .collect();
+
+            enums_typed.push(TypedEnum {
+                name: enum_node.name.clone(),
+                variants,
+                docs: enum_node.docs.clone(),
+                tagging: enum_node.tagging.clone(),
+                options: enum_node.options.clone(),
+            });
         }
 
-        for struct_definition in self.structs.values() {
-            for field_definition in struct_definition.fields.values() {
-                if let TypeCheckableFieldType::ToBeResolved(name) = field_definition {
-                    graph.add_edge(
-                        *node_ids.get(*name).unwrap(),
-                        *node_ids.get(&struct_definition.name).unwrap(),
-                        (*name).to_string(),
-                    );
-                }
+        let constants = self.check_constants(file.constants(), &mut errors);
+        let meta_fields = self.type_check_fields(None, None, &metadata_fields, &mut errors);
+        let rpc = file.rpc().map(|rpc| self.check_rpc(rpc, &mut errors));
+        let services = self.check_services(file, &mut errors);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(TypedFile {
+            constants,
+            structs: structs_typed,
+            enums: enums_typed,
+            meta: TypedMetadata {
+                fields: meta_fields,
+            },
+            rpc: TypedRpc {
+                calls: rpc.unwrap_or_default(),
+            },
+            services,
+        })
+    }
+
+    fn check_services(
+        &self,
+        file: &FileRaw<'input>,
+        errors: &mut Vec<TypeCheckError>,
+    ) -> Vec<TypedService> {
+        if let (Some(rpc), Some(_)) = (file.rpc(), file.services().first()) {
+            errors.push(TypeCheckError::RpcOutsideOfService { span: rpc.span });
+        }
+
+        let mut services = vec![];
+        // Services are generated as modules named in snake case, so those must be unique as well
+        let mut module_names = HashSet::new();
+        for service in file.services() {
+            Self::check_identifier(&service.name, errors);
+            let module_name = snake_case(service.name.0);
+            if RESERVED_IDENTIFIERS.contains(&module_name.as_str()) {
+                errors.push(TypeCheckError::ReservedIdentifier {
+                    name: module_name.clone(),
+                    span: service.name.1,
+                });
             }
+            if !module_names.insert(module_name) {
+                errors.push(TypeCheckError::RepeatedServiceName {
+                    name: service.name.0.to_string(),
+                    span: service.name.1,
+                });
+                continue;
+            }
+
+            services.push(TypedService {
+                name: service.name.0.to_string(),
+                docs: Self::docs(&service.docs),
+                rpc: TypedRpc {
+                    calls: self.check_rpc(&service.rpc, errors),
+                },
+            });
         }
 
-        let sorted = toposort(&graph, None).unwrap();
-        let mut structs_typed = HashMap::new();
-        let mut enums_typed = HashMap::new();
+        services
+    }
 
-        for x in sorted {
-            let node = graph.node_weight(x).unwrap();
+    fn check_rpc(
+        &self,
+        rpc: &RpcRaw<'input>,
+        errors: &mut Vec<TypeCheckError>,
+    ) -> Vec<TypedRpcCall> {
+        let mut rpc_typed = vec![];
+        let mut rpc_names = HashSet::new();
+        for rpc_definition in &rpc.definitions {
+            let name = rpc_definition.name();
+            Self::check_identifier(name, errors);
+
+            if !rpc_names.insert(name.0) {
+                errors.push(TypeCheckError::RepeatedRpcName {
+                    name: name.0.to_string(),
+                    span: name.1,
+                });
+                continue;
+            }
 
-            match node {
-                TypeCheckableDataDefinition::Struct(struct_node) => {
-                    let typed_struct = TypedStruct {
-                        name: struct_node.name.clone(),
-                        fields: self.type_check_fields(&struct_node.fields)?,
-                    };
-                    structs_typed.insert(struct_node.name.clone(), typed_struct);
+            let deprecated = Self::deprecation(rpc_definition.attributes(), METHOD_OPTIONS, errors);
+            let docs = Self::docs(rpc_definition.docs());
+            let options = Self::method_options(rpc_definition, errors);
+
+            match rpc_definition {
+                crate::parsing::RpcDefinitionRaw::Stream {
+                    name,
+                    request,
+                    request_stream,
+                    response,
+                    ..
+                } => {
+                    let request = self.resolve_rpc_request_type(name, request, *request_stream);
+                    let response = self.resolve_rpc_type(name, response);
+
+                    match (request, response) {
+                        (Ok(request), Ok(response)) => {
+                            let typed_rpc = TypedRpcCall::Stream {
+                                name: name.0.to_string(),
+                                request,
+                                request_stream: *request_stream,
+                                response,
+                                deprecated,
+                                docs,
+                                options,
+                            };
+                            rpc_typed.push(typed_rpc);
+                        }
+                        (request, response) => {
+                            errors.extend(request.err());
+                            errors.extend(response.err());
+                        }
+                    }
                 }
-                TypeCheckableDataDefinition::Enum(enum_node) => {
-                    let variants = enum_node.variants.iter().map(|variant| {
-                        let fields = self.type_check_fields(&variant.1.fields).unwrap();
-
-                        TypedEnumVariant {
-                            name: variant.1.name.to_string(),
-                            fields,
+                crate::parsing::RpcDefinitionRaw::Unary {
+                    name,
+                    request,
+                    request_stream,
+                    response,
+                    ..
+                } => {
+                    let request = self.resolve_rpc_request_type(name, request, *request_stream);
+                    let response = self.resolve_rpc_type(name, response);
+
+                    match (request, response) {
+                        (Ok(request), Ok(response)) => {
+                            let typed_rpc = TypedRpcCall::Unary {
+                                name: name.0.to_string(),
+                                request,
+                                request_stream: *request_stream,
+                                response,
+                                deprecated,
+                                docs,
+                                options,
+                            };
+                            rpc_typed.push(typed_rpc);
                         }
-                    });
+                        (request, response) => {
+                            errors.extend(request.err());
+                            errors.extend(response.err());
+                        }
+                    }
+                }
+            };
+        }
 
-                    enums_typed.insert(
-                        enum_node.name.clone(),
-                        TypedEnum {
-                            name: enum_node.name.clone(),
-                            variants: variants.collect(),
-                        },
-                    );
+        rpc_typed
+    }
+
+    // `@idempotent`, `@timeout(10s)` and `@requires("scope", ...)`, `@deprecated` is checked by `deprecation`
+    fn method_options(
+        rpc_definition: &crate::parsing::RpcDefinitionRaw,
+        errors: &mut Vec<TypeCheckError>,
+    ) -> TypedMethodOptions {
+        let mut options = TypedMethodOptions::default();
+        let mut seen = HashSet::new();
+        let method_name = rpc_definition.name().0;
+        let (single_response, request_stream) = match rpc_definition {
+            crate::parsing::RpcDefinitionRaw::Stream { request_stream, .. } => {
+                (false, *request_stream)
+            }
+            crate::parsing::RpcDefinitionRaw::Unary { request_stream, .. } => {
+                (true, *request_stream)
+            }
+        };
+
+        for attribute in rpc_definition.attributes() {
+            let attribute_name = attribute.name.0;
+
+            if !METHOD_OPTIONS.contains(&attribute_name) {
+                continue;
+            }
+
+            if !seen.insert(attribute_name) {
+                errors.push(TypeCheckError::RepeatedAttribute {
+                    name: attribute_name.to_string(),
+                    span: attribute.span,
+                });
+                continue;
+            }
+
+            let invalid_arguments = |expected| TypeCheckError::InvalidAttributeArguments {
+                name: attribute_name.to_string(),
+                expected,
+                span: attribute.span,
+            };
+            let unsupported = |reason| TypeCheckError::UnsupportedMethodOption {
+                name: attribute_name.to_string(),
+                method_name: method_name.to_string(),
+                reason,
+                span: attribute.span,
+            };
+
+            match (attribute_name, attribute.arguments.as_slice()) {
+                ("idempotent", []) if !single_response || request_stream => {
+                    errors.push(unsupported(
+                        "only calls with a single request and a single response can be sent again",
+                    ))
+                }
+                ("idempotent", []) => options.idempotent = true,
+                ("idempotent", _) => errors.push(invalid_arguments("no arguments")),
+                ("timeout", [LiteralRaw::Duration(..)]) if !single_response => {
+                    errors.push(unsupported(
+                        "only calls with a single response can time out",
+                    ));
+                }
+                ("timeout", [LiteralRaw::Duration(value, _)]) => {
+                    match Self::parse_duration(value).filter(|timeout| !timeout.is_zero()) {
+                        Some(timeout) => options.timeout = Some(timeout),
+                        None => errors.push(invalid_arguments(TIMEOUT_ARGUMENTS)),
+                    }
+                }
+                ("timeout", _) => errors.push(invalid_arguments(TIMEOUT_ARGUMENTS)),
+                (_, arguments) => {
+                    let scopes: Option<Vec<String>> = arguments
+                        .iter()
+                        .map(|argument| match argument {
+                            LiteralRaw::String(scope, _) => Some((*scope).to_string()),
+                            _ => None,
+                        })
+                        .collect();
+
+                    match scopes {
+                        Some(scopes) if !scopes.is_empty() => options.requires = scopes,
+                        _ => errors.push(invalid_arguments(
+                            "the scopes that the caller needs as strings",
+                        )),
+                    }
                 }
             }
         }
 
-        let meta_fields = self.type_check_fields(&metadata_fields)?;
-        let rpc = file.rpc().map(|rpc| {
-            let mut rpc_typed = HashMap::new();
-            for rpc_definition in &rpc.definitions {
-                match rpc_definition {
-                    crate::parsing::RpcDefinitionRaw::Stream {
-                        name,
-                        request,
-                        response,
-                    } => {
-                        let typed_rpc = TypedRpcCall::Stream {
-                            name: name.0.to_string(),
-                            request: self.resolve_type(&Self::resolve_raw_type(request)).unwrap(),
-                            response: self
-                                .resolve_type(&Self::resolve_raw_type(response))
-                                .unwrap(),
-                        };
-                        rpc_typed.insert(name.0.to_string(), typed_rpc);
-                    }
-                    crate::parsing::RpcDefinitionRaw::Unary {
+        options
+    }
+
+    // The grammar only accepts digits followed by one of the units
+    fn parse_duration(value: &str) -> Option<Duration> {
+        let (number, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit())?);
+        let number: u64 = number.parse().ok()?;
+
+        match unit {
+            "ms" => Some(Duration::from_millis(number)),
+            "s" => Some(Duration::from_secs(number)),
+            "m" => number.checked_mul(60).map(Duration::from_secs),
+            "h" => number.checked_mul(60 * 60).map(Duration::from_secs),
+            _ => None,
+        }
+    }
+
+    fn resolve_rpc_request_type(
+        &self,
+        method_name: &IdentifierRaw,
+        type_raw: &TypeRaw<'input>,
+        request_stream: bool,
+    ) -> Result<TypedFieldType, TypeCheckError> {
+        let type_id = self.resolve_rpc_type(method_name, type_raw)?;
+
+        if request_stream && type_id == TypedFieldType::Void {
+            return Err(TypeCheckError::VoidRequestStream {
+                method_name: method_name.0.to_string(),
+                span: type_raw.span(),
+            });
+        }
+
+        Ok(type_id)
+    }
+
+    fn resolve_rpc_type(
+        &self,
+        method_name: &IdentifierRaw,
+        type_raw: &TypeRaw<'input>,
+    ) -> Result<TypedFieldType, TypeCheckError> {
+        let type_id = self
+            .resolve_type(&Self::resolve_raw_type(type_raw))
+            .map_err(|error| match error {
+                TypeCheckError::StructNotFound { name, span }
+                | TypeCheckError::UnknownTypeInWrapper { name, span, .. } => {
+                    TypeCheckError::UnknownRpcType {
                         name,
-                        request,
-                        response,
-                    } => {
-                        let typed_rpc = TypedRpcCall::Unary {
-                            name: name.0.to_string(),
-                            request: self.resolve_type(&Self::resolve_raw_type(request)).unwrap(),
-                            response: self
-                                .resolve_type(&Self::resolve_raw_type(response))
-                                .unwrap(),
-                        };
-                        rpc_typed.insert(name.0.to_string(), typed_rpc);
+                        method_name: method_name.0.to_string(),
+                        span,
                     }
-                };
+                }
+                other => other,
+            })?;
+
+        if Self::needs_field_serializer(&type_id) {
+            return Err(TypeCheckError::UnsupportedRpcType {
+                type_name: type_id.to_string(),
+                method_name: method_name.0.to_string(),
+                span: type_raw.span(),
+            });
+        }
+
+        Ok(type_id)
+    }
+
+    // Instants, dates and decimals are only serialized in their format by the generated code when
+    // they are in a field, requests and responses are serialized as they are. Durations are
+    // serialized the same either way
+    fn needs_field_serializer(type_id: &TypedFieldType) -> bool {
+        match type_id {
+            TypedFieldType::Instant | TypedFieldType::Date | TypedFieldType::Decimal => true,
+            TypedFieldType::Optional(inner)
+            | TypedFieldType::Array(inner)
+            | TypedFieldType::Map(_, inner) => Self::needs_field_serializer(inner),
+            _ => false,
+        }
+    }
+
+    fn referenced_definition<'a>(
+        field_type: &TypeCheckableFieldType<'a>,
+    ) -> Option<(&'a str, ReferenceKind, Span)> {
+        match field_type {
+            TypeCheckableFieldType::ToBeResolved(name, span) => {
+                Some((name, ReferenceKind::Direct, *span))
             }
+            // An optional array is still stored on the heap
+            TypeCheckableFieldType::Optional(inner) => {
+                Self::referenced_definition(inner).map(|(name, kind, span)| match kind {
+                    ReferenceKind::Array => (name, ReferenceKind::Array, span),
+                    _ => (name, ReferenceKind::Optional, span),
+                })
+            }
+            // Map keys can only be scalars, so only the values can reference definitions
+            TypeCheckableFieldType::Array(inner) | TypeCheckableFieldType::Map(_, inner, _) => {
+                Self::referenced_definition(inner)
+                    .map(|(name, _, span)| (name, ReferenceKind::Array, span))
+            }
+            _ => None,
+        }
+    }
+
+    /// Finds definitions that contain themselves, which is only allowed through `?` and `[]`,
+    /// and marks the optional references closing such cycles as indirect.
+    fn check_references(&mut self) -> Vec<TypeCheckError> {
+        let mut graph: ReferenceGraph = DiGraph::new();
+        let mut node_ids = HashMap::new();
+        for name in self.structs.keys().chain(self.enums.keys()) {
+            let name = name.as_str();
+            node_ids.insert(name, graph.add_node(name));
+        }
 
-            rpc_typed
+        let struct_fields = self.structs.values().map(|s| {
+            (
+                s.name.as_str(),
+                s.fields
+                    .values()
+                    .map(|field| &field.type_)
+                    .collect::<Vec<_>>(),
+            )
+        });
+        let enum_fields = self.enums.values().map(|e| {
+            (
+                e.name.as_str(),
+                e.variants
+                    .values()
+                    .flat_map(|variant| variant.fields.values())
+                    .map(|field| &field.type_)
+                    .collect(),
+            )
         });
 
-        Ok(TypedFile {
-            structs: structs_typed.into_values().collect(),
-            enums: enums_typed.into_values().collect(),
-            meta: TypedMetadata {
-                fields: meta_fields,
-            },
-            rpc: TypedRpc {
-                calls: rpc.map_or_else(Vec::new, |rpc| rpc.into_values().collect()),
-            },
-        })
+        for (owner, fields) in struct_fields.chain(enum_fields) {
+            for field_type in fields {
+                let Some((target, kind, span)) = Self::referenced_definition(field_type) else {
+                    continue;
+                };
+
+                // Types that do not exist are reported when the fields are resolved
+                if let Some(target) = node_ids.get(target) {
+                    graph.add_edge(node_ids[owner], *target, (kind, span));
+                }
+            }
+        }
+
+        let mut errors = vec![];
+        let direct_graph = graph.filter_map(
+            |_, node| Some(*node),
+            |_, edge| (edge.0 == ReferenceKind::Direct).then_some(*edge),
+        );
+        for component in tarjan_scc(&direct_graph) {
+            if let Some((cycle, span)) = Self::find_cycle(&direct_graph, &component) {
+                errors.push(TypeCheckError::CyclicDefinition { cycle, span });
+            }
+        }
+
+        // Arrays are always stored on the heap, so they do not contribute to the size of a definition
+        let sized_graph = graph.filter_map(
+            |_, node| Some(*node),
+            |_, edge| (edge.0 != ReferenceKind::Array).then_some(*edge),
+        );
+        let mut component_ids = HashMap::new();
+        for (component_id, component) in tarjan_scc(&sized_graph).into_iter().enumerate() {
+            for node in component {
+                component_ids.insert(node, component_id);
+            }
+        }
+
+        let indirect_references = sized_graph
+            .edge_references()
+            .filter(|edge| {
+                edge.weight().0 == ReferenceKind::Optional
+                    && component_ids.get(&edge.source()) == component_ids.get(&edge.target())
+            })
+            .map(|edge| {
+                (
+                    sized_graph[edge.source()].to_string(),
+                    sized_graph[edge.target()].to_string(),
+                )
+            })
+            .collect();
+
+        self.indirect_references = indirect_references;
+
+        errors
+    }
+
+    /// Returns the names of the definitions making up the shortest cycle in a strongly connected
+    /// component, starting and ending at the same definition, together with the span of the first reference.
+    fn find_cycle(graph: &ReferenceGraph, component: &[NodeIndex]) -> Option<(Vec<String>, Span)> {
+        let start = *component.iter().min()?;
+        let mut predecessors: HashMap<NodeIndex, (NodeIndex, Span)> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(node) = queue.pop_front() {
+            for edge in graph.edges(node) {
+                let target = edge.target();
+
+                if target == start {
+                    let mut span = edge.weight().1;
+                    let mut path = vec![node];
+                    let mut current = node;
+                    while current != start {
+                        let (previous, previous_span) = predecessors[&current];
+                        span = previous_span;
+                        current = previous;
+                        path.push(current);
+                    }
+                    path.reverse();
+
+                    let mut cycle: Vec<String> =
+                        path.iter().map(|ix| graph[*ix].to_string()).collect();
+                    cycle.push(graph[start].to_string());
+
+                    return Some((cycle, span));
+                }
+
+                if component.contains(&target) && !predecessors.contains_key(&target) {
+                    predecessors.insert(target, (node, edge.weight().1));
+                    queue.push_back(target);
+                }
+            }
+        }
+
+        None
     }
 
     fn map_enum_variants(
         variants: &[EnumVariantRaw<'input>],
         name: &str,
-    ) -> Result<HashMap<&'input str, TypeCheckableEnumVariant<'input>>, TypeCheckError> {
-        variants
-            .iter()
-            .map(|variant| {
-                let fields = Self::map_fields(&variant.fields, name)?;
-                Ok((
-                    variant.name.0,
-                    TypeCheckableEnumVariant {
-                        name: variant.name.0.to_string(),
-                        fields,
-                    },
-                ))
-            })
-            .collect()
+        errors: &mut Vec<TypeCheckError>,
+    ) -> IndexMap<&'input str, TypeCheckableEnumVariant<'input>> {
+        let mut mapped_variants = IndexMap::new();
+
+        for variant in variants {
+            Self::check_identifier(&variant.name, errors);
+
+            if mapped_variants.contains_key(variant.name.0) {
+                errors.push(TypeCheckError::RepeatedVariantName {
+                    variant_name: variant.name.0.to_string(),
+                    enum_name: name.to_string(),
+                    span: variant.name.1,
+                });
+                continue;
+            }
+
+            let fields = Self::map_fields(&variant.fields, name, errors);
+            mapped_variants.insert(
+                variant.name.0,
+                TypeCheckableEnumVariant {
+                    name: variant.name.0.to_string(),
+                    fields,
+                    deprecated: Self::deprecation(&variant.attributes, &[], errors),
+                    docs: Self::docs(&variant.docs),
+                    unit: variant.unit,
+                },
+            );
+        }
+
+        mapped_variants
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parsing;
+
+    fn check(input: &str) -> Result<TypedFile, Vec<TypeCheckError>> {
+        let ast = parsing::grammar::RFileParser::new().parse(input).unwrap();
+
+        TypeChecker::new().check(&ast)
+    }
+
+    fn field_type<'a>(
+        file: &'a TypedFile,
+        struct_name: &str,
+        field_name: &str,
+    ) -> &'a TypedFieldType {
+        file.structs
+            .iter()
+            .find(|s| s.name() == struct_name)
+            .and_then(|s| s.fields().iter().find(|f| f.name() == field_name))
+            .map(TypedField::type_name)
+            .unwrap()
+    }
+
+    #[test]
+    pub fn preserves_declaration_order() {
+        let file = check(
+            "metadata { z: u8, a: u8 } \
+            struct Zebra { z: u8, y: u8, x: u8 } struct Aardvark { c: u8, b: u8, a: u8 } \
+            enum Order { Third, First(z: u8, a: u8), Second } \
+            rpc { zulu(Zebra) -> Zebra; alpha(Aardvark) -> stream Aardvark; mike(void) -> void; }",
+        )
+        .unwrap();
+
+        let structs: Vec<_> = file.structs.iter().map(TypedStruct::name).collect();
+        let fields: Vec<_> = file.structs[0]
+            .fields()
+            .iter()
+            .map(TypedField::name)
+            .collect();
+        let variants: Vec<_> = file.enums[0]
+            .variants()
+            .iter()
+            .map(TypedEnumVariant::name)
+            .collect();
+        let variant_fields: Vec<_> = file.enums[0].variants()[1]
+            .fields()
+            .iter()
+            .map(TypedField::name)
+            .collect();
+        let meta_fields: Vec<_> = file.meta.fields().iter().map(TypedField::name).collect();
+        let calls: Vec<_> = file
+            .rpc
+            .calls()
+            .iter()
+            .map(|call| match call {
+                TypedRpcCall::Stream { name, .. } | TypedRpcCall::Unary { name, .. } => {
+                    name.as_str()
+                }
+            })
+            .collect();
+
+        assert_eq!(structs, ["Zebra", "Aardvark"]);
+        assert_eq!(fields, ["z", "y", "x"]);
+        assert_eq!(variants, ["Third", "First", "Second"]);
+        assert_eq!(variant_fields, ["z", "a"]);
+        assert_eq!(meta_fields, ["z", "a"]);
+        assert_eq!(calls, ["zulu", "alpha", "mike"]);
+    }
+
+    #[test]
+    pub fn rejects_cyclic_definitions() {
+        let errors = check("struct A { b: B } struct B { a: A }").err().unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            TypeCheckError::CyclicDefinition { cycle, span }
+                if cycle == &["A", "B", "A"] && *span == Span::new(14, 15)
+        ));
+    }
+
+    #[test]
+    pub fn rejects_self_referencing_definitions() {
+        let errors = check("struct A {} enum E { X(e: E) }").err().unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            TypeCheckError::CyclicDefinition { cycle, .. } if cycle == &["E", "E"]
+        ));
+    }
+
+    #[test]
+    pub fn recursion_through_optional_is_indirect() {
+        let file = check("struct A { b: B } struct B { a: A?, c: C? } struct C {}").unwrap();
+
+        assert_eq!(
+            field_type(&file, "B", "a"),
+            &TypedFieldType::Optional(Box::new(TypedFieldType::Indirect(Box::new(
+                TypedFieldType::OtherStruct("A".to_string())
+            ))))
+        );
+        assert_eq!(
+            field_type(&file, "B", "c"),
+            &TypedFieldType::Optional(Box::new(TypedFieldType::OtherStruct("C".to_string())))
+        );
+        assert_eq!(
+            field_type(&file, "A", "b"),
+            &TypedFieldType::OtherStruct("B".to_string())
+        );
+    }
+
+    #[test]
+    pub fn recursion_through_nested_optionals_is_indirect() {
+        let file = check("struct A { next: A??, x: u8 }").unwrap();
+
+        assert_eq!(
+            field_type(&file, "A", "next"),
+            &TypedFieldType::Optional(Box::new(TypedFieldType::Optional(Box::new(
+                TypedFieldType::Indirect(Box::new(TypedFieldType::OtherStruct("A".to_string())))
+            ))))
+        );
+    }
+
+    #[test]
+    pub fn recursion_through_array_is_allowed() {
+        let file = check("struct Node { children: Node[] }").unwrap();
+
+        assert_eq!(
+            field_type(&file, "Node", "children"),
+            &TypedFieldType::Array(Box::new(TypedFieldType::OtherStruct("Node".to_string())))
+        );
+    }
+
+    #[test]
+    pub fn rejects_unknown_rpc_types() {
+        let errors = check("struct A {} rpc { call(B) -> A; stream_call(A) -> stream C?; }")
+            .err()
+            .unwrap();
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            TypeCheckError::UnknownRpcType { name, method_name, .. } if name == "B" && method_name == "call"
+        )));
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            TypeCheckError::UnknownRpcType { name, method_name, .. } if name == "C" && method_name == "stream_call"
+        )));
+    }
+
+    #[test]
+    pub fn rejects_unknown_types_inside_wrappers() {
+        let errors = check("struct A { b: B[] } enum E { X(c: C?) }")
+            .err()
+            .unwrap();
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            TypeCheckError::UnknownTypeInWrapper { name, wrapped, .. } if name == "B" && wrapped == "B[]"
+        )));
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            TypeCheckError::UnknownTypeInWrapper { name, wrapped, .. } if name == "C" && wrapped == "C?"
+        )));
+    }
+
+    #[test]
+    pub fn builtin_types_are_resolved() {
+        for name in BUILTIN_TYPES {
+            let type_ = TypeChecker::raw_field_type_to_typecheckable_type(&IdentifierRaw(
+                name,
+                Span::default(),
+            ));
+
+            assert!(
+                !matches!(type_, TypeCheckableFieldType::ToBeResolved(..)),
+                "{name} is not built in"
+            );
+        }
+    }
+
+    #[test]
+    pub fn resolves_nested_types() {
+        let file = check("struct A { tags: map<string, string[]>, b: map<u32, A?>[]? }").unwrap();
+
+        assert_eq!(
+            field_type(&file, "A", "tags"),
+            &TypedFieldType::Map(
+                Box::new(TypedFieldType::String),
+                Box::new(TypedFieldType::Array(Box::new(TypedFieldType::String)))
+            )
+        );
+        assert_eq!(field_type(&file, "A", "b").to_string(), "map<u32, A?>[]?");
+    }
+
+    #[test]
+    pub fn rejects_unknown_types_inside_nested_types() {
+        let errors = check("struct A { b: map<string, B[]>? }").err().unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            TypeCheckError::UnknownTypeInWrapper { name, wrapped, span }
+                if name == "B" && wrapped == "map<string, B[]>?" && *span == Span::new(26, 27)
+        ));
+    }
+
+    #[test]
+    pub fn rejects_invalid_map_keys() {
+        let errors = check("struct A { b: map<A, u8>, c: map<string?, u8>, d: map<guid, u8> }")
+            .err()
+            .unwrap();
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            &errors[0],
+            TypeCheckError::InvalidMapKey { type_name, span } if type_name == "A" && *span == Span::new(18, 19)
+        ));
+        assert!(matches!(
+            &errors[1],
+            TypeCheckError::InvalidMapKey { type_name, .. } if type_name == "string?"
+        ));
+    }
+
+    #[test]
+    pub fn recursion_through_map_is_allowed() {
+        let file = check("struct Node { children: map<string, Node>? }").unwrap();
+
+        assert_eq!(
+            field_type(&file, "Node", "children").to_string(),
+            "map<string, Node>?"
+        );
+    }
+
+    #[test]
+    pub fn rejects_repeated_struct_names() {
+        let errors = check("struct A {} struct A {}").err().unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [TypeCheckError::RepeatedName { name, span }] if name == "A" && *span == Span::new(19, 20)
+        ));
+    }
+
+    #[test]
+    pub fn rejects_repeated_enum_names() {
+        let errors = check("struct A {} enum E { X } enum E { Y }")
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [TypeCheckError::RepeatedEnumName { name, .. }] if name == "E"
+        ));
+    }
+
+    #[test]
+    pub fn rejects_enums_named_like_structs() {
+        let errors = check("struct A {} enum A { X }").err().unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [TypeCheckError::StructEnumNameConflict { name, .. }] if name == "A"
+        ));
+    }
+
+    #[test]
+    pub fn rejects_repeated_variant_names() {
+        let errors = check("struct A {} enum E { X, Y(a: u8), X(b: u8) }")
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [TypeCheckError::RepeatedVariantName { variant_name, enum_name, .. }]
+                if variant_name == "X" && enum_name == "E"
+        ));
+    }
+
+    #[test]
+    pub fn rejects_repeated_rpc_names() {
+        let errors = check("struct A {} rpc { call(A) -> A; call(A) -> stream A; }")
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [TypeCheckError::RepeatedRpcName { name, .. }] if name == "call"
+        ));
+    }
+
+    #[test]
+    pub fn checks_services() {
+        let file = check(
+            "struct A {} /// The library\nservice Library { call(A) -> A; list(void) -> stream A; } service Playback { call(A) -> void; }",
+        )
+        .unwrap();
+
+        assert!(file.rpc.calls().is_empty());
+        let names: Vec<_> = file.services.iter().map(TypedService::name).collect();
+        assert_eq!(names, ["Library", "Playback"]);
+        assert_eq!(file.services[0].docs(), ["The library"]);
+        let methods: Vec<_> = file.methods().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(methods, ["Library.call", "Library.list", "Playback.call"]);
+    }
+
+    #[test]
+    pub fn checks_request_streams() {
+        let file =
+            check("struct A {} rpc { upload(stream A) -> A; list(A) -> stream A; }").unwrap();
+        let request_streams: Vec<_> = file
+            .rpc
+            .calls()
+            .iter()
+            .map(TypedRpcCall::request_stream)
+            .collect();
+        assert_eq!(request_streams, [true, false]);
+
+        let errors = check("struct A {} rpc { upload(stream void) -> void; }")
+            .err()
+            .unwrap();
+        assert_eq!(
+            errors[0].to_string(),
+            "The RPC method \"upload\" cannot take a stream of void requests"
+        );
+        assert_eq!(errors[0].span(), Span::new(32, 36));
+    }
+
+    #[test]
+    pub fn checks_method_options() {
+        let file = check(
+            "struct A {} rpc { @idempotent @timeout(10s) @requires(\"admin\", \"write\") get(A) -> A; @timeout(2m) @deprecated(\"slow\") list(A) -> A[]; }",
+        )
+        .unwrap();
+        let calls = file.rpc.calls();
+        assert_eq!(
+            calls[0].options(),
+            &TypedMethodOptions {
+                idempotent: true,
+                timeout: Some(Duration::from_secs(10)),
+                requires: vec!["admin".to_string(), "write".to_string()],
+            }
+        );
+        assert_eq!(calls[1].options().timeout, Some(Duration::from_secs(120)));
+        assert_eq!(calls[1].deprecated(), Some("slow"));
+
+        let errors = check(
+            "struct A {} rpc { @idempotent watch(A) -> stream A; @timeout(5s) list(A) -> stream A; @idempotent upload(stream A) -> A; @timeout(0s) a(A) -> A; @requires b(A) -> A; @timeout(1s) @timeout(2s) c(A) -> A; }",
+        )
+        .err()
+        .unwrap();
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "The attribute \"@idempotent\" cannot be used on the RPC method \"watch\", only calls with a single request and a single response can be sent again",
+                "The attribute \"@timeout\" cannot be used on the RPC method \"list\", only calls with a single response can time out",
+                "The attribute \"@idempotent\" cannot be used on the RPC method \"upload\", only calls with a single request and a single response can be sent again",
+                "The attribute \"@timeout\" expects a positive duration, like 500ms, 10s, 5m or 1h",
+                "The attribute \"@requires\" expects the scopes that the caller needs as strings",
+                "The attribute \"@timeout\" is used more than once",
+            ]
+        );
+        assert_eq!(errors[0].span(), Span::new(18, 29));
+    }
+
+    #[test]
+    pub fn rejects_invalid_services() {
+        let errors = check(
+            "struct A {} rpc { a(A) -> A; } service S { a(A) -> A; a(B) -> A; } service S {} service Match {}",
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [
+                TypeCheckError::RpcOutsideOfService { .. },
+                TypeCheckError::RepeatedRpcName { name: method, .. },
+                TypeCheckError::RepeatedServiceName { name: service, .. },
+                TypeCheckError::ReservedIdentifier { name: module, .. },
+            ] if method == "a" && service == "S" && module == "match"
+        ));
+    }
+
+    #[test]
+    pub fn rejects_reserved_identifiers() {
+        let errors = check("struct type { match: u8 } enum E { Self } rpc { fn(type) -> type; }")
+            .err()
+            .unwrap();
+        let mut names: Vec<_> = errors
+            .iter()
+            .map(|e| match e {
+                TypeCheckError::ReservedIdentifier { name, .. } => name.as_str(),
+                other => panic!("Unexpected error {other:?}"),
+            })
+            .collect();
+        names.sort_unstable();
+
+        assert_eq!(names, ["Self", "fn", "match", "type"]);
+    }
+
+    #[test]
+    pub fn rejects_invalid_identifiers() {
+        let errors = check("struct A { fx-1: u8, 1x: u8, _: u8, _ok: u8 }")
+            .err()
+            .unwrap();
+        let mut names: Vec<_> = errors
+            .iter()
+            .map(|e| match e {
+                TypeCheckError::InvalidIdentifier { name, .. } => name.as_str(),
+                other => panic!("Unexpected error {other:?}"),
+            })
+            .collect();
+        names.sort_unstable();
+
+        assert_eq!(names, ["1x", "_", "fx-1"]);
+    }
+
+    #[test]
+    pub fn resolves_imported_types() {
+        let events =
+            check("struct FileOnMountPath { path: string } enum Event { Created }").unwrap();
+        let ast = parsing::grammar::RFileParser::new()
+            .parse("import \"events.evd\" { FileOnMountPath, Event } struct Track { path: FileOnMountPath, events: Event[] }")
+            .unwrap();
+        let file = TypeChecker::new()
+            .with_import("events.evd", "events", &events)
+            .check(&ast)
+            .unwrap();
+
+        assert_eq!(
+            field_type(&file, "Track", "path"),
+            &TypedFieldType::Imported {
+                module: "events".to_string(),
+                name: "FileOnMountPath".to_string()
+            }
+        );
+        assert_eq!(
+            field_type(&file, "Track", "events"),
+            &TypedFieldType::Array(Box::new(TypedFieldType::Imported {
+                module: "events".to_string(),
+                name: "Event".to_string()
+            }))
+        );
+    }
+
+    #[test]
+    pub fn rejects_invalid_imports() {
+        let events = check("struct FileOnMountPath { path: string }").unwrap();
+        let ast = parsing::grammar::RFileParser::new()
+            .parse("import \"events.evd\" { FileOnMountPath, Missing } import \"other.evd\" { A } struct FileOnMountPath {}")
+            .unwrap();
+        let errors = TypeChecker::new()
+            .with_import("events.evd", "events", &events)
+            .check(&ast)
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [
+                TypeCheckError::ImportedTypeNotFound { name, .. },
+                TypeCheckError::UnknownImport { path, .. },
+                TypeCheckError::RepeatedName { name: repeated, .. },
+            ] if name == "Missing" && path == "other.evd" && repeated == "FileOnMountPath"
+        ));
+    }
+
+    #[test]
+    pub fn checks_default_values() {
+        let file =
+            check("struct A { a: u8 = 255, b: s8? = -128, c: string = \"x\", d: u64, e: bool = true, f: f32 = -0.5, g: f64 = 2 }")
+                .unwrap();
+        let defaults: Vec<_> = file.structs[0]
+            .fields()
+            .iter()
+            .map(TypedField::default)
+            .collect();
+
+        assert_eq!(
+            defaults,
+            [
+                Some(&TypedValue::Integer(255)),
+                Some(&TypedValue::Integer(-128)),
+                Some(&TypedValue::String("x".to_string())),
+                None,
+                Some(&TypedValue::Boolean(true)),
+                Some(&TypedValue::Float(-0.5)),
+                Some(&TypedValue::Float(2.0)),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn rejects_invalid_default_values() {
+        let errors = check(
+            "struct A { a: u8 = 256, b: u32 = -1, c: string = 1, d: guid = \"x\", e: bool = 1, f: u8 = 1.5, g: f32 = false }",
+        )
+        .err()
+        .unwrap();
+
+        assert_eq!(errors.len(), 7);
+        assert!(errors
+            .iter()
+            .all(|error| matches!(error, TypeCheckError::InvalidDefault { .. })));
+        assert_eq!(
+            errors[0].to_string(),
+            "The default value of the field \"a\" is not a valid u8"
+        );
+        assert_eq!(errors[0].span(), Span::new(19, 22));
+    }
+
+    #[test]
+    pub fn resolves_scalar_types() {
+        let file = check(
+            "struct A { a: s32, b: f32, c: f64, d: bool, e: duration, f: date?, g: decimal[] }",
+        )
+        .unwrap();
+        let types: Vec<_> = file.structs[0]
+            .fields()
+            .iter()
+            .map(|field| field.type_name().to_string())
+            .collect();
+
+        assert_eq!(
+            types,
+            [
+                "s32",
+                "f32",
+                "f64",
+                "bool",
+                "duration",
+                "date?",
+                "decimal[]"
+            ]
+        );
+    }
+
+    #[test]
+    pub fn rejects_instants_dates_and_decimals_used_directly_by_rpc() {
+        let errors = check("struct A { d: date } rpc { a(A) -> A; b(date[]) -> void; c(void) -> stream instant?; d(decimal) -> duration; }")
+            .err()
+            .unwrap();
+
+        // Durations are serialized the same in fields and outside of them
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            errors[0].to_string(),
+            "The type \"date[]\" cannot be used directly by RPC method \"b\", wrap it in a struct"
+        );
+        assert_eq!(errors[0].span(), Span::new(40, 44));
+        assert!(matches!(
+            &errors[1],
+            TypeCheckError::UnsupportedRpcType { method_name, .. } if method_name == "c"
+        ));
+        assert!(matches!(
+            &errors[2],
+            TypeCheckError::UnsupportedRpcType { type_name, method_name, .. }
+                if type_name == "decimal" && method_name == "d"
+        ));
+    }
+
+    #[test]
+    pub fn collects_deprecations() {
+        let file = check(
+            "struct A { @deprecated(\"a\") a: u8 } enum E { @deprecated(\"x\") X } rpc { @deprecated(\"m\") m(A) -> A; }",
+        )
+        .unwrap();
+
+        assert_eq!(file.structs[0].fields()[0].deprecated(), Some("a"));
+        assert_eq!(file.enums[0].variants()[0].deprecated(), Some("x"));
+        assert_eq!(file.rpc.calls()[0].deprecated(), Some("m"));
+    }
+
+    #[test]
+    pub fn collects_docs() {
+        let file = check(
+            "/// A struct\n///\n///with two paragraphs\nstruct A {\n    /// A field\n    a: u8\n}\n\
+             /// An enum\nenum E {\n    /// A variant\n    X\n}\n\
+             rpc {\n    /// A method\n    m(A) -> A;\n}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            file.structs[0].docs(),
+            ["A struct", "", "with two paragraphs"]
+        );
+        assert_eq!(file.structs[0].fields()[0].docs(), ["A field"]);
+        assert_eq!(file.enums[0].docs(), ["An enum"]);
+        assert_eq!(file.enums[0].variants()[0].docs(), ["A variant"]);
+        assert_eq!(file.rpc.calls()[0].docs(), ["A method"]);
+    }
+
+    #[test]
+    pub fn checks_enum_tagging_and_unit_variants() {
+        let file = check(
+            "struct A {} enum E { X, Y(), Z(a: u8) } @tag(\"type\") enum F { X } @tag(\"t\", \"c\") enum G { X }",
+        )
+        .unwrap();
+
+        let units: Vec<_> = file.enums[0]
+            .variants()
+            .iter()
+            .map(TypedEnumVariant::is_unit)
+            .collect();
+        assert_eq!(units, [true, false, false]);
+        assert_eq!(file.enums[0].tagging(), &TypedEnumTagging::External);
+        assert_eq!(
+            file.enums[1].tagging(),
+            &TypedEnumTagging::Internal {
+                tag: "type".to_string()
+            }
+        );
+        assert_eq!(
+            file.enums[2].tagging(),
+            &TypedEnumTagging::Adjacent {
+                tag: "t".to_string(),
+                content: "c".to_string()
+            }
+        );
+    }
+
+    #[test]
+    pub fn rejects_invalid_tagging() {
+        let errors = check(
+            "struct A {} @tag(\"kind\") enum E { X(kind: u8) } @tag(\"t\", \"t\") enum F { X } \
+             @tag @deprecated(\"x\") enum G { X } @tag(\"a\") @tag(\"b\") enum H { X }",
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [
+                TypeCheckError::TagConflict { tag, variant_name, .. },
+                TypeCheckError::InvalidAttributeArguments { .. },
+                TypeCheckError::UnknownAttribute { name, .. },
+                TypeCheckError::InvalidAttributeArguments { .. },
+                TypeCheckError::RepeatedAttribute { .. },
+            ] if tag == "kind" && variant_name == "X" && name == "deprecated"
+        ));
+    }
+
+    #[test]
+    pub fn checks_definition_options() {
+        let file = check(
+            "@derive(\"Eq\", \"Hash\") @rename_all(\"camelCase\") @non_exhaustive struct A { mount_id: string, played_at: instant? } \
+             @derive(\"Default\") struct B { a: A[], at: instant?, count: u8 = 3, gain: f64 } \
+             @rename_all(\"SCREAMING-KEBAB-CASE\") @derive(\"PartialEq\") enum E { FileMoved, Deleted(a: A) }",
+        )
+        .unwrap();
+
+        let a = file.structs[0].options();
+        assert_eq!(
+            a.derives,
+            [TypedDerive::PartialEq, TypedDerive::Eq, TypedDerive::Hash].into()
+        );
+        assert_eq!(a.rename_all, Some(TypedRenameRule::CamelCase));
+        assert!(a.non_exhaustive);
+        assert_eq!(file.structs[0].fields[0].serialized_name(), "mountId");
+        assert_eq!(file.structs[0].fields[1].serialized_name(), "playedAt");
+        assert_eq!(file.structs[1].fields[1].serialized_name(), "at");
+        assert_eq!(file.enums[0].variants[0].serialized_name(), "FILE-MOVED");
+        // Only the variants of enums are renamed, like serde does
+        assert_eq!(file.enums[0].variants[1].fields[0].serialized_name(), "a");
+    }
+
+    #[test]
+    pub fn rejects_invalid_definition_options() {
+        let errors = check(
+            "@derive(\"Eq\") struct A { gain: f64 } @derive(\"Hash\") struct B { a: A? } \
+             @derive(\"Default\") struct C { at: instant } @derive(\"Ord\") @rename_all(\"Title Case\") struct D {} \
+             @rename_all(\"camelCase\") struct F { a_b: u8, aB: u8 } @tag(\"type\") @non_exhaustive(true) struct G {} \
+             @derive(\"Default\") enum E { X }",
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [
+                TypeCheckError::InvalidAttributeArguments { name: derive, .. },
+                TypeCheckError::InvalidAttributeArguments { name: rename_all, .. },
+                TypeCheckError::RepeatedFieldName { field_name, .. },
+                TypeCheckError::UnknownAttribute { name: tag, .. },
+                TypeCheckError::InvalidAttributeArguments { .. },
+                TypeCheckError::UnsupportedDerive { .. },
+                TypeCheckError::MissingDerive { derive: eq, field_name: gain, .. },
+                TypeCheckError::MissingDerive { derive: hash, .. },
+                TypeCheckError::MissingDerive { derive: default, .. },
+            ] if derive == "derive"
+                && rename_all == "rename_all"
+                && field_name == "aB"
+                && tag == "tag"
+                && eq == "Eq"
+                && gain == "gain"
+                && hash == "Hash"
+                && default == "Default"
+        ));
+    }
+
+    #[test]
+    pub fn checks_derives_of_imported_types() {
+        let events =
+            check("@derive(\"Hash\") struct FileOnMountPath { path: string } struct Other {}")
+                .unwrap();
+        let ast = parsing::grammar::RFileParser::new()
+            .parse("import \"events.evd\" { FileOnMountPath, Other } @derive(\"Hash\") struct A { path: FileOnMountPath } @derive(\"Hash\") struct B { other: Other }")
+            .unwrap();
+        let errors = TypeChecker::new()
+            .with_import("events.evd", "events", &events)
+            .check(&ast)
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [TypeCheckError::MissingDerive { name, .. }] if name == "B"
+        ));
+    }
+
+    #[test]
+    pub fn rename_rules_match_serde() {
+        let cases = [
+            (
+                TypedRenameRule::Lowercase,
+                "played_at",
+                "played_at",
+                "FileMoved",
+                "filemoved",
+            ),
+            (
+                TypedRenameRule::Uppercase,
+                "played_at",
+                "PLAYED_AT",
+                "FileMoved",
+                "FILEMOVED",
+            ),
+            (
+                TypedRenameRule::PascalCase,
+                "played_at",
+                "PlayedAt",
+                "FileMoved",
+                "FileMoved",
+            ),
+            (
+                TypedRenameRule::CamelCase,
+                "played_at",
+                "playedAt",
+                "FileMoved",
+                "fileMoved",
+            ),
+            (
+                TypedRenameRule::SnakeCase,
+                "played_at",
+                "played_at",
+                "FileMoved",
+                "file_moved",
+            ),
+            (
+                TypedRenameRule::ScreamingSnakeCase,
+                "played_at",
+                "PLAYED_AT",
+                "FileMoved",
+                "FILE_MOVED",
+            ),
+            (
+                TypedRenameRule::KebabCase,
+                "played_at",
+                "played-at",
+                "FileMoved",
+                "file-moved",
+            ),
+            (
+                TypedRenameRule::ScreamingKebabCase,
+                "played_at",
+                "PLAYED-AT",
+                "FileMoved",
+                "FILE-MOVED",
+            ),
+        ];
+
+        for (rule, field, renamed_field, variant, renamed_variant) in cases {
+            assert_eq!(rule.apply_to_field(field), renamed_field);
+            assert_eq!(rule.apply_to_variant(variant), renamed_variant);
+        }
+    }
+
+    #[test]
+    pub fn checks_constants() {
+        let file = check(
+            "/// The port\nconst DEFAULT_PORT: u16 = 7654; const RATIO: f32 = 2; const NAME: string = \"x\"; struct A {}",
+        )
+        .unwrap();
+
+        assert_eq!(file.constants.len(), 3);
+        assert_eq!(file.constants[0].name(), "DEFAULT_PORT");
+        assert_eq!(file.constants[0].type_name(), &TypedFieldType::U16);
+        assert_eq!(file.constants[0].value(), &TypedValue::Integer(7654));
+        assert_eq!(file.constants[0].docs(), ["The port"]);
+        assert_eq!(file.constants[1].value(), &TypedValue::Float(2.0));
+        assert_eq!(
+            file.constants[2].value(),
+            &TypedValue::String("x".to_string())
+        );
+    }
+
+    #[test]
+    pub fn rejects_invalid_constants() {
+        let errors = check(
+            "const A: u8 = 256; const A: u8 = 1; const B: guid = \"x\"; const C: A = 1; const D: B = 1; struct A {}",
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [
+                TypeCheckError::InvalidConstantValue { name, .. },
+                TypeCheckError::RepeatedConstantName { .. },
+                TypeCheckError::UnsupportedConstantType { type_name, .. },
+                TypeCheckError::UnsupportedConstantType { .. },
+                TypeCheckError::StructNotFound { .. },
+            ] if name == "A" && type_name == "guid"
+        ));
+    }
+
+    #[test]
+    pub fn rejects_invalid_attributes() {
+        let errors = check(
+            "struct A { @unknown a: u8, @deprecated b: u8, @deprecated(\"x\") @deprecated(\"y\") c: u8 }",
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [
+                TypeCheckError::UnknownAttribute { name, .. },
+                TypeCheckError::InvalidAttributeArguments { .. },
+                TypeCheckError::RepeatedAttribute { .. },
+            ] if name == "unknown"
+        ));
+    }
+
+    #[test]
+    pub fn checks_constraints() {
+        let file = check(
+            "struct A { id: string @pattern(\"[a-z0-9-]+\") @max_length(64), tags: string[]? @non_empty, \
+             port: u16 @min(1), gain: f32 @min(-12) @max(6.5) }",
+        )
+        .unwrap();
+        let fields = file.structs[0].fields();
+
+        assert_eq!(
+            fields[0].constraints(),
+            &TypedConstraints {
+                pattern: Some("[a-z0-9-]+".to_string()),
+                max_length: Some(64),
+                ..TypedConstraints::default()
+            }
+        );
+        assert_eq!(fields[1].constraints().min_length, Some(1));
+        assert_eq!(fields[2].constraints().min, Some(TypedValue::Integer(1)));
+        assert_eq!(fields[2].constraints().max, None);
+        assert_eq!(fields[3].constraints().min, Some(TypedValue::Float(-12.0)));
+        assert_eq!(fields[3].constraints().max, Some(TypedValue::Float(6.5)));
+    }
+
+    #[test]
+    pub fn rejects_invalid_constraints() {
+        let errors = check(
+            "metadata { m: string @non_empty } \
+             struct A { a: u8 @pattern(\"x\"), b: string @pattern(\"(\"), c: u8 @min(-1), \
+             d: string @min_length(3) @max_length(2), e: u8[] @non_empty @min_length(2), \
+             @max(1) f: u8, g: u8 @other }",
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            &errors[..],
+            [
+                TypeCheckError::MisplacedConstraint { .. },
+                TypeCheckError::MisplacedConstraint { .. },
+                TypeCheckError::UnsupportedConstraint { .. },
+                TypeCheckError::InvalidPattern { .. },
+                TypeCheckError::InvalidAttributeArguments { .. },
+                TypeCheckError::UnsatisfiableConstraints { .. },
+                TypeCheckError::RepeatedAttribute { .. },
+                TypeCheckError::UnknownAttribute { .. },
+            ]
+        ));
+        assert_eq!(
+            errors[2].to_string(),
+            "The constraint \"@pattern\" cannot be used on a field of type \"u8\""
+        );
+        assert_eq!(
+            errors[3].to_string(),
+            "\"(\" is not a valid regular expression"
+        );
+        assert_eq!(
+            errors[5].to_string(),
+            "The constraints of the field \"d\" cannot be satisfied by any value"
+        );
+    }
 
     #[test]
     pub fn type_check_error_display_tests() {
-        let error = TypeCheckError::RepeatedName("test".to_string());
+        let error = TypeCheckError::RepeatedName {
+            name: "test".to_string(),
+            span: Span::default(),
+        };
 
         assert_eq!(
             error.to_string(),
@@ -534,11 +3705,22 @@ mod tests {
         let error = TypeCheckError::RepeatedFieldName {
             field_name: "test1".to_string(),
             struct_name: "test2".to_string(),
+            span: Span::default(),
         };
 
         assert_eq!(
             error.to_string(),
             "A field with name \"test1\" already exists in struct \"test2\""
         );
+
+        let error = TypeCheckError::CyclicDefinition {
+            cycle: vec!["A".to_string(), "B".to_string(), "A".to_string()],
+            span: Span::default(),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "The definition of \"A\" is cyclic (A -> B -> A), use \"?\" or \"[]\" to break the cycle"
+        );
     }
 }